@@ -0,0 +1 @@
+drop table slack_request;
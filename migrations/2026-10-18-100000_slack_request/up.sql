@@ -0,0 +1,12 @@
+create table slack_request
+(
+    request_key   varchar not null
+        constraint slack_request_pkey primary key,
+    status_code   integer,
+    content_type  varchar,
+    response_body text,
+    created       timestamp with time zone not null default (now() at time zone 'utc'),
+    completed     timestamp with time zone
+);
+
+create index slack_request_created_idx on slack_request (created);
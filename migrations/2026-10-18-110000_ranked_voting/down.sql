@@ -0,0 +1,9 @@
+alter table song_vote
+    drop column rank;
+
+alter table song
+    drop constraint song_competition_number_key,
+    drop column number;
+
+alter table competition
+    drop column voting_method;
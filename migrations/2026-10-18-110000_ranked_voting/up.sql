@@ -0,0 +1,20 @@
+alter table competition
+    add column voting_method varchar not null default 'plurality';
+
+-- Songs are numbered per competition so they can be referred to in votes
+alter table song
+    add column number integer;
+
+update song
+set number = numbered.number
+from (select id, row_number() over (partition by competition_id order by id) as number
+      from song) numbered
+where song.id = numbered.id;
+
+alter table song
+    alter column number set not null,
+    add constraint song_competition_number_key unique (competition_id, number);
+
+-- Position of the song on the voter's ballot, 1 is the first preference
+alter table song_vote
+    add column rank integer not null default 1;
@@ -0,0 +1,5 @@
+alter table song_vote
+    drop column score;
+
+alter table competition
+    drop column points_budget;
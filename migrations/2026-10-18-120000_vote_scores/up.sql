@@ -0,0 +1,7 @@
+-- Points a user may spread over the songs when voting with a budget
+alter table competition
+    add column points_budget integer not null default 10;
+
+-- Score given to the song, 1 for single and ranked votes
+alter table song_vote
+    add column score integer not null default 1;
@@ -0,0 +1,9 @@
+alter table song_vote
+    drop column runoff;
+
+alter table competition
+    drop column tie_break_seed,
+    drop column tie_break_used,
+    drop column winner_song_id,
+    drop column runoff,
+    drop column tie_break;
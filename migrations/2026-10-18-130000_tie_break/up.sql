@@ -0,0 +1,11 @@
+alter table competition
+    add column tie_break      varchar not null default 'earliest',
+    -- Set while the tied songs of a competition are in a runoff vote
+    add column runoff         boolean not null default false,
+    add column winner_song_id uuid references song (id),
+    -- How a tie for the win was broken, shown with the results
+    add column tie_break_used varchar,
+    add column tie_break_seed bigint;
+
+alter table song_vote
+    add column runoff boolean not null default false;
@@ -0,0 +1,3 @@
+alter table competition
+    drop column rules,
+    drop column submission_deadline;
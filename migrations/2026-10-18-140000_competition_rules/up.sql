@@ -0,0 +1,5 @@
+alter table competition
+    -- Rules for taking part, as json. Rules left out take their default
+    add column rules               text not null default '{}',
+    -- Songs submitted after the deadline are late
+    add column submission_deadline timestamptz;
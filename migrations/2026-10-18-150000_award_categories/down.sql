@@ -0,0 +1,5 @@
+alter table song_vote
+    drop column category;
+
+alter table competition
+    drop column categories;
@@ -0,0 +1,7 @@
+alter table competition
+    -- Named awards voted on besides the overall winner
+    add column categories text[] not null default '{}';
+
+alter table song_vote
+    -- The award a vote is for, null for the overall ballot
+    add column category varchar;
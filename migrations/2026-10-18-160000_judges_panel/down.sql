@@ -0,0 +1,8 @@
+alter table song_vote
+    drop column criterion,
+    drop column panel;
+
+alter table competition
+    drop column rubric,
+    drop column panel_weight,
+    drop column judges;
@@ -0,0 +1,13 @@
+alter table competition
+    -- Slack users judging the songs alongside the audience
+    add column judges text[] not null default '{}',
+    -- Percentage of the result decided by the judges
+    add column panel_weight integer not null default 50,
+    -- Criteria the judges score each song on, empty when judges vote like the audience
+    add column rubric text[] not null default '{}';
+
+alter table song_vote
+    -- Cast by a judge, counted for the panel instead of the audience
+    add column panel boolean not null default false,
+    -- The rubric criterion a judge's score is for
+    add column criterion varchar;
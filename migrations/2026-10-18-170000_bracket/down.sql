@@ -0,0 +1,8 @@
+alter table song_vote
+    drop column bracket_match_id;
+
+drop table bracket_match;
+
+alter table competition
+    drop column match_minutes,
+    drop column kind;
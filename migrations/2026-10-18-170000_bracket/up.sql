@@ -0,0 +1,28 @@
+alter table competition
+    -- An open competition, or a bracket of head-to-head matches between past winners
+    add column kind          varchar not null default 'open',
+    -- How long every match of a bracket is open for votes
+    add column match_minutes integer;
+
+create table bracket_match
+(
+    id             uuid    not null
+        constraint bracket_match_pkey primary key
+        default uuid_generate_v4(),
+    competition_id uuid    not null references competition (id),
+    round          integer not null,
+    slot           integer not null,
+    home_song_id   uuid    not null references song (id),
+    -- Null when the home song has a bye
+    away_song_id   uuid references song (id),
+    deadline       timestamp with time zone not null,
+    closed         timestamp with time zone,
+    winner_song_id uuid references song (id),
+    home_votes     integer not null default 0,
+    away_votes     integer not null default 0,
+    unique (competition_id, round, slot)
+);
+
+alter table song_vote
+    -- The bracket match a vote is cast in
+    add column bracket_match_id uuid references bracket_match (id);
@@ -0,0 +1,7 @@
+alter table song
+    drop column place;
+
+alter table competition
+    drop column season_id;
+
+drop table season;
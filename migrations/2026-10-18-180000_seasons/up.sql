@@ -0,0 +1,22 @@
+create table season
+(
+    id           uuid      not null
+        constraint season_pkey primary key
+        default uuid_generate_v4(),
+    name         varchar   not null,
+    user_id      varchar   not null,
+    started      timestamp with time zone not null default (now() at time zone 'utc'),
+    ended        timestamp with time zone,
+    is_active    boolean   not null default true,
+    -- The season ends when this many of its competitions are closed
+    competitions integer   not null,
+    -- Points for first place, second place and so on
+    points       integer[] not null
+);
+
+alter table competition
+    add column season_id uuid references season (id);
+
+alter table song
+    -- Final placing in a closed competition, songs sharing a place have the same
+    add column place integer;
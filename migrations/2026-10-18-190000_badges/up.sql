@@ -0,0 +1,13 @@
+create table badge
+(
+    id             uuid     not null
+        constraint badge_pkey primary key
+        default uuid_generate_v4(),
+    user_id        varchar  not null,
+    -- Key of the achievement in the rule table of the bot
+    achievement    varchar  not null,
+    -- The competition whose close earned the badge
+    competition_id uuid     not null references competition (id),
+    awarded        timestamp with time zone not null default (now() at time zone 'utc'),
+    constraint badge_user_achievement unique (user_id, achievement)
+);
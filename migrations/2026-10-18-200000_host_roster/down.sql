@@ -0,0 +1,4 @@
+drop table host_roster;
+
+alter table competition
+    drop column channel_id;
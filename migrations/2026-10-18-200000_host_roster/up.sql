@@ -0,0 +1,21 @@
+alter table competition
+    -- Channel the competition was started in, its host roster nominates the next host
+    add column channel_id varchar;
+
+create table host_roster
+(
+    id         uuid    not null
+        constraint host_roster_pkey primary key
+        default uuid_generate_v4(),
+    channel_id varchar not null,
+    user_id    varchar not null,
+    -- Members host in turn, from the lowest position
+    position   integer not null,
+    -- Set for the member whose turn it is to host
+    nominated  timestamp with time zone,
+    joined     timestamp with time zone not null default (now() at time zone 'utc'),
+    constraint host_roster_member unique (channel_id, user_id)
+);
+
+-- One nominated host per channel
+create unique index host_roster_nominee on host_roster (channel_id) where nominated is not null;
@@ -0,0 +1,19 @@
+create table user_role
+(
+    id             uuid    not null
+        constraint user_role_pkey primary key
+        default uuid_generate_v4(),
+    user_id        varchar not null,
+    -- bot_admin, channel_admin or co_host
+    role           varchar not null,
+    -- Channel a channel admin manages, unset for the other roles
+    channel_id     varchar,
+    -- Competition a co-host helps run, unset for the other roles
+    competition_id uuid references competition (id),
+    granted_by     varchar not null,
+    granted        timestamp with time zone not null default (now() at time zone 'utc')
+);
+
+-- A role is granted once for the same channel or competition
+create unique index user_role_grant on user_role (user_id, role, coalesce(channel_id, ''),
+                                                  coalesce(competition_id::text, ''));
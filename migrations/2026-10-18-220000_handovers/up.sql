@@ -0,0 +1,13 @@
+create table handover
+(
+    id             uuid    not null
+        constraint handover_pkey primary key
+        default uuid_generate_v4(),
+    competition_id uuid    not null references competition (id),
+    -- Host of the competition before and after the handover
+    from_user_id   varchar not null,
+    to_user_id     varchar not null,
+    -- Who handed the competition over, the host or someone running it with them
+    user_id        varchar not null,
+    handed_over    timestamp with time zone not null default (now() at time zone 'utc')
+);
@@ -0,0 +1,4 @@
+drop table ban;
+
+alter table song
+    drop column disqualified;
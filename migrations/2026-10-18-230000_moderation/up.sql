@@ -0,0 +1,17 @@
+alter table song
+    -- Disqualified songs stay listed but are left out of the tally
+    add column disqualified boolean not null default false;
+
+create table ban
+(
+    id         uuid    not null
+        constraint ban_pkey primary key
+        default uuid_generate_v4(),
+    user_id    varchar not null,
+    -- Channel the user may not submit songs or vote in
+    channel_id varchar not null,
+    reason     varchar,
+    banned_by  varchar not null,
+    banned     timestamp with time zone not null default (now() at time zone 'utc'),
+    constraint ban_user unique (channel_id, user_id)
+);
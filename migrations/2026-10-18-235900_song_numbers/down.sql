@@ -0,0 +1,2 @@
+alter table competition
+    drop column next_song_number;
@@ -0,0 +1,6 @@
+alter table competition
+    -- Number the next song submitted gets, numbers of removed songs are not handed out again
+    add column next_song_number integer not null default 1;
+
+update competition
+set next_song_number = coalesce((select max(number) from song where song.competition_id = competition.id), 0) + 1;
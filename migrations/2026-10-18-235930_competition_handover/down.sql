@@ -0,0 +1,3 @@
+alter table competition
+    drop column previous_user_id,
+    drop column handed_over;
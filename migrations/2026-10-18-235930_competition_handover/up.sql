@@ -0,0 +1,4 @@
+alter table competition
+    -- Host before the last handover and when it happened, the handover table keeps the full history
+    add column previous_user_id varchar,
+    add column handed_over      timestamp with time zone;
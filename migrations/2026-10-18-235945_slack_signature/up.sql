@@ -0,0 +1,11 @@
+-- Signatures of accepted Slack requests, a request sent again with the same signature is a replay.
+-- Kept in the database so every server instance rejects it, not only the one that saw it first.
+create table slack_signature
+(
+    signature         varchar not null
+        constraint slack_signature_pkey primary key,
+    -- X-Slack-Request-Timestamp of the request, signatures are dropped once it is out of the window
+    request_timestamp bigint  not null
+);
+
+create index slack_signature_request_timestamp_idx on slack_signature (request_timestamp);
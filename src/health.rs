@@ -0,0 +1,95 @@
+use crate::metrics::{observe_pool, render};
+use crate::sotw_db::database::ping;
+use crate::sotw_db::errors::{BotError, DataError};
+use crate::DbPool;
+use actix_web::{web, HttpResponse};
+use std::time::Duration;
+
+// Probes should answer quickly, rather than waiting the full pool timeout
+static READINESS_POOL_TIMEOUT_SECS: u64 = 2;
+
+/// Liveness: the process is up and serving requests
+pub async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().body("ok")
+}
+
+/// Readiness: a connection can be checked out of the pool and run a query
+pub async fn readyz(db_pool: web::Data<DbPool>) -> HttpResponse {
+    let result = web::block(move || {
+        observe_pool(&db_pool);
+
+        let connection = db_pool
+            .get_timeout(Duration::from_secs(READINESS_POOL_TIMEOUT_SECS))
+            .map_err(|e| BotError {
+                data_error: DataError::DieselError(e.to_string()),
+                message: "Unable to get a connection from the pool".to_string(),
+            })?;
+
+        ping(&connection)
+    })
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().body("ok"),
+        Err(e) => {
+            warn!("Readiness check failed err={}", e);
+            HttpResponse::ServiceUnavailable().body("database unavailable")
+        }
+    }
+}
+
+pub async fn metrics(db_pool: web::Data<DbPool>) -> HttpResponse {
+    observe_pool(&db_pool);
+
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(render())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::DatabaseConfig;
+    use crate::create_db_pool;
+    use crate::health::{healthz, metrics, readyz};
+    use actix_web::{test, web, App};
+
+    fn test_db_pool() -> crate::DbPool {
+        dotenv::dotenv().ok();
+
+        create_db_pool(&DatabaseConfig {
+            url: std::env::var("DATABASE_URL").expect("Database connection string missing!"),
+            pool_max_size: 1,
+            pool_min_idle: None,
+            connection_timeout_secs: 5,
+        })
+    }
+
+    #[actix_rt::test]
+    async fn test_probes() {
+        let mut app = test::init_service(
+            App::new()
+                .data(test_db_pool())
+                .route("/healthz", web::get().to(healthz))
+                .route("/readyz", web::get().to(readyz))
+                .route("/metrics", web::get().to(metrics)),
+        )
+        .await;
+
+        for uri in &["/healthz", "/readyz", "/metrics"] {
+            let request = test::TestRequest::get().uri(uri).to_request();
+            let response = test::call_service(&mut app, request).await;
+
+            assert!(response.status().is_success(), "{} should be ok", uri);
+        }
+
+        let request = test::TestRequest::get().uri("/metrics").to_request();
+        let body = test::read_response(&mut app, request).await;
+
+        assert!(
+            String::from_utf8(body.to_vec())
+                .unwrap()
+                .contains("sotw_db_pool_max_size 1"),
+            "pool gauges should be sampled on scrape"
+        );
+    }
+}
@@ -1,32 +1,43 @@
 #[macro_use]
 extern crate diesel;
 #[macro_use]
-extern crate log;
+extern crate lazy_static;
+#[macro_use]
+extern crate tracing;
 
+use crate::config::{Config, DatabaseConfig};
+use crate::health::{healthz, metrics, readyz};
 use crate::slack::handler::handler;
 use actix_web::middleware::Logger;
 use actix_web::{web, App, HttpServer};
 use diesel::r2d2::ConnectionManager;
 use diesel::PgConnection;
 use dotenv::dotenv;
-use env_logger::Env;
 use r2d2::Pool;
 use reqwest::Client;
+use std::time::Duration;
 
+mod config;
+mod health;
+mod metrics;
+mod scheduler;
 mod schema;
 mod slack;
 mod sotw_db;
+mod telemetry;
 
 pub type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 pub type SlackSecret = String;
+pub type SlackSecrets = Vec<SlackSecret>;
 
-/// Create a DB connection pool using simple defaults
-pub fn create_db_pool() -> Pool<ConnectionManager<PgConnection>> {
-    let connection = std::env::var("DATABASE_URL").expect("Database connection string missing!");
-    let manager = ConnectionManager::<PgConnection>::new(connection);
+/// Create a DB connection pool sized by the database config
+pub fn create_db_pool(database: &DatabaseConfig) -> Pool<ConnectionManager<PgConnection>> {
+    let manager = ConnectionManager::<PgConnection>::new(database.url.clone());
 
     r2d2::Pool::builder()
-        .max_size(2)
+        .max_size(database.pool_max_size)
+        .min_idle(database.pool_min_idle)
+        .connection_timeout(Duration::from_secs(database.connection_timeout_secs))
         .build(manager)
         .expect("Failed creating connection pool!")
 }
@@ -35,27 +46,61 @@ pub fn create_db_pool() -> Pool<ConnectionManager<PgConnection>> {
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
 
-    let log_level = std::env::var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string());
-    env_logger::from_env(Env::default().default_filter_or(log_level)).init();
+    let config: Config = config::load().map_err(|errors| {
+        eprintln!("{}", errors);
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid configuration")
+    })?;
+
+    let otlp_handle = telemetry::init(&config.log_level, &config.telemetry);
 
-    let slack_secret: SlackSecret =
-        std::env::var("SLACK_SIGNING_SECRET").expect("Missing slack secret!");
+    let slack_secrets: SlackSecrets = config.slack.signing_secrets.clone();
 
-    let db_pool = create_db_pool();
+    let db_pool = create_db_pool(&config.database);
 
     let http_client = Client::builder()
+        .timeout(Duration::from_secs(config.slack.response_timeout_secs))
         .build()
         .expect("Unable to create reqwest client for communicating with slack api!");
 
-    HttpServer::new(move || {
+    actix_rt::spawn(scheduler::run(
+        db_pool.clone(),
+        http_client.clone(),
+        config.clone(),
+    ));
+
+    let bind_address = config.server.bind_address();
+    let server_config = config.server.clone();
+
+    let mut server = HttpServer::new(move || {
         App::new()
             .wrap(Logger::default())
             .data(db_pool.clone())
             .data(http_client.clone())
-            .data(slack_secret.clone())
+            .data(slack_secrets.clone())
+            .data(config.clone())
             .route("/", web::post().to(handler))
+            .route("/healthz", web::get().to(healthz))
+            .route("/readyz", web::get().to(readyz))
+            .configure(|cfg| {
+                if config.features.metrics {
+                    cfg.route("/metrics", web::get().to(metrics));
+                }
+            })
     })
-    .bind("127.0.0.1:9000")?
-    .run()
-    .await
+    .client_timeout(server_config.request_timeout_secs * 1000)
+    .shutdown_timeout(server_config.shutdown_timeout_secs);
+
+    if let Some(workers) = server_config.workers {
+        server = server.workers(workers);
+    }
+
+    info!("Starting server on {}", bind_address);
+
+    let result = server.bind(bind_address)?.run().await;
+
+    if let Some(otlp_handle) = otlp_handle {
+        otlp_handle.flush();
+    }
+
+    result
 }
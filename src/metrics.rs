@@ -0,0 +1,133 @@
+use crate::DbPool;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+
+// Prometheus metrics for the bot.
+// Everything is registered in a single registry which is rendered by `/metrics`.
+
+lazy_static! {
+    pub static ref REGISTRY: Registry = Registry::new();
+    pub static ref COMMANDS_TOTAL: IntCounterVec = register(
+        IntCounterVec::new(
+            Opts::new("sotw_commands_total", "Received commands per sub command"),
+            &["command"],
+        )
+        .unwrap()
+    );
+    pub static ref HANDLER_DURATION: HistogramVec = register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "sotw_handler_duration_seconds",
+                "Time spent handling a command, including database and Slack calls",
+            ),
+            &["command"],
+        )
+        .unwrap()
+    );
+    pub static ref SIGNATURE_FAILURES_TOTAL: IntCounter = register(
+        IntCounter::new(
+            "sotw_signature_verification_failures_total",
+            "Requests rejected because the Slack signature did not verify",
+        )
+        .unwrap()
+    );
+    pub static ref REPLAYED_REQUESTS_TOTAL: IntCounter = register(
+        IntCounter::new(
+            "sotw_replayed_requests_total",
+            "Requests rejected because their signature was already used",
+        )
+        .unwrap()
+    );
+    pub static ref DEDUPLICATED_REQUESTS_TOTAL: IntCounter = register(
+        IntCounter::new(
+            "sotw_deduplicated_requests_total",
+            "Retried requests answered without running the command again",
+        )
+        .unwrap()
+    );
+    pub static ref SLACK_DELIVERY_FAILURES_TOTAL: IntCounter = register(
+        IntCounter::new(
+            "sotw_slack_delivery_failures_total",
+            "Responses that could not be delivered to a Slack response_url",
+        )
+        .unwrap()
+    );
+    pub static ref DROPPED_SPANS_TOTAL: IntCounter = register(
+        IntCounter::new(
+            "sotw_otlp_dropped_spans_total",
+            "Spans dropped because the queue to the OTLP collector was full",
+        )
+        .unwrap()
+    );
+    pub static ref DB_POOL_MAX_SIZE: IntGauge = register(
+        IntGauge::new(
+            "sotw_db_pool_max_size",
+            "Maximum number of connections in the database pool"
+        )
+        .unwrap()
+    );
+    pub static ref DB_POOL_IN_USE: IntGauge = register(
+        IntGauge::new(
+            "sotw_db_pool_in_use_connections",
+            "Database connections currently checked out of the pool",
+        )
+        .unwrap()
+    );
+    pub static ref DB_POOL_IDLE: IntGauge = register(
+        IntGauge::new(
+            "sotw_db_pool_idle_connections",
+            "Idle database connections in the pool",
+        )
+        .unwrap()
+    );
+}
+
+fn register<T>(collector: T) -> T
+where
+    T: prometheus::core::Collector + Clone + 'static,
+{
+    REGISTRY
+        .register(Box::new(collector.clone()))
+        .expect("Metric registered twice!");
+    collector
+}
+
+/// Refresh the pool gauges, they are sampled rather than counted
+pub fn observe_pool(db_pool: &DbPool) {
+    let state = db_pool.state();
+
+    DB_POOL_MAX_SIZE.set(db_pool.max_size() as i64);
+    DB_POOL_IDLE.set(state.idle_connections as i64);
+    DB_POOL_IN_USE.set((state.connections - state.idle_connections) as i64);
+}
+
+/// Render all registered metrics in the Prometheus text format
+pub fn render() -> String {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&REGISTRY.gather(), &mut buffer)
+        .expect("Unable to encode metrics!");
+
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::{render, COMMANDS_TOTAL, SIGNATURE_FAILURES_TOTAL};
+
+    #[test]
+    fn test_render() {
+        COMMANDS_TOTAL.with_label_values(&["list"]).inc();
+        SIGNATURE_FAILURES_TOTAL.inc();
+
+        let output = render();
+
+        assert!(
+            output.contains("sotw_commands_total{command=\"list\"}"),
+            "command counter should be labeled with the sub command"
+        );
+        assert!(output.contains("sotw_signature_verification_failures_total"));
+    }
+}
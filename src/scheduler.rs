@@ -0,0 +1,46 @@
+use crate::config::Config;
+use crate::slack::bracket::format_advance;
+use crate::slack::response::in_channel_response;
+use crate::sotw_db::database::advance_bracket;
+use crate::sotw_db::errors::{BotError, DataError};
+use crate::DbPool;
+use actix_web::web;
+use reqwest::Client;
+use std::time::Duration;
+
+/// Advance the active bracket past its match deadlines, until the server stops.
+/// Slash command responses can't be sent this late, so every advance is posted to
+/// the Slack webhook when one is configured, and shown with `/sotw bracket` otherwise.
+pub async fn run(db_pool: DbPool, http_client: Client, config: Config) {
+    let mut interval =
+        actix_rt::time::interval(Duration::from_secs(config.server.scheduler_interval_secs));
+
+    loop {
+        interval.tick().await;
+
+        let pool = db_pool.clone();
+        let advanced = web::block(move || {
+            let connection = pool.get().map_err(|e| BotError {
+                data_error: DataError::DieselError(e.to_string()),
+                message: "Unable to get a database connection".to_string(),
+            })?;
+            advance_bracket(chrono::Utc::now(), &connection)
+        })
+        .await;
+
+        match advanced {
+            Ok(Some(advance)) => {
+                if let Some(webhook_url) = &config.slack.webhook_url {
+                    in_channel_response(
+                        webhook_url.clone(),
+                        format_advance(&advance),
+                        &http_client,
+                    )
+                    .await;
+                }
+            }
+            Ok(None) => {}
+            Err(e) => warn!(error = %e, "Unable to advance bracket"),
+        }
+    }
+}
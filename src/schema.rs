@@ -7,6 +7,95 @@ pub mod sotw {
             started -> Timestamptz,
             ended -> Nullable<Timestamptz>,
             is_active -> Bool,
+            voting_method -> Varchar,
+            points_budget -> Int4,
+            tie_break -> Varchar,
+            runoff -> Bool,
+            winner_song_id -> Nullable<Uuid>,
+            tie_break_used -> Nullable<Varchar>,
+            tie_break_seed -> Nullable<Int8>,
+            rules -> Text,
+            submission_deadline -> Nullable<Timestamptz>,
+            categories -> Array<Text>,
+            judges -> Array<Text>,
+            panel_weight -> Int4,
+            rubric -> Array<Text>,
+            kind -> Varchar,
+            match_minutes -> Nullable<Int4>,
+            season_id -> Nullable<Uuid>,
+            channel_id -> Nullable<Varchar>,
+            next_song_number -> Int4,
+            previous_user_id -> Nullable<Varchar>,
+            handed_over -> Nullable<Timestamptz>,
+        }
+    }
+
+    table! {
+        sotw.handover (id) {
+            id -> Uuid,
+            competition_id -> Uuid,
+            from_user_id -> Varchar,
+            to_user_id -> Varchar,
+            user_id -> Varchar,
+            handed_over -> Timestamptz,
+        }
+    }
+
+    table! {
+        sotw.host_roster (id) {
+            id -> Uuid,
+            channel_id -> Varchar,
+            user_id -> Varchar,
+            position -> Int4,
+            nominated -> Nullable<Timestamptz>,
+            joined -> Timestamptz,
+        }
+    }
+
+    table! {
+        sotw.season (id) {
+            id -> Uuid,
+            name -> Varchar,
+            user_id -> Varchar,
+            started -> Timestamptz,
+            ended -> Nullable<Timestamptz>,
+            is_active -> Bool,
+            competitions -> Int4,
+            points -> Array<Int4>,
+        }
+    }
+
+    table! {
+        sotw.bracket_match (id) {
+            id -> Uuid,
+            competition_id -> Uuid,
+            round -> Int4,
+            slot -> Int4,
+            home_song_id -> Uuid,
+            away_song_id -> Nullable<Uuid>,
+            deadline -> Timestamptz,
+            closed -> Nullable<Timestamptz>,
+            winner_song_id -> Nullable<Uuid>,
+            home_votes -> Int4,
+            away_votes -> Int4,
+        }
+    }
+
+    table! {
+        sotw.slack_request (request_key) {
+            request_key -> Varchar,
+            status_code -> Nullable<Int4>,
+            content_type -> Nullable<Varchar>,
+            response_body -> Nullable<Text>,
+            created -> Timestamptz,
+            completed -> Nullable<Timestamptz>,
+        }
+    }
+
+    table! {
+        sotw.slack_signature (signature) {
+            signature -> Varchar,
+            request_timestamp -> Int8,
         }
     }
 
@@ -16,6 +105,9 @@ pub mod sotw {
             user_id -> Varchar,
             song_uri -> Varchar,
             competition_id -> Uuid,
+            number -> Int4,
+            place -> Nullable<Int4>,
+            disqualified -> Bool,
         }
     }
 
@@ -24,11 +116,69 @@ pub mod sotw {
             id -> Uuid,
             user_id -> Varchar,
             song_id -> Uuid,
+            rank -> Int4,
+            score -> Int4,
+            runoff -> Bool,
+            category -> Nullable<Varchar>,
+            panel -> Bool,
+            criterion -> Nullable<Varchar>,
+            bracket_match_id -> Nullable<Uuid>,
+        }
+    }
+
+    table! {
+        sotw.badge (id) {
+            id -> Uuid,
+            user_id -> Varchar,
+            achievement -> Varchar,
+            competition_id -> Uuid,
+            awarded -> Timestamptz,
+        }
+    }
+
+    table! {
+        sotw.user_role (id) {
+            id -> Uuid,
+            user_id -> Varchar,
+            role -> Varchar,
+            channel_id -> Nullable<Varchar>,
+            competition_id -> Nullable<Uuid>,
+            granted_by -> Varchar,
+            granted -> Timestamptz,
+        }
+    }
+
+    table! {
+        sotw.ban (id) {
+            id -> Uuid,
+            user_id -> Varchar,
+            channel_id -> Varchar,
+            reason -> Nullable<Varchar>,
+            banned_by -> Varchar,
+            banned -> Timestamptz,
         }
     }
 
+    joinable!(badge -> competition (competition_id));
+    joinable!(bracket_match -> competition (competition_id));
+    joinable!(competition -> season (season_id));
+    joinable!(handover -> competition (competition_id));
     joinable!(song -> competition (competition_id));
     joinable!(song_vote -> song (song_id));
+    joinable!(user_role -> competition (competition_id));
 
-    allow_tables_to_appear_in_same_query!(competition, song, song_vote,);
+    allow_tables_to_appear_in_same_query!(
+        badge,
+        ban,
+        bracket_match,
+        competition,
+        handover,
+        host_roster,
+        season,
+        slack_request,
+        slack_signature,
+        song,
+        song_vote,
+        user_role,
+    );
 }
@@ -0,0 +1,118 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+
+// Calls to the Slack Web API, authorised with the bot token
+
+#[derive(Deserialize, Debug)]
+struct UsersInfo {
+    ok: bool,
+    user: Option<UserInfo>,
+}
+
+#[derive(Deserialize, Debug)]
+struct UserInfo {
+    #[serde(default)]
+    is_admin: bool,
+    #[serde(default)]
+    is_owner: bool,
+}
+
+/// Whether the user is an admin or owner of the workspace, as told by
+/// `users.info`. A failed lookup is logged and counts as not an admin.
+pub async fn is_workspace_admin(
+    api_url: &str,
+    bot_token: &str,
+    user_id: &str,
+    http_client: &Client,
+) -> bool {
+    let span = info_span!("slack_api", otel.kind = "client", method = "users.info");
+
+    let result = async {
+        http_client
+            .get(&format!("{}/users.info", api_url.trim_end_matches('/')))
+            .bearer_auth(bot_token)
+            .query(&[("user", user_id)])
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<UsersInfo>()
+            .await
+    }
+    .instrument(span)
+    .await;
+
+    match result {
+        Ok(UsersInfo {
+            ok: true,
+            user: Some(user),
+        }) => user.is_admin || user.is_owner,
+        Ok(_) => {
+            warn!("Slack refused to look up a user");
+            false
+        }
+        Err(e) => {
+            warn!(error = %e, "Unable to look up a user in Slack");
+            false
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct PostMessage<'a> {
+    channel: &'a str,
+    text: &'a str,
+}
+
+#[derive(Deserialize, Debug)]
+struct PostMessageResult {
+    ok: bool,
+    error: Option<String>,
+}
+
+/// Send a direct message to a user with `chat.postMessage`, giving the user as
+/// the channel. A failed message is logged, it never fails the command.
+pub async fn send_direct_message(
+    api_url: &str,
+    bot_token: &str,
+    user_id: &str,
+    text: &str,
+    http_client: &Client,
+) {
+    let span = info_span!(
+        "slack_api",
+        otel.kind = "client",
+        method = "chat.postMessage"
+    );
+
+    let result = async {
+        http_client
+            .post(&format!(
+                "{}/chat.postMessage",
+                api_url.trim_end_matches('/')
+            ))
+            .bearer_auth(bot_token)
+            .json(&PostMessage {
+                channel: user_id,
+                text,
+            })
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<PostMessageResult>()
+            .await
+    }
+    .instrument(span)
+    .await;
+
+    match result {
+        Ok(PostMessageResult { ok: true, .. }) => {}
+        Ok(PostMessageResult { error, .. }) => {
+            warn!(
+                error = error.as_deref().unwrap_or(""),
+                "Slack refused to send a message"
+            )
+        }
+        Err(e) => warn!(error = %e, "Unable to send a message in Slack"),
+    }
+}
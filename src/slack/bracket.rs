@@ -0,0 +1,184 @@
+use crate::slack::results::format_song;
+use crate::sotw_db::bracket::rounds;
+use crate::sotw_db::database::{Bracket, BracketAdvance};
+use crate::sotw_db::model::BracketMatch;
+
+/// Text view of a bracket, round by round. Decided matches show the votes and
+/// the song going through, open matches show when voting closes.
+pub fn format_bracket(bracket: &Bracket) -> String {
+    let songs = &bracket.songs;
+    let total_rounds = rounds(songs.len()) as i32;
+    let mut lines = vec![format!("*Bracket:* {}", bracket.competition.description)];
+
+    for round in 1..=total_rounds {
+        lines.push(if round == total_rounds {
+            "*Final*".to_string()
+        } else {
+            format!("*Round {}*", round)
+        });
+
+        let matches: Vec<&BracketMatch> = bracket
+            .matches
+            .iter()
+            .filter(|bracket_match| bracket_match.round == round)
+            .collect();
+        if matches.is_empty() {
+            lines.push(format!("Waiting for round {}", round - 1));
+        }
+        for bracket_match in matches {
+            let home = format_song(songs, &bracket_match.home_song_id);
+            let line = match (bracket_match.away_song_id, bracket_match.winner_song_id) {
+                (None, _) => format!("{} - bye", home),
+                (Some(away), Some(winner)) => format!(
+                    "{} ({}) vs {} ({}) - #{} goes through",
+                    home,
+                    bracket_match.home_votes,
+                    format_song(songs, &away),
+                    bracket_match.away_votes,
+                    song_number(bracket, winner)
+                ),
+                (Some(away), None) => format!(
+                    "{} vs {} - voting until {}",
+                    home,
+                    format_song(songs, &away),
+                    bracket_match.deadline.format("%Y-%m-%d %H:%M UTC")
+                ),
+            };
+            lines.push(line);
+        }
+    }
+
+    if let Some(champion) = bracket.competition.winner_song_id {
+        lines.push(format!("*Champion:* {}", format_song(songs, &champion)));
+    }
+
+    lines.join("\n")
+}
+
+/// Announcement for a bracket which advanced past a match deadline
+pub fn format_advance(advance: &BracketAdvance) -> String {
+    let competition = &advance.bracket.competition;
+    let header = match (competition.is_active, advance.opened.first()) {
+        (false, _) => format!("The bracket *{}* is decided", competition.description),
+        (true, Some(opened)) if opened.round as usize == rounds(advance.bracket.songs.len()) => {
+            format!(
+                "The final of *{}* is open, vote with `/sotw vote <song>`",
+                competition.description
+            )
+        }
+        (true, Some(opened)) => format!(
+            "Round {} of *{}* is open, vote with `/sotw vote <song>` in every match",
+            opened.round, competition.description
+        ),
+        (true, None) => format!(
+            "{} matches of *{}* are decided",
+            advance.closed.len(),
+            competition.description
+        ),
+    };
+
+    format!("{}\n{}", header, format_bracket(&advance.bracket))
+}
+
+/// A duration in whole minutes, in the largest unit which fits, like `1d` or `90m`
+pub fn format_minutes(minutes: i32) -> String {
+    if minutes % (24 * 60) == 0 {
+        format!("{}d", minutes / (24 * 60))
+    } else if minutes % 60 == 0 {
+        format!("{}h", minutes / 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+fn song_number(bracket: &Bracket, song_id: uuid::Uuid) -> i32 {
+    bracket
+        .songs
+        .iter()
+        .find(|song| song.id == song_id)
+        .map_or(0, |song| song.number)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::slack::bracket::{format_bracket, format_minutes};
+    use crate::sotw_db::database::Bracket;
+    use crate::sotw_db::model::{BracketMatch, Competition, CompetitionKind, Song};
+    use chrono::{DateTime, Utc};
+    use uuid::Uuid;
+
+    fn bracket_match(
+        round: i32,
+        slot: i32,
+        home: &Song,
+        away: Option<&Song>,
+        votes: Option<(i32, i32)>,
+    ) -> BracketMatch {
+        let deadline: DateTime<Utc> = "2026-10-19T18:00:00Z".parse().unwrap();
+        let (home_votes, away_votes) = votes.unwrap_or((0, 0));
+        let winner = match (away, votes) {
+            (None, _) => Some(home.id),
+            (Some(away), Some(_)) if away_votes > home_votes => Some(away.id),
+            (Some(_), Some(_)) => Some(home.id),
+            (Some(_), None) => None,
+        };
+        BracketMatch {
+            id: Uuid::new_v4(),
+            competition_id: home.competition_id,
+            round,
+            slot,
+            home_song_id: home.id,
+            away_song_id: away.map(|away| away.id),
+            deadline,
+            closed: winner.map(|_| deadline),
+            winner_song_id: winner,
+            home_votes,
+            away_votes,
+        }
+    }
+
+    #[test]
+    fn test_format_bracket() {
+        let competition = Competition {
+            description: "Finals".to_string(),
+            kind: CompetitionKind::Bracket,
+            match_minutes: Some(24 * 60),
+            ..Competition::for_test("UHOST")
+        };
+        let songs: Vec<Song> = (1..=3)
+            .map(|number| Song {
+                id: Uuid::new_v4(),
+                user_id: format!("U{}", number),
+                song_uri: format!("http://example.org/{}", number),
+                competition_id: competition.id,
+                number,
+                place: None,
+                disqualified: false,
+            })
+            .collect();
+        let matches = vec![
+            bracket_match(1, 1, &songs[0], None, None),
+            bracket_match(1, 2, &songs[1], Some(&songs[2]), Some((1, 2))),
+            bracket_match(2, 1, &songs[0], Some(&songs[2]), None),
+        ];
+
+        let text = format_bracket(&Bracket {
+            competition,
+            songs,
+            matches,
+        });
+
+        assert_eq!(
+            text,
+            "*Bracket:* Finals\n\
+            *Round 1*\n\
+            #1 http://example.org/1 by <@U1> - bye\n\
+            #2 http://example.org/2 by <@U2> (1) vs #3 http://example.org/3 by <@U3> (2) - #3 goes through\n\
+            *Final*\n\
+            #1 http://example.org/1 by <@U1> vs #3 http://example.org/3 by <@U3> - voting until 2026-10-19 18:00 UTC"
+        );
+        assert_eq!(format_minutes(24 * 60), "1d");
+        assert_eq!(format_minutes(90), "90m");
+        assert_eq!(format_minutes(120), "2h");
+    }
+}
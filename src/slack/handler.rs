@@ -1,55 +1,225 @@
-use crate::slack::model::{BotSubCommand, SlackRequestCommand};
-use crate::slack::response::in_channel_response;
+use crate::config::Config;
+use crate::metrics::{
+    COMMANDS_TOTAL, DEDUPLICATED_REQUESTS_TOTAL, HANDLER_DURATION, REPLAYED_REQUESTS_TOTAL,
+};
+use crate::slack::api::{is_workspace_admin, send_direct_message};
+use crate::slack::bracket::{format_bracket, format_minutes};
+use crate::slack::history::{format_history, format_past};
+use crate::slack::idempotency::{request_key, Outcome};
+use crate::slack::info::{format_info, format_panel};
+use crate::slack::model::{
+    parse_duration, parse_switch, parse_user_id, season_options, start_options, BanCommand,
+    BotSubCommand, HostCommand, RoleCommand, SlackRequestCommand,
+};
+use crate::slack::moderation::format_bans;
+use crate::slack::response::{announce_response, announce_with_attachments, ephemeral_response};
+use crate::slack::results::{format_results, pairwise_attachment};
+use crate::slack::roles::format_roles;
+use crate::slack::roster::format_roster;
+use crate::slack::season::{format_season_end, format_standings};
+use crate::slack::stats::{format_badges, format_stats};
 use crate::slack::verify_request::{validate_request_headers, validate_slack_signature};
 use crate::sotw_db::database::{
-    close_competition, list_songs_active_competition, save_competition, save_song, save_song_vote,
+    authorize, ban_user, claim_request, complete_request, disqualify_song, edit_description,
+    end_season, extend_deadline, find_active_bracket, find_active_competition,
+    find_past_competition, find_season_standings, grant_role, handover_competition, join_roster,
+    leave_roster, list_bans, list_past_competitions, list_roles, list_songs_active_competition,
+    load_roster, record_signature, release_request, remove_song, reopen_competition, revoke_role,
+    save_ballot, save_category_vote, save_competition, save_rubric_scores, save_song, skip_host,
+    start_bracket, start_season, stop_competition, unban_user, user_stats, Closing, Extension,
+    RequestClaim,
 };
-use crate::sotw_db::model::CompetitionInsert;
-use crate::{DbPool, SlackSecret};
+use crate::sotw_db::errors::{BotError, DataError};
+use crate::sotw_db::model::{
+    is_category_name, CompetitionInsert, CompetitionKind, CompetitionRef, PointsScheme,
+    SeasonInsert, SongRef, TieBreak, VoteChoice, VotingMethod, MAX_CATEGORY_LENGTH, MAX_SCORE,
+};
+use crate::sotw_db::permissions::{Action, Actor, Scope};
+use crate::{DbPool, SlackSecrets};
 use actix_rt::blocking::BlockingError;
+use actix_web::error::ErrorBadRequest;
 use actix_web::{web, Error, HttpRequest, HttpResponse, ResponseError};
+use diesel::PgConnection;
 use reqwest::Client;
-use uuid::Uuid;
+use tracing::field::{debug, display, Empty};
+use tracing::{Instrument, Span};
+
+// Past competitions listed per page of `/sotw history`
+static HISTORY_PAGE_SIZE: i64 = 10;
+
+/// Entry point for Slack commands.
+/// Everything done for the request, including database and Slack calls,
+/// happens inside one span carrying team, channel, user and command.
+pub async fn handler(
+    request: HttpRequest,
+    raw_body: web::Bytes,
+    db_pool: web::Data<DbPool>,
+    slack_secrets: web::Data<SlackSecrets>,
+    http_client: web::Data<Client>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let span = info_span!(
+        "slack_command",
+        otel.kind = "server",
+        team = Empty,
+        channel = Empty,
+        user = Empty,
+        command = Empty,
+        retry_num = Empty,
+        retry_reason = Empty,
+    );
+
+    handle_command(
+        request,
+        raw_body,
+        db_pool,
+        slack_secrets,
+        http_client,
+        config,
+    )
+    .instrument(span)
+    .await
+}
 
 /// Delegate to sub-handlers for the different bot commands
 /// Instead of calling web::Form<SlackRequestCommand> get web::Bytes
 /// directly in order to use the body in Slack signature verification.
-pub async fn handler(
+async fn handle_command(
     request: HttpRequest,
     raw_body: web::Bytes,
     db_pool: web::Data<DbPool>,
-    slack_secret: web::Data<SlackSecret>,
+    slack_secrets: web::Data<SlackSecrets>,
     http_client: web::Data<Client>,
+    config: web::Data<Config>,
 ) -> Result<HttpResponse, Error> {
     //Verify request
     let slack_validated_headers = validate_request_headers(request.headers())?;
     let body = String::from_utf8(raw_body.to_vec()).unwrap();
     validate_slack_signature(
-        slack_secret.as_ref(),
-        slack_validated_headers.request_signature,
+        slack_secrets.as_ref(),
+        slack_validated_headers.request_signature.clone(),
         body,
         slack_validated_headers.request_timestamp,
     )?;
+    // A captured request sent again is rejected before it can get at a stored outcome,
+    // signatures are kept in the database so this holds across server instances
+    let signature = slack_validated_headers.request_signature;
+    let timestamp = slack_validated_headers.request_timestamp;
+    let first_use = db_block("record_signature", db_pool.clone(), move |connection| {
+        record_signature(signature, timestamp, connection)
+    })
+    .await?;
+    if !first_use {
+        REPLAYED_REQUESTS_TOTAL.inc();
+        warn!(timestamp, "Rejected replayed request");
+        return Err(ErrorBadRequest("request has already been received"));
+    }
 
     let command: SlackRequestCommand = serde_urlencoded::from_bytes(&raw_body)?;
 
+    if command.command.as_deref() != Some(config.slack.command_prefix.as_str()) {
+        return handle_unimplemented().await;
+    }
+
+    let command_name = command.text.as_ref().map_or("unknown", |c| c.name());
+    let span = Span::current();
+    span.record("team", display(&command.team_id));
+    span.record("channel", display(&command.channel_id));
+    span.record("user", display(&command.user_id));
+    span.record("command", command_name);
+    if let Some(retry_num) = request.headers().get("X-Slack-Retry-Num") {
+        span.record("retry_num", debug(retry_num));
+    }
+    if let Some(retry_reason) = request.headers().get("X-Slack-Retry-Reason") {
+        span.record("retry_reason", debug(retry_reason));
+    }
+
+    // Slack signs its retries anew, they get the original outcome
+    let key = request_key(&command);
+    let claim_key = key.clone();
+    let claim = db_block("claim_request", db_pool.clone(), move |connection| {
+        claim_request(claim_key, connection)
+    })
+    .await?;
+
+    match claim {
+        RequestClaim::Completed(stored) => {
+            DEDUPLICATED_REQUESTS_TOTAL.inc();
+            info!("Answering repeated request with the stored outcome");
+            return Ok(Outcome::from_stored(stored).to_response());
+        }
+        RequestClaim::InProgress => {
+            DEDUPLICATED_REQUESTS_TOTAL.inc();
+            info!("Repeated request is still being handled");
+            return Ok(HttpResponse::Ok().finish());
+        }
+        RequestClaim::Claimed => {}
+    }
+
+    COMMANDS_TOTAL.with_label_values(&[command_name]).inc();
+    let _timer = HANDLER_DURATION
+        .with_label_values(&[command_name])
+        .start_timer();
+
+    let result = dispatch(&command, db_pool.clone(), http_client, config).await;
+    let outcome = Outcome::from_result(result);
+
+    if outcome.is_final() {
+        let status_code = i32::from(outcome.status_code.as_u16());
+        let content_type = outcome.content_type.clone();
+        let body = outcome.body.clone();
+        db_block("complete_request", db_pool, move |connection| {
+            complete_request(key, status_code, content_type, body, connection)
+        })
+        .await?;
+    } else {
+        db_block("release_request", db_pool, move |connection| {
+            release_request(key, connection)
+        })
+        .await?;
+    }
+
+    Ok(outcome.to_response())
+}
+
+async fn dispatch(
+    command: &SlackRequestCommand,
+    db_pool: web::Data<DbPool>,
+    http_client: web::Data<Client>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
     match &command.text {
         Some(sub_command) => match sub_command {
             BotSubCommand::Start(description) => {
-                handle_start(description.clone(), &command, db_pool, http_client).await
+                handle_start(description.clone(), command, db_pool, http_client, config).await
+            }
+            BotSubCommand::Stop(casting_vote) => {
+                handle_stop(*casting_vote, command, db_pool, http_client, config).await
+            }
+            BotSubCommand::Vote(Some(category), choices) => {
+                handle_category_vote(
+                    category.clone(),
+                    choices.clone(),
+                    command.user_id.clone(),
+                    command.response_url.clone(),
+                    db_pool,
+                    http_client,
+                    config,
+                )
+                .await
             }
-            BotSubCommand::Stop => handle_stop(&command, db_pool, http_client).await,
-            BotSubCommand::Vote(song_id) => {
+            BotSubCommand::Vote(None, choices) => {
                 handle_vote(
-                    *song_id,
+                    choices.clone(),
                     command.user_id.clone(),
                     command.response_url.clone(),
                     db_pool,
                     http_client,
+                    config,
                 )
                 .await
             }
-            BotSubCommand::List => handle_list(db_pool).await,
+            BotSubCommand::List => handle_list(command.user_id.clone(), db_pool).await,
             BotSubCommand::Song(song_uri) => {
                 handle_song(
                     song_uri.clone(),
@@ -57,10 +227,65 @@ pub async fn handler(
                     command.response_url.clone(),
                     db_pool,
                     http_client,
+                    config,
+                )
+                .await
+            }
+            BotSubCommand::Info => handle_info(db_pool).await,
+            BotSubCommand::Bracket => handle_bracket(db_pool).await,
+            BotSubCommand::SeasonStart(name) => {
+                handle_season_start(name.clone(), command, db_pool, http_client, config).await
+            }
+            BotSubCommand::SeasonEnd => {
+                handle_season_end(command, db_pool, http_client, config).await
+            }
+            BotSubCommand::Standings => handle_standings(db_pool).await,
+            BotSubCommand::History(page) => handle_history(*page, db_pool).await,
+            BotSubCommand::Show(competition) => handle_show(*competition, db_pool, config).await,
+            BotSubCommand::Stats(user_id) => {
+                let user_id = user_id.clone().unwrap_or_else(|| command.user_id.clone());
+                handle_stats(user_id, db_pool).await
+            }
+            BotSubCommand::Host(host_command) => {
+                handle_host(host_command, command, db_pool, http_client, config).await
+            }
+            BotSubCommand::Role(role_command) => {
+                handle_role(role_command, command, db_pool, http_client, config).await
+            }
+            BotSubCommand::Handover(user_id) => {
+                handle_handover(user_id.clone(), command, db_pool, http_client, config).await
+            }
+            BotSubCommand::EditDescription(description) => {
+                handle_edit_description(description.clone(), command, db_pool, http_client, config)
+                    .await
+            }
+            BotSubCommand::Extend(minutes) => {
+                handle_extend(*minutes, command, db_pool, http_client, config).await
+            }
+            BotSubCommand::Reopen(competition) => {
+                handle_reopen(*competition, command, db_pool, http_client, config).await
+            }
+            BotSubCommand::Remove(song, reason) => {
+                handle_remove(*song, reason.clone(), command, db_pool, http_client, config).await
+            }
+            BotSubCommand::Disqualify(song) => {
+                handle_disqualify(*song, command, db_pool, http_client, config).await
+            }
+            BotSubCommand::Ban(ban_command) => {
+                handle_ban(ban_command, command, db_pool, http_client, config).await
+            }
+            BotSubCommand::Judge(song, scores) => {
+                handle_judge(
+                    *song,
+                    scores.clone(),
+                    command.user_id.clone(),
+                    command.response_url.clone(),
+                    db_pool,
+                    http_client,
+                    config,
                 )
                 .await
             }
-            BotSubCommand::Info => handle_info().await,
         },
         None => handle_unimplemented().await,
     }
@@ -71,28 +296,174 @@ pub async fn handle_start(
     command: &SlackRequestCommand,
     db_pool: web::Data<DbPool>,
     http_client: web::Data<Client>,
+    config: web::Data<Config>,
 ) -> Result<HttpResponse, Error> {
-    let competition = CompetitionInsert {
-        description,
+    let (options, description) = start_options(&description);
+
+    let max_description_length = config.competition.max_description_length;
+    if description.is_empty() {
+        return Err(invalid_start("description is missing".to_string()).into());
+    }
+    if description.chars().count() > max_description_length {
+        return Err(invalid_start(format!(
+            "description is longer than {} characters",
+            max_description_length
+        ))
+        .into());
+    }
+
+    let mut competition = CompetitionInsert {
+        description: description.to_string(),
         user_id: command.user_id.clone(),
         started: chrono::Utc::now(),
         ended: None,
         is_active: false,
+        voting_method: config.competition.default_voting_method,
+        points_budget: config.competition.default_points_budget,
+        tie_break: config.competition.default_tie_break,
+        rules: config.competition.default_rules.clone(),
+        submission_deadline: None,
+        categories: vec![],
+        judges: vec![],
+        panel_weight: config.competition.default_panel_weight,
+        rubric: vec![],
+        kind: CompetitionKind::Open,
+        match_minutes: None,
+        season_id: None,
+        channel_id: Some(command.channel_id.clone()),
     };
+    let mut bracket_seeds = None;
+    let mut override_roster = false;
+    for (key, value) in options {
+        match key {
+            "override" => {
+                override_roster = parse_switch(value).ok_or_else(|| {
+                    invalid_start(format!("override must be yes or no, got {}", value))
+                })?
+            }
+            "bracket" => match value.parse::<usize>() {
+                Ok(seeds) if seeds >= 2 => bracket_seeds = Some(seeds),
+                _ => {
+                    return Err(invalid_start(format!(
+                        "bracket must be the number of past winners to seed, at least 2, got {}",
+                        value
+                    ))
+                    .into())
+                }
+            },
+            _ => apply_start_option(&mut competition, key, value)?,
+        }
+    }
+    if override_roster {
+        let actor = resolve_actor(&command.user_id, &config, http_client.get_ref()).await;
+        let channel_id = command.channel_id.clone();
+        db_block("authorize", db_pool.clone(), move |connection| {
+            authorize(
+                &actor,
+                Action::ManageRoster,
+                Scope::Channel(&channel_id),
+                connection,
+            )
+        })
+        .await?;
+    } else {
+        let channel_id = command.channel_id.clone();
+        let roster = db_block("load_roster", db_pool.clone(), move |connection| {
+            load_roster(&channel_id, connection)
+        })
+        .await?;
+        match roster.nominee() {
+            Some(nominee) if nominee != command.user_id => {
+                return Err(invalid_start(format!(
+                    "it is <@{}>'s turn to host, an admin can start anyway with override=yes",
+                    nominee
+                ))
+                .into())
+            }
+            _ => {}
+        }
+    }
+    if let Some(seeds) = bracket_seeds {
+        return handle_start_bracket(competition, seeds, command, db_pool, http_client, config)
+            .await;
+    }
+    if competition.judges.is_empty() && !competition.rubric.is_empty() {
+        return Err(invalid_start("a rubric needs judges to score it".to_string()).into());
+    }
+    if competition.rules.secret_ballot && config.competition.ballot_key.is_none() {
+        return Err(invalid_start(
+            "secret ballots need COMPETITION_BALLOT_KEY to be configured".to_string(),
+        )
+        .into());
+    }
 
-    let competition = web::block(move || save_competition(competition, &db_pool.get().unwrap()))
-        .await
-        .map_err(|e| match e {
-            BlockingError::Error(e) => e.error_response(),
-            _ => HttpResponse::InternalServerError().finish(),
-        })?;
+    let competition = db_block("save_competition", db_pool, move |connection| {
+        save_competition(competition, connection)
+    })
+    .await?;
 
-    let response_text = format!(
+    let mut response_text = format!(
         "<@{}> started competition with description: *{}*",
         competition.user_id, competition.description
     );
+    match competition.voting_method {
+        VotingMethod::Plurality => {}
+        VotingMethod::InstantRunoff | VotingMethod::Schulze => response_text
+            .push_str("\nBallots are ranked, vote with song numbers in order of preference"),
+        VotingMethod::Approval => {
+            response_text.push_str("\nVote for every song you approve of, like `vote 3 1 5`")
+        }
+        VotingMethod::Score => response_text.push_str(&format!(
+            "\nScore songs from 0 to {}, like `vote 3={} 1=2`",
+            MAX_SCORE, MAX_SCORE
+        )),
+        VotingMethod::Points => response_text.push_str(&format!(
+            "\nSpread up to {} points over the songs, like `vote 3=6 1=4`",
+            competition.points_budget
+        )),
+    }
+    match competition.tie_break {
+        TieBreak::Earliest => {}
+        TieBreak::Runoff => response_text.push_str("\nA tie for the win goes to a runoff vote"),
+        TieBreak::CastingVote => {
+            response_text.push_str("\nA tie for the win is broken by the host's casting vote")
+        }
+        TieBreak::Random => {
+            response_text.push_str("\nA tie for the win is broken by a seeded draw")
+        }
+    }
+    if competition.rules.anonymous_submissions {
+        response_text.push_str("\nSubmitters are hidden until the competition closes");
+    }
+    if competition.rules.secret_ballot {
+        response_text
+            .push_str("\nVoting is secret, votes stay hidden until the competition closes");
+    }
+    if let Some(category) = competition.categories.first() {
+        response_text.push_str(&format!(
+            "\nAward categories: {}, vote in them like `vote {} 3`",
+            competition.categories.join(", "),
+            category
+        ));
+    }
+    if !competition.judges.is_empty() {
+        response_text.push_str(&format!("\n{}", format_panel(&competition)));
+        if let Some(criterion) = competition.rubric.first() {
+            response_text.push_str(&format!(", like `judge 3 {}=4`", criterion));
+        }
+    }
+    if let Some(deadline) = competition.submission_deadline {
+        response_text.push_str(&format!(
+            "\nSubmissions close {}",
+            deadline.format("%Y-%m-%d %H:%M UTC")
+        ));
+    }
+    if competition.season_id.is_some() {
+        response_text.push_str("\nCounts towards the season, see `/sotw standings`");
+    }
 
-    in_channel_response(
+    announce_response(
+        config.features.announce_in_channel,
         command.response_url.clone(),
         response_text,
         &http_client.get_ref(),
@@ -102,69 +473,545 @@ pub async fn handle_start(
     Ok(HttpResponse::Ok().finish()) //.json(competition))
 }
 
-pub async fn handle_stop(
+fn apply_start_option(
+    competition: &mut CompetitionInsert,
+    key: &str,
+    value: &str,
+) -> Result<(), BotError> {
+    match key {
+        "voting" => {
+            competition.voting_method = value.parse().map_err(|_| {
+                invalid_start(format!(
+                    "unknown voting method {}, use plurality, ranked, schulze, approval, score or points",
+                    value
+                ))
+            })?
+        }
+        "budget" => match value.parse::<i32>() {
+            Ok(budget) if budget > 0 => competition.points_budget = budget,
+            _ => {
+                return Err(invalid_start(format!(
+                    "budget must be a positive number, got {}",
+                    value
+                )))
+            }
+        },
+        "tiebreak" => {
+            competition.tie_break = value.parse().map_err(|_| {
+                invalid_start(format!(
+                    "unknown tie-break {}, use earliest, runoff, casting or random",
+                    value
+                ))
+            })?
+        }
+        "selfvote" | "mustvote" | "late" | "anonymous" | "secret" => {
+            let switch = parse_switch(value).ok_or_else(|| {
+                invalid_start(format!("{} must be yes or no, got {}", key, value))
+            })?;
+            match key {
+                "selfvote" => competition.rules.self_vote = switch,
+                "mustvote" => competition.rules.submitters_must_vote = switch,
+                "anonymous" => competition.rules.anonymous_submissions = switch,
+                "secret" => competition.rules.secret_ballot = switch,
+                _ => competition.rules.late_submissions = switch,
+            }
+        }
+        "songs" => match value.parse::<i32>() {
+            Ok(songs) if songs > 0 => competition.rules.songs_per_user = songs,
+            _ => {
+                return Err(invalid_start(format!(
+                    "songs must be a positive number, got {}",
+                    value
+                )))
+            }
+        },
+        "categories" => {
+            for category in value.to_lowercase().split(',') {
+                if !is_category_name(category) {
+                    return Err(invalid_start(format!(
+                        "category {} must be a word of at most {} letters, digits, _ or -",
+                        category, MAX_CATEGORY_LENGTH
+                    )));
+                }
+                if competition.categories.iter().any(|c| c == category) {
+                    return Err(invalid_start(format!("category {} is given twice", category)));
+                }
+                competition.categories.push(category.to_string());
+            }
+        }
+        "deadline" => {
+            let duration = parse_duration(value).ok_or_else(|| {
+                invalid_start(format!(
+                    "deadline must be a duration like 90m, 12h or 3d, got {}",
+                    value
+                ))
+            })?;
+            competition.submission_deadline = Some(competition.started + duration)
+        }
+        "judges" => {
+            for judge in value.split(',') {
+                let judge = parse_user_id(judge).ok_or_else(|| {
+                    invalid_start(format!("judge {} must be a user mention", judge))
+                })?;
+                if !competition.judges.iter().any(|j| j == judge) {
+                    competition.judges.push(judge.to_string());
+                }
+            }
+        }
+        "panel" => match value.trim_end_matches('%').parse::<i32>() {
+            Ok(weight) if (0..=100).contains(&weight) => competition.panel_weight = weight,
+            _ => {
+                return Err(invalid_start(format!(
+                    "panel must be a percentage from 0 to 100, got {}",
+                    value
+                )))
+            }
+        },
+        "match" => {
+            let duration = parse_duration(value).ok_or_else(|| {
+                invalid_start(format!(
+                    "match must be a duration like 90m, 12h or 3d, got {}",
+                    value
+                ))
+            })?;
+            competition.match_minutes = Some(duration.num_minutes() as i32)
+        }
+        "rubric" => {
+            for criterion in value.to_lowercase().split(',') {
+                if !is_category_name(criterion) {
+                    return Err(invalid_start(format!(
+                        "criterion {} must be a word of at most {} letters, digits, _ or -",
+                        criterion, MAX_CATEGORY_LENGTH
+                    )));
+                }
+                if competition.rubric.iter().any(|c| c == criterion) {
+                    return Err(invalid_start(format!("criterion {} is given twice", criterion)));
+                }
+                competition.rubric.push(criterion.to_string());
+            }
+        }
+        _ => return Err(invalid_start(format!("unknown option {}", key))),
+    }
+
+    Ok(())
+}
+
+/// A bracket is seeded from past winners instead of taking submissions,
+/// so judges, award categories and a submission deadline don't apply
+async fn handle_start_bracket(
+    mut competition: CompetitionInsert,
+    seeds: usize,
     command: &SlackRequestCommand,
     db_pool: web::Data<DbPool>,
     http_client: web::Data<Client>,
+    config: web::Data<Config>,
 ) -> Result<HttpResponse, Error> {
-    let user_id = command.user_id.clone();
-    let close_result = web::block(move || close_competition(user_id, &db_pool.get().unwrap()))
-        .await
-        .map_err(|e| match e {
-            BlockingError::Error(e) => e.error_response(),
-            _ => HttpResponse::InternalServerError().finish(),
-        })?;
+    if !competition.judges.is_empty()
+        || !competition.categories.is_empty()
+        || competition.submission_deadline.is_some()
+    {
+        return Err(invalid_start(
+            "a bracket has no judges, award categories or submission deadline".to_string(),
+        )
+        .into());
+    }
+    competition.match_minutes = competition
+        .match_minutes
+        .or(Some(config.competition.default_match_minutes));
+
+    let bracket = db_block("start_bracket", db_pool, move |connection| {
+        start_bracket(competition, seeds, connection)
+    })
+    .await?;
 
     let response_text = format!(
-        "<@{}> *ended* competition with description: *{}*",
-        command.user_id, close_result.description
+        "<@{}> started a bracket between the last {} winners, each match is open for votes for {}\n\
+        Vote with `/sotw vote <song>` in every match\n{}",
+        bracket.competition.user_id,
+        bracket.songs.len(),
+        format_minutes(bracket.competition.match_minutes.unwrap_or(0)),
+        format_bracket(&bracket)
     );
 
-    in_channel_response(
+    announce_response(
+        config.features.announce_in_channel,
         command.response_url.clone(),
         response_text,
+        http_client.get_ref(),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn invalid_start(reason: String) -> BotError {
+    BotError {
+        data_error: DataError::InvalidArgument(reason),
+        message: "Unable to start competition".to_string(),
+    }
+}
+
+/// Start a season, options like `competitions=13` and `points=10,7,5,3,1` come
+/// before the name and fall back to the configured defaults
+pub async fn handle_season_start(
+    text: String,
+    command: &SlackRequestCommand,
+    db_pool: web::Data<DbPool>,
+    http_client: web::Data<Client>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let invalid = |reason: String| BotError {
+        data_error: DataError::InvalidArgument(reason),
+        message: "Unable to start season".to_string(),
+    };
+    let (options, name) = season_options(&text);
+    let mut season = SeasonInsert {
+        name: name.trim().to_string(),
+        user_id: command.user_id.clone(),
+        started: chrono::Utc::now(),
+        competitions: config.competition.season_competitions,
+        points: config.competition.season_points.0.clone(),
+    };
+    for (key, value) in options {
+        match key {
+            "competitions" => match value.parse::<i32>() {
+                Ok(competitions) if competitions >= 1 => season.competitions = competitions,
+                _ => {
+                    return Err(invalid(format!(
+                        "competitions must be a number of at least 1, got {}",
+                        value
+                    ))
+                    .into())
+                }
+            },
+            "points" => match value.parse::<PointsScheme>() {
+                Ok(points) => season.points = points.0,
+                Err(_) => {
+                    return Err(invalid(format!(
+                        "points must go down by place, like 10,7,5,3,1, got {}",
+                        value
+                    ))
+                    .into())
+                }
+            },
+            _ => {}
+        }
+    }
+    if season.name.is_empty() {
+        return Err(invalid("a season needs a name".to_string()).into());
+    }
+    if season.name.chars().count() > config.competition.max_description_length {
+        return Err(invalid(format!(
+            "name is longer than {} characters",
+            config.competition.max_description_length
+        ))
+        .into());
+    }
+
+    let season = db_block("start_season", db_pool, move |connection| {
+        start_season(season, connection)
+    })
+    .await?;
+
+    let points: Vec<String> = season.points.iter().map(|p| p.to_string()).collect();
+    let response_text = format!(
+        "<@{}> started the season *{}*: the next {} competitions count, with {} points by place\nSee the leaderboard with `/sotw standings`",
+        season.user_id,
+        season.name,
+        season.competitions,
+        points.join(", ")
+    );
+    announce_response(
+        config.features.announce_in_channel,
+        command.response_url.clone(),
+        response_text,
+        http_client.get_ref(),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub async fn handle_season_end(
+    command: &SlackRequestCommand,
+    db_pool: web::Data<DbPool>,
+    http_client: web::Data<Client>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let user_id = command.user_id.clone();
+    let season_standings = db_block("end_season", db_pool, move |connection| {
+        end_season(user_id, connection)
+    })
+    .await?;
+
+    announce_response(
+        config.features.announce_in_channel,
+        command.response_url.clone(),
+        format_season_end(&season_standings),
+        http_client.get_ref(),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub async fn handle_standings(db_pool: web::Data<DbPool>) -> Result<HttpResponse, Error> {
+    let season_standings =
+        db_block("find_season_standings", db_pool, find_season_standings).await?;
+
+    Ok(HttpResponse::Ok().body(format_standings(&season_standings)))
+}
+
+pub async fn handle_stop(
+    casting_vote: Option<SongRef>,
+    command: &SlackRequestCommand,
+    db_pool: web::Data<DbPool>,
+    http_client: web::Data<Client>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let actor = resolve_actor(&command.user_id, &config, http_client.get_ref()).await;
+    let ballot_key = config.competition.ballot_key.clone().unwrap_or_default();
+    let closing = db_block("stop_competition", db_pool, move |connection| {
+        stop_competition(actor, casting_vote, ballot_key.as_bytes(), connection)
+    })
+    .await?;
+
+    let (competition, response_text, attachments) = match closing {
+        Closing::Closed(results) => {
+            let mut response_text = format_results(
+                &results.competition,
+                &results.songs,
+                &results.tally,
+                results.runoff.as_ref(),
+                &results.categories,
+                results.panel.as_ref(),
+            );
+            if !results.badges.is_empty() {
+                response_text.push_str(&format!("\n\n{}", format_badges(&results.badges)));
+            }
+            if let Some(season_end) = &results.season_end {
+                response_text.push_str(&format!("\n\n{}", format_season_end(season_end)));
+            }
+            if let Some(next_host) = &results.next_host {
+                response_text.push_str(&format!("\n\n*Next host:* <@{}>", next_host));
+            }
+            let attachments = pairwise_attachment(&results.songs, &results.tally)
+                .into_iter()
+                .collect();
+            (results.competition, response_text, attachments)
+        }
+        Closing::Runoff(competition, tied) => {
+            let tied = tied
+                .iter()
+                .map(|song| format!("#{} {}", song.number, song.song_uri))
+                .collect::<Vec<String>>();
+            let response_text = format!(
+                "*Tied:* {}\nRunoff vote: vote for one of them with `/sotw vote <song>`, <@{}> stops the competition again to close it",
+                tied.join(", "),
+                competition.user_id
+            );
+            (*competition, response_text, vec![])
+        }
+    };
+
+    announce_with_attachments(
+        config.features.announce_in_channel,
+        command.response_url.clone(),
+        response_text,
+        attachments,
         &http_client.get_ref(),
     )
     .await;
 
-    Ok(HttpResponse::Ok().json(close_result))
+    Ok(HttpResponse::Ok().json(competition))
 }
 
 pub async fn handle_vote(
-    song_id: Uuid,
+    choices: Vec<VoteChoice>,
     user_id: String,
     response_url: String,
     db_pool: web::Data<DbPool>,
     http_client: web::Data<Client>,
+    config: web::Data<Config>,
 ) -> Result<HttpResponse, Error> {
-    let song_vote = web::block(move || save_song_vote(song_id, user_id, &db_pool.get().unwrap()))
-        .await
-        .map_err(|e| match e {
-            BlockingError::Error(e) => e.error_response(),
-            _ => HttpResponse::InternalServerError().finish(),
-        })?;
+    let ballot_choices = choices.clone();
+    let ballot_user_id = user_id.clone();
+    let ballot_key = config.competition.ballot_key.clone().unwrap_or_default();
+    let (competition, song_votes) = db_block("save_ballot", db_pool, move |connection| {
+        save_ballot(
+            ballot_choices,
+            ballot_user_id,
+            ballot_key.as_bytes(),
+            connection,
+        )
+    })
+    .await?;
 
-    let response_text = format!(
-        "<@{}> *voted* for song_id: {}",
-        song_vote.user_id, song_vote.song_id
-    );
+    let verb = match competition.voting_method {
+        _ if competition.runoff => "voted in the runoff for",
+        _ if song_votes.iter().any(|vote| vote.panel) => "judged",
+        VotingMethod::Plurality => "voted for",
+        VotingMethod::InstantRunoff | VotingMethod::Schulze => "ranked",
+        VotingMethod::Approval => "approved",
+        VotingMethod::Score | VotingMethod::Points => "scored",
+    };
+    let choices = choices
+        .iter()
+        .map(|choice| choice.to_string())
+        .collect::<Vec<String>>();
+
+    // Secret votes are only confirmed to the voter, and the stored votes are not returned
+    if competition.rules.secret_ballot {
+        let response_text = format!(
+            "You *{}* {}, your vote stays secret",
+            verb,
+            choices.join(", ")
+        );
+        ephemeral_response(response_url, response_text, http_client.get_ref()).await;
+
+        return Ok(HttpResponse::Ok().finish());
+    }
+
+    let response_text = format!("<@{}> *{}* {}", user_id, verb, choices.join(", "));
+
+    announce_response(
+        config.features.announce_in_channel,
+        response_url,
+        response_text,
+        http_client.get_ref(),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(song_votes))
+}
+
+pub async fn handle_category_vote(
+    category: String,
+    choices: Vec<VoteChoice>,
+    user_id: String,
+    response_url: String,
+    db_pool: web::Data<DbPool>,
+    http_client: web::Data<Client>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let choice = match choices.as_slice() {
+        [choice] => *choice,
+        _ => {
+            return Err(BotError {
+                data_error: DataError::InvalidArgument(format!(
+                    "vote for a single song in {}",
+                    category
+                )),
+                message: "Unable to save vote".to_string(),
+            }
+            .into())
+        }
+    };
+
+    let vote_user_id = user_id.clone();
+    let vote_category = category.clone();
+    let ballot_key = config.competition.ballot_key.clone().unwrap_or_default();
+    let (competition, song_vote) = db_block("save_category_vote", db_pool, move |connection| {
+        save_category_vote(
+            vote_category,
+            choice,
+            vote_user_id,
+            ballot_key.as_bytes(),
+            connection,
+        )
+    })
+    .await?;
+
+    if competition.rules.secret_ballot {
+        let response_text = format!(
+            "You *voted for* {} in *{}*, your vote stays secret",
+            choice, category
+        );
+        ephemeral_response(response_url, response_text, http_client.get_ref()).await;
 
-    in_channel_response(response_url, response_text, &http_client.get_ref()).await;
+        return Ok(HttpResponse::Ok().finish());
+    }
+
+    let response_text = format!("<@{}> *voted for* {} in *{}*", user_id, choice, category);
+
+    announce_response(
+        config.features.announce_in_channel,
+        response_url,
+        response_text,
+        http_client.get_ref(),
+    )
+    .await;
 
     Ok(HttpResponse::Ok().json(song_vote))
 }
 
-pub async fn handle_list(db_pool: web::Data<DbPool>) -> Result<HttpResponse, Error> {
-    let active_songs = web::block(move || list_songs_active_competition(&db_pool.get().unwrap()))
-        .await
-        .map_err(|e| match e {
-            BlockingError::Error(e) => e.error_response(),
-            _ => HttpResponse::InternalServerError().finish(),
-        })?;
+/// Judges' scores on the rubric are only confirmed to the judge, the panel's
+/// verdict is shown with the results
+pub async fn handle_judge(
+    song: SongRef,
+    scores: Vec<(String, i32)>,
+    user_id: String,
+    response_url: String,
+    db_pool: web::Data<DbPool>,
+    http_client: web::Data<Client>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let judged_scores = scores.clone();
+    let ballot_key = config.competition.ballot_key.clone().unwrap_or_default();
+    db_block("save_rubric_scores", db_pool, move |connection| {
+        save_rubric_scores(
+            song,
+            judged_scores,
+            user_id,
+            ballot_key.as_bytes(),
+            connection,
+        )
+    })
+    .await?;
+
+    let scores = scores
+        .iter()
+        .map(|(criterion, score)| format!("{} {}", criterion, score))
+        .collect::<Vec<String>>();
+    let response_text = format!("You *judged* {}: {}", song, scores.join(", "));
+    ephemeral_response(response_url, response_text, http_client.get_ref()).await;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// In competitions with anonymous submissions only the user's own songs show their submitter
+pub async fn handle_list(
+    user_id: String,
+    db_pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (competition, active_songs) = db_block(
+        "list_songs_active_competition",
+        db_pool,
+        list_songs_active_competition,
+    )
+    .await?;
 
     let list_response = active_songs
         .iter()
-        .map(|song| format!("<@{}> - {}", song.user_id, song.song_uri.clone()))
+        .map(|song| {
+            let line = if !competition.rules.anonymous_submissions {
+                format!(
+                    "#{} <@{}> - {}",
+                    song.number,
+                    song.user_id,
+                    song.song_uri.clone()
+                )
+            } else if song.user_id == user_id {
+                format!("#{} {} (your song)", song.number, song.song_uri)
+            } else {
+                format!("#{} {}", song.number, song.song_uri)
+            };
+            if song.disqualified {
+                format!("{} (disqualified)", line)
+            } else {
+                line
+            }
+        })
         .collect::<Vec<String>>()
         .join("\n");
 
@@ -177,25 +1024,666 @@ pub async fn handle_song(
     response_url: String,
     db_pool: web::Data<DbPool>,
     http_client: web::Data<Client>,
+    config: web::Data<Config>,
 ) -> Result<HttpResponse, Error> {
-    let song = web::block(move || save_song(song_uri, user_id, &db_pool.get().unwrap()))
-        .await
-        .map_err(|e| match e {
+    let (song, competition) = db_block("save_song", db_pool, move |connection| {
+        let song = save_song(song_uri, user_id, connection)?;
+        let competition = find_active_competition(connection)?;
+        Ok((song, competition))
+    })
+    .await?;
+
+    // Anonymous songs are only confirmed to the submitter
+    if matches!(competition, Some(c) if c.rules.anonymous_submissions) {
+        let response_text = format!(
+            "You *added* song #{}: {}, submitters are hidden until the competition closes",
+            song.number, song.song_uri
+        );
+        ephemeral_response(response_url, response_text, http_client.get_ref()).await;
+    } else {
+        let response_text = format!("<@{}> *added* song: {}", song.user_id, song.song_uri);
+        announce_response(
+            config.features.announce_in_channel,
+            response_url,
+            response_text,
+            http_client.get_ref(),
+        )
+        .await;
+    }
+
+    Ok(HttpResponse::Ok().json(song))
+}
+/// Run a blocking database call on the thread pool.
+/// The closure runs on another thread, so the span is handed over explicitly
+/// to keep the call inside the request span.
+async fn db_block<F, T>(
+    operation: &'static str,
+    db_pool: web::Data<DbPool>,
+    f: F,
+) -> Result<T, Error>
+where
+    F: FnOnce(&PgConnection) -> Result<T, BotError> + Send + 'static,
+    T: Send + 'static,
+{
+    let span = info_span!("db", operation);
+
+    web::block(move || {
+        let _enter = span.enter();
+        let connection = db_pool.get().map_err(|e| BotError {
+            data_error: DataError::DieselError(e.to_string()),
+            message: "Unable to get a database connection".to_string(),
+        })?;
+        f(&connection)
+    })
+    .await
+    .map_err(|e| {
+        match e {
             BlockingError::Error(e) => e.error_response(),
             _ => HttpResponse::InternalServerError().finish(),
-        })?;
+        }
+        .into()
+    })
+}
 
-    let response_text = format!("<@{}> *added* song: {}", song.user_id, song.song_uri);
+pub async fn handle_bracket(db_pool: web::Data<DbPool>) -> Result<HttpResponse, Error> {
+    let bracket = db_block("find_active_bracket", db_pool, find_active_bracket).await?;
 
-    in_channel_response(response_url, response_text, &http_client.get_ref()).await;
+    Ok(HttpResponse::Ok().body(format_bracket(&bracket)))
+}
 
-    Ok(HttpResponse::Ok().json(song))
+pub async fn handle_history(page: i64, db_pool: web::Data<DbPool>) -> Result<HttpResponse, Error> {
+    let history = db_block("list_past_competitions", db_pool, move |connection| {
+        list_past_competitions(page, HISTORY_PAGE_SIZE, connection)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().body(format_history(&history)))
+}
+
+pub async fn handle_show(
+    competition: CompetitionRef,
+    db_pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let ballot_key = config.competition.ballot_key.clone().unwrap_or_default();
+    let past = db_block("find_past_competition", db_pool, move |connection| {
+        find_past_competition(competition, ballot_key.as_bytes(), connection)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().body(format_past(&past)))
 }
-pub async fn handle_info() -> Result<HttpResponse, Error> {
-    Ok(HttpResponse::Ok().body("Bot information - TODO"))
+
+pub async fn handle_stats(
+    user_id: String,
+    db_pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let stats = db_block("user_stats", db_pool, move |connection| {
+        user_stats(user_id, connection)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().body(format_stats(&stats)))
+}
+
+/// Join, leave or skip a turn in the host queue of the channel, or show it
+pub async fn handle_host(
+    host_command: &HostCommand,
+    command: &SlackRequestCommand,
+    db_pool: web::Data<DbPool>,
+    http_client: web::Data<Client>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let channel_id = command.channel_id.clone();
+    let user_id = command.user_id.clone();
+    let (roster, response_text) = match host_command {
+        HostCommand::Join => {
+            let roster = db_block("join_roster", db_pool, move |connection| {
+                join_roster(channel_id, user_id, connection)
+            })
+            .await?;
+            let response_text = format!("<@{}> joined the host queue", command.user_id);
+            (roster, response_text)
+        }
+        HostCommand::Leave => {
+            let roster = db_block("leave_roster", db_pool, move |connection| {
+                leave_roster(channel_id, user_id, connection)
+            })
+            .await?;
+            let response_text = format!("<@{}> left the host queue", command.user_id);
+            (roster, response_text)
+        }
+        HostCommand::Skip => {
+            let roster = db_block("load_roster", db_pool.clone(), move |connection| {
+                load_roster(&channel_id, connection)
+            })
+            .await?;
+            let skipped = roster.nominee().map(String::from);
+            let actor = resolve_actor(&command.user_id, &config, http_client.get_ref()).await;
+            let roster = db_block("skip_host", db_pool, move |connection| {
+                skip_host(&actor, roster.channel_id, connection)
+            })
+            .await?;
+            let response_text = format!(
+                "<@{}>'s turn to host was skipped",
+                skipped.unwrap_or_default()
+            );
+            (roster, response_text)
+        }
+        HostCommand::Queue => {
+            let roster = db_block("load_roster", db_pool, move |connection| {
+                load_roster(&channel_id, connection)
+            })
+            .await?;
+            return Ok(HttpResponse::Ok().body(format_roster(&roster)));
+        }
+    };
+
+    announce_response(
+        config.features.announce_in_channel,
+        command.response_url.clone(),
+        format!("{}\n{}", response_text, format_roster(&roster)),
+        http_client.get_ref(),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub async fn handle_handover(
+    new_host: String,
+    command: &SlackRequestCommand,
+    db_pool: web::Data<DbPool>,
+    http_client: web::Data<Client>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let actor = resolve_actor(&command.user_id, &config, http_client.get_ref()).await;
+    let handover = db_block("handover_competition", db_pool, move |connection| {
+        handover_competition(&actor, new_host, connection)
+    })
+    .await?;
+
+    let response_text = if handover.user_id == handover.from_user_id {
+        format!(
+            "<@{}> handed the competition over to <@{}>",
+            handover.from_user_id, handover.to_user_id
+        )
+    } else {
+        format!(
+            "<@{}> handed the competition of <@{}> over to <@{}>",
+            handover.user_id, handover.from_user_id, handover.to_user_id
+        )
+    };
+    announce_response(
+        config.features.announce_in_channel,
+        command.response_url.clone(),
+        response_text,
+        http_client.get_ref(),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub async fn handle_edit_description(
+    description: String,
+    command: &SlackRequestCommand,
+    db_pool: web::Data<DbPool>,
+    http_client: web::Data<Client>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let max_description_length = config.competition.max_description_length;
+    if description.chars().count() > max_description_length {
+        return Err(BotError {
+            data_error: DataError::InvalidArgument(format!(
+                "description is longer than {} characters",
+                max_description_length
+            )),
+            message: "Unable to edit competition".to_string(),
+        }
+        .into());
+    }
+
+    let actor = resolve_actor(&command.user_id, &config, http_client.get_ref()).await;
+    let (old_description, edited) = db_block("edit_description", db_pool, move |connection| {
+        edit_description(&actor, description, connection)
+    })
+    .await?;
+
+    announce_response(
+        config.features.announce_in_channel,
+        command.response_url.clone(),
+        format!(
+            "<@{}> changed the description from *{}* to *{}*",
+            command.user_id, old_description, edited.description
+        ),
+        http_client.get_ref(),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub async fn handle_extend(
+    minutes: i64,
+    command: &SlackRequestCommand,
+    db_pool: web::Data<DbPool>,
+    http_client: web::Data<Client>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let actor = resolve_actor(&command.user_id, &config, http_client.get_ref()).await;
+    let extension = db_block("extend_deadline", db_pool, move |connection| {
+        extend_deadline(
+            &actor,
+            chrono::Duration::minutes(minutes),
+            chrono::Utc::now(),
+            connection,
+        )
+    })
+    .await?;
+
+    let response_text = match extension {
+        Extension::Submissions(deadline) => format!(
+            "<@{}> extended the submission deadline to {}",
+            command.user_id,
+            deadline.format("%Y-%m-%d %H:%M UTC")
+        ),
+        Extension::Matches(deadline) => format!(
+            "<@{}> extended the open matches of the bracket to {}",
+            command.user_id,
+            deadline.format("%Y-%m-%d %H:%M UTC")
+        ),
+    };
+    announce_response(
+        config.features.announce_in_channel,
+        command.response_url.clone(),
+        response_text,
+        http_client.get_ref(),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub async fn handle_reopen(
+    competition: Option<CompetitionRef>,
+    command: &SlackRequestCommand,
+    db_pool: web::Data<DbPool>,
+    http_client: web::Data<Client>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let actor = resolve_actor(&command.user_id, &config, http_client.get_ref()).await;
+    let channel_id = command.channel_id.clone();
+    let reopened = db_block("reopen_competition", db_pool, move |connection| {
+        reopen_competition(&actor, competition, channel_id, connection)
+    })
+    .await?;
+
+    announce_response(
+        config.features.announce_in_channel,
+        command.response_url.clone(),
+        format!(
+            "<@{}> reopened the competition *{}* hosted by <@{}>, its results are decided again when it is stopped",
+            command.user_id, reopened.description, reopened.user_id
+        ),
+        http_client.get_ref(),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Grant or revoke a role, or list the roles which apply in the channel
+pub async fn handle_role(
+    role_command: &RoleCommand,
+    command: &SlackRequestCommand,
+    db_pool: web::Data<DbPool>,
+    http_client: web::Data<Client>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let channel_id = command.channel_id.clone();
+    let response_text = match role_command {
+        RoleCommand::Add(role, user_id) => {
+            let actor = resolve_actor(&command.user_id, &config, http_client.get_ref()).await;
+            let (role, user_id) = (*role, user_id.clone());
+            let granted = db_block("grant_role", db_pool, move |connection| {
+                grant_role(&actor, user_id, role, channel_id, connection)
+            })
+            .await?;
+            format!(
+                "<@{}> made <@{}> a {}",
+                granted.granted_by, granted.user_id, granted.role
+            )
+        }
+        RoleCommand::Remove(role, user_id) => {
+            let actor = resolve_actor(&command.user_id, &config, http_client.get_ref()).await;
+            let (role, user_id) = (*role, user_id.clone());
+            let revoked = db_block("revoke_role", db_pool, move |connection| {
+                revoke_role(&actor, user_id, role, channel_id, connection)
+            })
+            .await?;
+            format!(
+                "<@{}> is no longer a {}, by <@{}>",
+                revoked.user_id, revoked.role, command.user_id
+            )
+        }
+        RoleCommand::List => {
+            let roles = db_block("list_roles", db_pool, move |connection| {
+                list_roles(channel_id, connection)
+            })
+            .await?;
+            return Ok(HttpResponse::Ok().body(format_roles(&roles, &config.slack.admin_users)));
+        }
+    };
+
+    announce_response(
+        config.features.announce_in_channel,
+        command.response_url.clone(),
+        response_text,
+        http_client.get_ref(),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub async fn handle_remove(
+    song: SongRef,
+    reason: String,
+    command: &SlackRequestCommand,
+    db_pool: web::Data<DbPool>,
+    http_client: web::Data<Client>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let actor = resolve_actor(&command.user_id, &config, http_client.get_ref()).await;
+    let removed = db_block("remove_song", db_pool, move |connection| {
+        remove_song(&actor, song, connection)
+    })
+    .await?;
+
+    // The submitter is told privately, the channel is not told whose song it was
+    match &config.slack.bot_token {
+        Some(bot_token) => {
+            let text = format!(
+                "Your song {} was removed from the competition by <@{}>: {}",
+                removed.song_uri, command.user_id, reason
+            );
+            send_direct_message(
+                &config.slack.api_url,
+                bot_token,
+                &removed.user_id,
+                &text,
+                http_client.get_ref(),
+            )
+            .await;
+        }
+        None => warn!("No bot token configured, the submitter is not told of the removal"),
+    }
+
+    announce_response(
+        config.features.announce_in_channel,
+        command.response_url.clone(),
+        format!(
+            "<@{}> removed song #{}: {}",
+            command.user_id, removed.number, reason
+        ),
+        http_client.get_ref(),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub async fn handle_disqualify(
+    song: SongRef,
+    command: &SlackRequestCommand,
+    db_pool: web::Data<DbPool>,
+    http_client: web::Data<Client>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let actor = resolve_actor(&command.user_id, &config, http_client.get_ref()).await;
+    let disqualified = db_block("disqualify_song", db_pool, move |connection| {
+        disqualify_song(&actor, song, connection)
+    })
+    .await?;
+
+    announce_response(
+        config.features.announce_in_channel,
+        command.response_url.clone(),
+        format!(
+            "<@{}> disqualified song #{}, it is left out of the results",
+            command.user_id, disqualified.number
+        ),
+        http_client.get_ref(),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub async fn handle_ban(
+    ban_command: &BanCommand,
+    command: &SlackRequestCommand,
+    db_pool: web::Data<DbPool>,
+    http_client: web::Data<Client>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    let channel_id = command.channel_id.clone();
+    let response_text = match ban_command {
+        BanCommand::Add(user_id, reason) => {
+            let actor = resolve_actor(&command.user_id, &config, http_client.get_ref()).await;
+            let (user_id, reason) = (user_id.clone(), reason.clone());
+            let ban = db_block("ban_user", db_pool, move |connection| {
+                ban_user(&actor, user_id, channel_id, reason, connection)
+            })
+            .await?;
+            let banned = format!(
+                "<@{}> banned <@{}> from submitting songs and voting in this channel",
+                ban.banned_by, ban.user_id
+            );
+            match ban.reason {
+                Some(reason) => format!("{}: {}", banned, reason),
+                None => banned,
+            }
+        }
+        BanCommand::Remove(user_id) => {
+            let actor = resolve_actor(&command.user_id, &config, http_client.get_ref()).await;
+            let user_id = user_id.clone();
+            let lifted = db_block("unban_user", db_pool, move |connection| {
+                unban_user(&actor, user_id, channel_id, connection)
+            })
+            .await?;
+            format!(
+                "<@{}> is no longer banned, by <@{}>",
+                lifted.user_id, command.user_id
+            )
+        }
+        BanCommand::List => {
+            let bans = db_block("list_bans", db_pool, move |connection| {
+                list_bans(channel_id, connection)
+            })
+            .await?;
+            return Ok(HttpResponse::Ok().body(format_bans(&bans)));
+        }
+    };
+
+    announce_response(
+        config.features.announce_in_channel,
+        command.response_url.clone(),
+        response_text,
+        http_client.get_ref(),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// The user giving a command, as an admin when configured as one or when an
+/// admin of the Slack workspace
+async fn resolve_actor(user_id: &str, config: &Config, http_client: &Client) -> Actor {
+    let mut actor = Actor::user(user_id.to_string());
+    actor.admin = config
+        .slack
+        .admin_users
+        .iter()
+        .any(|admin| admin == user_id)
+        || match &config.slack.bot_token {
+            Some(bot_token) => {
+                is_workspace_admin(&config.slack.api_url, bot_token, user_id, http_client).await
+            }
+            None => false,
+        };
+
+    actor
+}
+
+pub async fn handle_info(db_pool: web::Data<DbPool>) -> Result<HttpResponse, Error> {
+    let active_competition =
+        db_block("find_active_competition", db_pool, find_active_competition).await?;
+
+    Ok(HttpResponse::Ok().body(format_info(active_competition.as_ref())))
 }
 
 pub async fn handle_unimplemented() -> Result<HttpResponse, Error> {
     warn!("Received unimplemented command");
     Ok(HttpResponse::Ok().body("Unknown or unimplemented command"))
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{from_sources, Config, DatabaseConfig};
+    use crate::create_db_pool;
+    use crate::slack::handler::handler;
+    use crate::{DbPool, SlackSecrets};
+    use actix_web::http::StatusCode;
+    use actix_web::{test, web, App};
+    use diesel::{Connection, PgConnection, RunQueryDsl};
+    use reqwest::Client;
+    use ring::hmac;
+    use uuid::Uuid;
+
+    fn signed_request(body: &str, timestamp: i64) -> test::TestRequest {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret");
+        let message = format!("v0:{}:{}", timestamp, body);
+        let signature = hex::encode(hmac::sign(&key, message.as_bytes()).as_ref());
+
+        test::TestRequest::post()
+            .uri("/")
+            .header("X-Slack-Request-Timestamp", timestamp.to_string())
+            .header("X-Slack-Signature", format!("v0={}", signature))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .set_payload(body.to_string())
+    }
+
+    fn database_url() -> String {
+        dotenv::dotenv().ok();
+        std::env::var("DATABASE_URL").expect("Database connection string missing!")
+    }
+
+    fn test_config(database_url: String) -> (DbPool, Config) {
+        let config = from_sources(None, |key: &str| match key {
+            "DATABASE_URL" => Some(database_url.clone()),
+            "SLACK_SIGNING_SECRET" => Some("secret".to_string()),
+            "FEATURE_ANNOUNCE_IN_CHANNEL" => Some("false".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        let db_pool = create_db_pool(&DatabaseConfig {
+            pool_max_size: 1,
+            ..config.database.clone()
+        });
+
+        (db_pool, config)
+    }
+
+    fn info_body() -> String {
+        format!(
+            "token=tok&team_id=T1&team_domain=x&channel_id=C1&channel_name=general\
+            &user_id=U1&command=%2Fsotw&text=info&api_app_id=A1\
+            &response_url=http%3A%2F%2F127.0.0.1%3A9%2Fhook&trigger_id={}",
+            Uuid::new_v4()
+        )
+    }
+
+    #[actix_rt::test]
+    async fn test_replayed_request() {
+        let (db_pool, config) = test_config(database_url());
+        let slack_secrets: SlackSecrets = config.slack.signing_secrets.clone();
+
+        let mut app = test::init_service(
+            App::new()
+                .data(db_pool)
+                .data(Client::new())
+                .data(slack_secrets)
+                .data(config)
+                .route("/", web::post().to(handler)),
+        )
+        .await;
+
+        let body = info_body();
+        let timestamp = chrono::Utc::now().timestamp();
+
+        let handled =
+            test::call_service(&mut app, signed_request(&body, timestamp).to_request()).await;
+        let replayed =
+            test::call_service(&mut app, signed_request(&body, timestamp).to_request()).await;
+        let retried =
+            test::call_service(&mut app, signed_request(&body, timestamp - 1).to_request()).await;
+
+        assert_eq!(handled.status(), StatusCode::OK);
+        assert_eq!(
+            replayed.status(),
+            StatusCode::BAD_REQUEST,
+            "the same signed request is not answered twice"
+        );
+        assert_eq!(
+            retried.status(),
+            StatusCode::OK,
+            "a retry signed anew gets the stored outcome"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_database_error_is_retried() {
+        let database_url = database_url();
+        let separator = if database_url.contains('?') { '&' } else { '?' };
+        let (db_pool, config) = test_config(format!(
+            "{}{}options=-c%20lock_timeout%3D200",
+            database_url, separator
+        ));
+        let slack_secrets: SlackSecrets = config.slack.signing_secrets.clone();
+
+        let mut app = test::init_service(
+            App::new()
+                .data(db_pool)
+                .data(Client::new())
+                .data(slack_secrets)
+                .data(config)
+                .route("/", web::post().to(handler)),
+        )
+        .await;
+
+        // Hold the competition table so the command fails on a lock timeout
+        let locker = PgConnection::establish(&database_url).unwrap();
+        diesel::sql_query("begin").execute(&locker).unwrap();
+        diesel::sql_query("lock table sotw.competition in access exclusive mode")
+            .execute(&locker)
+            .unwrap();
+
+        let body = info_body();
+        let timestamp = chrono::Utc::now().timestamp();
+
+        let failed =
+            test::call_service(&mut app, signed_request(&body, timestamp).to_request()).await;
+        diesel::sql_query("rollback").execute(&locker).unwrap();
+        let retried =
+            test::call_service(&mut app, signed_request(&body, timestamp - 1).to_request()).await;
+
+        assert_eq!(failed.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            retried.status(),
+            StatusCode::OK,
+            "a retry after a database error runs the command again"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use crate::slack::bracket::format_bracket;
+use crate::slack::results::{format_results, format_song};
+use crate::sotw_db::database::{HistoryPage, PastCompetition};
+use crate::sotw_db::model::Competition;
+use std::slice;
+
+/// A page of past competitions, numbered from the latest so the number can be
+/// given to `/sotw show`
+pub fn format_history(history: &HistoryPage) -> String {
+    if history.competitions.is_empty() {
+        return if history.page == 1 {
+            "No competitions have closed yet".to_string()
+        } else {
+            format!("There is no page {} of the history", history.page)
+        };
+    }
+
+    let pages = (history.total + history.page_size - 1) / history.page_size;
+    let mut lines = vec![format!("*History*, page {} of {}", history.page, pages)];
+    let first_number = (history.page - 1) * history.page_size + 1;
+    for (index, (past, winner)) in history.competitions.iter().enumerate() {
+        let winner = match winner {
+            Some(winner) => format!(
+                "won by {}",
+                format_song(slice::from_ref(winner), &winner.id)
+            ),
+            None => "no winner".to_string(),
+        };
+        lines.push(format!(
+            "{}. {}, {}",
+            first_number + index as i64,
+            format_header(past),
+            winner
+        ));
+    }
+
+    lines.push(if history.page < pages {
+        format!(
+            "More with `/sotw history {}`, see one with `/sotw show <number>`",
+            history.page + 1
+        )
+    } else {
+        "See one with `/sotw show <number>`".to_string()
+    });
+
+    lines.join("\n")
+}
+
+/// Songs and results of a past competition, as they were announced when it closed
+pub fn format_past(past: &PastCompetition) -> String {
+    match past {
+        PastCompetition::Open(results) => {
+            let mut lines = vec![format_header(&results.competition)];
+            lines.extend(
+                results
+                    .songs
+                    .iter()
+                    .map(|song| format_song(&results.songs, &song.id)),
+            );
+            lines.push(format_results(
+                &results.competition,
+                &results.songs,
+                &results.tally,
+                results.runoff.as_ref(),
+                &results.categories,
+                results.panel.as_ref(),
+            ));
+            lines.join("\n")
+        }
+        PastCompetition::Bracket(bracket) => format!(
+            "{}\n{}",
+            format_header(&bracket.competition),
+            format_bracket(bracket)
+        ),
+    }
+}
+
+fn format_header(competition: &Competition) -> String {
+    let ended = competition
+        .ended
+        .map_or_else(String::new, |ended| ended.format("%Y-%m-%d").to_string());
+    format!(
+        "*{}* by <@{}>, {} to {}",
+        competition.description,
+        competition.user_id,
+        competition.started.format("%Y-%m-%d"),
+        ended
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::slack::history::format_history;
+    use crate::sotw_db::database::HistoryPage;
+    use crate::sotw_db::model::{Competition, Song};
+    use chrono::{DateTime, Utc};
+    use uuid::Uuid;
+
+    fn past_competition(description: &str, started: &str, ended: &str) -> Competition {
+        Competition {
+            description: description.to_string(),
+            started: started.parse::<DateTime<Utc>>().unwrap(),
+            ended: Some(ended.parse::<DateTime<Utc>>().unwrap()),
+            is_active: false,
+            ..Competition::for_test("UHOST")
+        }
+    }
+
+    #[test]
+    fn test_format_history() {
+        let mut trains = past_competition("Trains", "2026-10-11T18:00:00Z", "2026-10-18T18:00:00Z");
+        let winner = Song {
+            id: Uuid::new_v4(),
+            user_id: "UA".to_string(),
+            song_uri: "http://example.org/1".to_string(),
+            competition_id: trains.id,
+            number: 1,
+            place: Some(1),
+            disqualified: false,
+        };
+        trains.winner_song_id = Some(winner.id);
+        let boats = past_competition("Boats", "2026-10-04T18:00:00Z", "2026-10-11T18:00:00Z");
+
+        let history = HistoryPage {
+            competitions: vec![(trains, Some(winner)), (boats, None)],
+            page: 2,
+            page_size: 2,
+            total: 5,
+        };
+
+        assert_eq!(
+            format_history(&history),
+            "*History*, page 2 of 3\n\
+            3. *Trains* by <@UHOST>, 2026-10-11 to 2026-10-18, won by #1 http://example.org/1 by <@UA>\n\
+            4. *Boats* by <@UHOST>, 2026-10-04 to 2026-10-11, no winner\n\
+            More with `/sotw history 3`, see one with `/sotw show <number>`"
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use crate::slack::model::SlackRequestCommand;
+use crate::sotw_db::model::SlackRequest;
+use actix_http::body::{Body, ResponseBody};
+use actix_web::http::{header, StatusCode};
+use actix_web::{Error, HttpResponse};
+
+/// Outcome of a handled command, as stored for answering retries
+#[derive(PartialEq, Debug)]
+pub struct Outcome {
+    pub status_code: StatusCode,
+    pub content_type: Option<String>,
+    pub body: String,
+}
+
+/// Slack sends the same trigger_id again when it retries a command,
+/// while a new invocation always gets a new one.
+pub fn request_key(command: &SlackRequestCommand) -> String {
+    format!("{}:{}", command.team_id, command.trigger_id)
+}
+
+impl Outcome {
+    /// Capture what the handler answered, errors included
+    pub fn from_result(result: Result<HttpResponse, Error>) -> Outcome {
+        let mut response = match result {
+            Ok(response) => response,
+            Err(error) => error.as_response_error().error_response(),
+        };
+
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+
+        let body = match response.take_body() {
+            ResponseBody::Body(Body::Bytes(bytes)) | ResponseBody::Other(Body::Bytes(bytes)) => {
+                String::from_utf8_lossy(&bytes).into_owned()
+            }
+            _ => String::new(),
+        };
+
+        Outcome {
+            status_code: response.status(),
+            content_type,
+            body,
+        }
+    }
+
+    pub fn from_stored(stored: SlackRequest) -> Outcome {
+        Outcome {
+            status_code: stored
+                .status_code
+                .and_then(|code| StatusCode::from_u16(code as u16).ok())
+                .unwrap_or(StatusCode::OK),
+            content_type: stored.content_type,
+            body: stored.response_body.unwrap_or_default(),
+        }
+    }
+
+    /// Server errors are not stored, Slack's retry should get to run again
+    pub fn is_final(&self) -> bool {
+        !self.status_code.is_server_error()
+    }
+
+    pub fn to_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code);
+        if let Some(content_type) = &self.content_type {
+            response.set_header(header::CONTENT_TYPE, content_type.as_str());
+        }
+
+        response.body(self.body.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::slack::idempotency::Outcome;
+    use crate::sotw_db::errors::{BotError, DataError};
+    use actix_web::http::StatusCode;
+    use actix_web::HttpResponse;
+
+    #[test]
+    fn test_outcome_roundtrip() {
+        let outcome = Outcome::from_result(Ok(HttpResponse::Ok().json(vec!["song"])));
+        let replayed = Outcome::from_result(Ok(outcome.to_response()));
+
+        assert_eq!(outcome.body, "[\"song\"]");
+        assert_eq!(outcome, replayed, "a stored outcome should answer the same");
+    }
+
+    #[test]
+    fn test_outcome_from_error() {
+        let conflict = Outcome::from_result(Err(BotError {
+            data_error: DataError::NoActiveCompetition,
+            message: "".to_string(),
+        }
+        .into()));
+        let server_error = Outcome::from_result(Ok(HttpResponse::InternalServerError().finish()));
+        let database_error = Outcome::from_result(Err(BotError {
+            data_error: DataError::DieselError("".to_string()),
+            message: "".to_string(),
+        }
+        .into()));
+
+        assert_eq!(conflict.status_code, StatusCode::NOT_FOUND);
+        assert!(conflict.is_final(), "client errors are the final outcome");
+        assert!(
+            !server_error.is_final(),
+            "server errors should be retried instead of stored"
+        );
+        assert!(
+            !database_error.is_final(),
+            "database errors should be retried instead of stored"
+        );
+    }
+}
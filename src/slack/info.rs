@@ -0,0 +1,136 @@
+use crate::sotw_db::model::{Competition, MAX_SCORE};
+
+/// Answer to `/sotw info`, the bot version and the rules of the active competition
+pub fn format_info(active_competition: Option<&Competition>) -> String {
+    let mut lines = vec![format!(
+        "{} {}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    )];
+
+    let competition = match active_competition {
+        Some(competition) => competition,
+        None => {
+            lines.push("No active competition".to_string());
+            return lines.join("\n");
+        }
+    };
+    let rules = &competition.rules;
+
+    lines.push(format!(
+        "Active competition by <@{}>: *{}*",
+        competition.user_id, competition.description
+    ));
+    lines.push(format!(
+        "Voting: {}, tie-break: {}",
+        competition.voting_method.as_str(),
+        competition.tie_break.as_str()
+    ));
+    if !competition.categories.is_empty() {
+        lines.push(format!(
+            "Award categories: {}",
+            competition.categories.join(", ")
+        ));
+    }
+    if !competition.judges.is_empty() {
+        lines.push(format_panel(competition));
+    }
+    lines.push("Rules:".to_string());
+    lines.push(if rules.self_vote {
+        "• Voting for your own song is allowed".to_string()
+    } else {
+        "• Voting for your own song is not allowed".to_string()
+    });
+    lines.push(if rules.songs_per_user == 1 {
+        "• One song per user, submitting again replaces it".to_string()
+    } else {
+        format!("• Up to {} songs per user", rules.songs_per_user)
+    });
+    if rules.anonymous_submissions {
+        lines.push("• Submitters are hidden until the competition closes".to_string());
+    }
+    if rules.secret_ballot {
+        lines.push("• Secret ballot, votes stay hidden until the competition closes".to_string());
+    }
+    if rules.submitters_must_vote {
+        lines.push("• Submitters must vote, or their songs are left out".to_string());
+    }
+    match competition.submission_deadline {
+        Some(deadline) => lines.push(format!(
+            "• Submissions close {}, late songs are {}",
+            deadline.format("%Y-%m-%d %H:%M UTC"),
+            if rules.late_submissions {
+                "accepted"
+            } else {
+                "not accepted"
+            }
+        )),
+        None => lines.push("• No submission deadline".to_string()),
+    }
+
+    lines.join("\n")
+}
+
+/// The judges of a competition, their share of the result and their rubric
+pub fn format_panel(competition: &Competition) -> String {
+    let judges: Vec<String> = competition
+        .judges
+        .iter()
+        .map(|judge| format!("<@{}>", judge))
+        .collect();
+    let mut line = format!(
+        "Judges: {}, deciding {}% of the result",
+        judges.join(", "),
+        competition.panel_weight
+    );
+    if !competition.rubric.is_empty() {
+        line.push_str(&format!(
+            ", scoring {} from 0 to {}",
+            competition.rubric.join(", "),
+            MAX_SCORE
+        ));
+    }
+
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::slack::info::format_info;
+    use crate::sotw_db::model::{Competition, CompetitionRules, TieBreak, VotingMethod};
+
+    #[test]
+    fn test_rules() {
+        let competition = Competition {
+            voting_method: VotingMethod::Schulze,
+            tie_break: TieBreak::Runoff,
+            rules: CompetitionRules {
+                songs_per_user: 2,
+                submitters_must_vote: true,
+                late_submissions: false,
+                ..CompetitionRules::default()
+            },
+            submission_deadline: Some("2026-10-23T18:00:00Z".parse().unwrap()),
+            categories: vec!["lyrics".to_string(), "banger".to_string()],
+            judges: vec!["UJ1".to_string(), "UJ2".to_string()],
+            panel_weight: 40,
+            rubric: vec!["lyrics".to_string(), "production".to_string()],
+            ..Competition::for_test("UHOST")
+        };
+
+        let info = format_info(Some(&competition));
+
+        assert!(info.ends_with(
+            "Active competition by <@UHOST>: *trains*\n\
+            Voting: schulze, tie-break: runoff\n\
+            Award categories: lyrics, banger\n\
+            Judges: <@UJ1>, <@UJ2>, deciding 40% of the result, scoring lyrics, production from 0 to 5\n\
+            Rules:\n\
+            • Voting for your own song is not allowed\n\
+            • Up to 2 songs per user\n\
+            • Submitters must vote, or their songs are left out\n\
+            • Submissions close 2026-10-23 18:00 UTC, late songs are not accepted"
+        ));
+        assert!(format_info(None).ends_with("No active competition"));
+    }
+}
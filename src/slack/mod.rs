@@ -1,4 +1,15 @@
+pub mod api;
+pub mod bracket;
 pub mod handler;
+pub mod history;
+pub mod idempotency;
+pub mod info;
 pub mod model;
+pub mod moderation;
 pub mod response;
+pub mod results;
+pub mod roles;
+pub mod roster;
+pub mod season;
+pub mod stats;
 pub mod verify_request;
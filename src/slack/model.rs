@@ -1,18 +1,111 @@
+use crate::sotw_db::model::{is_category_name, CompetitionRef, Role, SongRef, VoteChoice};
 use core::fmt;
 use serde::de::{self, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
 use std::str::FromStr;
-use uuid::Uuid;
+
+// Options which may lead the description of `/sotw start`, written as key=value
+static START_OPTIONS: &[&str] = &[
+    "voting",
+    "budget",
+    "tiebreak",
+    "selfvote",
+    "songs",
+    "mustvote",
+    "late",
+    "anonymous",
+    "secret",
+    "categories",
+    "deadline",
+    "judges",
+    "panel",
+    "rubric",
+    "bracket",
+    "match",
+    "override",
+];
+
+static SEASON_OPTIONS: &[&str] = &["competitions", "points"];
 
 #[derive(PartialEq, Debug, Deserialize)]
 #[serde(untagged)]
 pub enum BotSubCommand {
-    Start(String), // Starts a competition with String as theme
-    Stop,          // Stops the active competition
-    Vote(Uuid),    // Vote for a song based on id
-    List,          // List all songs in current active competition
-    Song(String),  // Add a song to the competition
-    Info,          // Get the build info and stuff
+    Start(String),                         // Starts a competition with String as theme
+    Stop(Option<SongRef>), // Stops the active competition, with the casting vote on a tie
+    Vote(Option<String>, Vec<VoteChoice>), // Vote for songs, or for a song in an award category
+    List,                  // List all songs in current active competition
+    Song(String),          // Add a song to the competition
+    Info,                  // Get the build info and stuff
+    Judge(SongRef, Vec<(String, i32)>), // A judge scores a song on the rubric
+    Bracket,               // Show the matches of the active bracket
+    SeasonStart(String),   // Starts a season with String as its name
+    SeasonEnd,             // Ends the running season early
+    Standings,             // Show the standings of the season
+    History(i64),          // List closed competitions, a page at a time
+    Show(CompetitionRef),  // Show the songs and results of a closed competition
+    Stats(Option<String>), // Show a user's record, the user giving the command without one
+    Host(HostCommand),     // Manage the host roster of the channel
+    Role(RoleCommand),     // Grant, revoke or list roles
+    Handover(String),      // Hand the active competition over to the user
+    EditDescription(String), // Change the description of the active competition
+    Extend(i64),           // Push the deadline of the active competition back by minutes
+    Reopen(Option<CompetitionRef>), // Reopen a closed competition, the channel's latest by default
+    Remove(SongRef, String), // Remove a song from the active competition, with the reason
+    Disqualify(SongRef),   // Leave a song of the active competition out of the tally
+    Ban(BanCommand),       // Ban users from the channel, lift bans or list them
+}
+
+#[derive(PartialEq, Debug, Deserialize)]
+pub enum HostCommand {
+    Join,  // Join the end of the host queue
+    Leave, // Leave the host queue
+    Queue, // Show the host queue, starting with the nominated host
+    Skip,  // Pass the turn to the next host in the queue
+}
+
+#[derive(PartialEq, Debug, Deserialize)]
+pub enum RoleCommand {
+    Add(Role, String),    // Grant the role to the user
+    Remove(Role, String), // Revoke the role from the user
+    List,                 // List the roles which apply in the channel
+}
+
+#[derive(PartialEq, Debug, Deserialize)]
+pub enum BanCommand {
+    Add(String, Option<String>), // Ban the user, with an optional reason
+    Remove(String),              // Lift the ban of the user
+    List,                        // List the users banned in the channel
+}
+
+impl BotSubCommand {
+    /// Stable name used when labeling metrics
+    pub fn name(&self) -> &'static str {
+        match self {
+            BotSubCommand::Start(_) => "start",
+            BotSubCommand::Stop(_) => "stop",
+            BotSubCommand::Vote(_, _) => "vote",
+            BotSubCommand::List => "list",
+            BotSubCommand::Song(_) => "song",
+            BotSubCommand::Info => "info",
+            BotSubCommand::Judge(_, _) => "judge",
+            BotSubCommand::Bracket => "bracket",
+            BotSubCommand::SeasonStart(_) => "season_start",
+            BotSubCommand::SeasonEnd => "season_end",
+            BotSubCommand::Standings => "standings",
+            BotSubCommand::History(_) => "history",
+            BotSubCommand::Show(_) => "show",
+            BotSubCommand::Stats(_) => "stats",
+            BotSubCommand::Host(_) => "host",
+            BotSubCommand::Role(_) => "role",
+            BotSubCommand::Handover(_) => "handover",
+            BotSubCommand::EditDescription(_) => "edit",
+            BotSubCommand::Extend(_) => "extend",
+            BotSubCommand::Reopen(_) => "reopen",
+            BotSubCommand::Remove(_, _) => "remove",
+            BotSubCommand::Disqualify(_) => "disqualify",
+            BotSubCommand::Ban(_) => "ban",
+        }
+    }
 }
 
 // This is the incoming /command from Slack.
@@ -39,6 +132,15 @@ pub struct SlackRequestCommand {
 pub struct SlackResponseCommand {
     pub response_type: String,
     pub text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<SlackAttachment>,
+}
+
+// Secondary content shown below the message text
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SlackAttachment {
+    pub title: String,
+    pub text: String,
 }
 
 fn str_as_cmd<'de, D>(deserializer: D) -> Result<Option<BotSubCommand>, D::Error>
@@ -68,13 +170,34 @@ impl<'de> Visitor<'de> for CmdVisitor {
                         Err(E::custom("cmd missing argument"))
                     }
                 }
-                ("stop", _) => Ok(Some(BotSubCommand::Stop)),
+                ("stop", None) => Ok(Some(BotSubCommand::Stop(None))),
+                ("stop", Some(cmd_val)) => match SongRef::from_str(cmd_val.trim()) {
+                    Ok(casting_vote) => Ok(Some(BotSubCommand::Stop(Some(casting_vote)))),
+                    Err(_) => Err(E::custom("casting vote must be a song number or id")),
+                },
                 ("vote", x) => {
                     if let Some(cmd_val) = x {
-                        if let Ok(song_id) = Uuid::from_str(cmd_val) {
-                            return Ok(Some(BotSubCommand::Vote(song_id)));
+                        let mut words = cmd_val.split_whitespace().peekable();
+                        // A leading word which is not a song names an award category
+                        let category = match words.peek() {
+                            Some(word)
+                                if VoteChoice::from_str(word).is_err()
+                                    && is_category_name(word) =>
+                            {
+                                words.next().map(String::from)
+                            }
+                            _ => None,
+                        };
+                        let choices: Result<Vec<VoteChoice>, ()> =
+                            words.map(VoteChoice::from_str).collect();
+                        match choices {
+                            Ok(choices) if !choices.is_empty() => {
+                                Ok(Some(BotSubCommand::Vote(category, choices)))
+                            }
+                            _ => Err(E::custom(
+                                "songs must be given by number or id, optionally with =value",
+                            )),
                         }
-                        Err(E::custom("song_id is not a valid uuid"))
                     } else {
                         Err(E::custom("cmd missing argument"))
                     }
@@ -89,6 +212,160 @@ impl<'de> Visitor<'de> for CmdVisitor {
                     }
                 }
                 ("info", _) => Ok(Some(BotSubCommand::Info)),
+                ("bracket", _) => Ok(Some(BotSubCommand::Bracket)),
+                ("standings", _) => Ok(Some(BotSubCommand::Standings)),
+                ("history", None) => Ok(Some(BotSubCommand::History(1))),
+                ("history", Some(cmd_val)) => match cmd_val.trim().parse::<i64>() {
+                    Ok(page) if page > 0 => Ok(Some(BotSubCommand::History(page))),
+                    _ => Err(E::custom("history page must be a number from 1")),
+                },
+                ("me", None) | ("stats", None) => Ok(Some(BotSubCommand::Stats(None))),
+                ("stats", Some(cmd_val)) => match parse_user_id(cmd_val.trim()) {
+                    Some(user_id) => Ok(Some(BotSubCommand::Stats(Some(user_id.to_string())))),
+                    None => Err(E::custom("stats takes a user, like @alice")),
+                },
+                ("show", Some(cmd_val)) => match CompetitionRef::from_str(cmd_val.trim()) {
+                    Ok(competition) => Ok(Some(BotSubCommand::Show(competition))),
+                    Err(_) => Err(E::custom(
+                        "competition must be its number in the history or its id",
+                    )),
+                },
+                ("season", x) => match cmd_payload(x.unwrap_or("").trim()) {
+                    Some(("start", Some(name))) if !name.trim().is_empty() => {
+                        Ok(Some(BotSubCommand::SeasonStart(name.to_string())))
+                    }
+                    Some(("end", None)) => Ok(Some(BotSubCommand::SeasonEnd)),
+                    _ => Err(E::custom("season takes start with a name, or end")),
+                },
+                ("host", x) => match x.map(str::trim) {
+                    Some("join") => Ok(Some(BotSubCommand::Host(HostCommand::Join))),
+                    Some("leave") => Ok(Some(BotSubCommand::Host(HostCommand::Leave))),
+                    Some("queue") | None => Ok(Some(BotSubCommand::Host(HostCommand::Queue))),
+                    Some("skip") => Ok(Some(BotSubCommand::Host(HostCommand::Skip))),
+                    _ => Err(E::custom("host takes join, leave, queue or skip")),
+                },
+                ("edit", x) => match cmd_payload(x.unwrap_or("").trim()) {
+                    Some(("description", Some(text))) if !text.trim().is_empty() => Ok(Some(
+                        BotSubCommand::EditDescription(text.trim().to_string()),
+                    )),
+                    _ => Err(E::custom("edit takes description with the new description")),
+                },
+                ("extend", Some(cmd_val)) => match parse_duration(cmd_val) {
+                    Some(duration) => Ok(Some(BotSubCommand::Extend(duration.num_minutes()))),
+                    None => Err(E::custom("extend takes a duration like 90m, 12h, 3d or 1w")),
+                },
+                ("reopen", None) => Ok(Some(BotSubCommand::Reopen(None))),
+                ("reopen", Some(cmd_val)) => match CompetitionRef::from_str(cmd_val.trim()) {
+                    Ok(competition) => Ok(Some(BotSubCommand::Reopen(Some(competition)))),
+                    Err(_) => Err(E::custom(
+                        "competition must be its number in the history or its id",
+                    )),
+                },
+                ("remove", x) => match cmd_payload(x.unwrap_or("").trim()) {
+                    Some((song, Some(reason))) if !reason.trim().is_empty() => {
+                        match SongRef::from_str(song) {
+                            Ok(song) => {
+                                Ok(Some(BotSubCommand::Remove(song, reason.trim().to_string())))
+                            }
+                            Err(_) => Err(E::custom("song must be a song number or id")),
+                        }
+                    }
+                    _ => Err(E::custom(
+                        "remove takes a song and the reason, like `remove 3 not a song`",
+                    )),
+                },
+                ("disqualify", Some(cmd_val)) => match SongRef::from_str(cmd_val.trim()) {
+                    Ok(song) => Ok(Some(BotSubCommand::Disqualify(song))),
+                    Err(_) => Err(E::custom("song must be a song number or id")),
+                },
+                ("ban", Some(cmd_val)) => match cmd_payload(cmd_val.trim()) {
+                    Some((user, reason)) => match parse_user_id(user) {
+                        Some(user_id) => Ok(Some(BotSubCommand::Ban(BanCommand::Add(
+                            user_id.to_string(),
+                            reason
+                                .map(str::trim)
+                                .filter(|reason| !reason.is_empty())
+                                .map(String::from),
+                        )))),
+                        None => Err(E::custom("ban takes a user, like @alice, and a reason")),
+                    },
+                    None => Err(E::custom("ban takes a user, like @alice, and a reason")),
+                },
+                ("unban", Some(cmd_val)) => match parse_user_id(cmd_val.trim()) {
+                    Some(user_id) => Ok(Some(BotSubCommand::Ban(BanCommand::Remove(
+                        user_id.to_string(),
+                    )))),
+                    None => Err(E::custom("unban takes a user, like @alice")),
+                },
+                ("bans", None) => Ok(Some(BotSubCommand::Ban(BanCommand::List))),
+                ("handover", Some(cmd_val)) => match parse_user_id(cmd_val.trim()) {
+                    Some(user_id) => Ok(Some(BotSubCommand::Handover(user_id.to_string()))),
+                    None => Err(E::custom("handover takes the new host, like @alice")),
+                },
+                ("cohost", x) => match cmd_payload(x.unwrap_or("").trim()) {
+                    Some(("add", Some(user))) => match parse_user_id(user.trim()) {
+                        Some(user_id) => Ok(Some(BotSubCommand::Role(RoleCommand::Add(
+                            Role::CoHost,
+                            user_id.to_string(),
+                        )))),
+                        None => Err(E::custom("cohost add takes a user, like @alice")),
+                    },
+                    Some(("remove", Some(user))) => match parse_user_id(user.trim()) {
+                        Some(user_id) => Ok(Some(BotSubCommand::Role(RoleCommand::Remove(
+                            Role::CoHost,
+                            user_id.to_string(),
+                        )))),
+                        None => Err(E::custom("cohost remove takes a user, like @alice")),
+                    },
+                    _ => Err(E::custom("cohost takes add or remove with a user")),
+                },
+                ("roles", None) => Ok(Some(BotSubCommand::Role(RoleCommand::List))),
+                ("role", x) => {
+                    let words: Vec<&str> = x.unwrap_or("").split_whitespace().collect();
+                    let target = |role: &str, user: &str| {
+                        Some((Role::from_str(role).ok()?, parse_user_id(user)?.to_string()))
+                    };
+                    match words.as_slice() {
+                        ["list"] | [] => Ok(Some(BotSubCommand::Role(RoleCommand::List))),
+                        ["add", role, user] => match target(role, user) {
+                            Some((role, user_id)) => {
+                                Ok(Some(BotSubCommand::Role(RoleCommand::Add(role, user_id))))
+                            }
+                            None => Err(E::custom(
+                                "role add takes a role and a user, like `role add cohost @alice`",
+                            )),
+                        },
+                        ["remove", role, user] => match target(role, user) {
+                            Some((role, user_id)) => {
+                                Ok(Some(BotSubCommand::Role(RoleCommand::Remove(role, user_id))))
+                            }
+                            None => Err(E::custom(
+                                "role remove takes a role and a user, like `role remove cohost @alice`",
+                            )),
+                        },
+                        _ => Err(E::custom("role takes add, remove or list")),
+                    }
+                }
+                ("judge", x) => {
+                    let mut words = x.unwrap_or("").split_whitespace();
+                    let judged = words.next().map(SongRef::from_str);
+                    let scores: Option<Vec<(String, i32)>> = words
+                        .map(|word| {
+                            let mut score = word.splitn(2, '=');
+                            let criterion = score.next()?.to_lowercase();
+                            let value = score.next()?.parse().ok()?;
+                            Some((criterion, value))
+                        })
+                        .collect();
+                    match (judged, scores) {
+                        (Some(Ok(judged)), Some(scores)) if !scores.is_empty() => {
+                            Ok(Some(BotSubCommand::Judge(judged, scores)))
+                        }
+                        _ => Err(E::custom(
+                            "judge a song by number or id, with criterion=score for the rubric",
+                        )),
+                    }
+                }
                 (&_, _) => Err(E::custom("unable to match input with cmd")),
             },
             None => Err(E::custom("it's all over")),
@@ -105,9 +382,96 @@ fn cmd_payload(input: &str) -> Option<(&str, Option<&str>)> {
     }
 }
 
+/// Split the input of `/sotw start` into leading options and the description
+pub fn start_options(input: &str) -> (Vec<(&str, &str)>, &str) {
+    leading_options(input, START_OPTIONS)
+}
+
+/// Split the input of `/sotw season start` into leading options and the name
+pub fn season_options(input: &str) -> (Vec<(&str, &str)>, &str) {
+    leading_options(input, SEASON_OPTIONS)
+}
+
+fn leading_options<'a>(input: &'a str, known: &[&str]) -> (Vec<(&'a str, &'a str)>, &'a str) {
+    let mut options = Vec::new();
+    let mut rest = input.trim_start();
+
+    loop {
+        let (word, remainder) = match rest.find(' ') {
+            Some(index) => (&rest[..index], rest[index..].trim_start()),
+            None => (rest, ""),
+        };
+        let mut option = word.splitn(2, '=');
+        match (option.next(), option.next()) {
+            (Some(key), Some(value)) if known.contains(&key) => {
+                options.push((key, value));
+                rest = remainder;
+            }
+            _ => break,
+        }
+    }
+
+    (options, rest)
+}
+
+/// Parse a duration like `90m`, `12h`, `3d` or `1w`
+pub fn parse_duration(input: &str) -> Option<chrono::Duration> {
+    let input = input.trim();
+    let unit_at = input.len().checked_sub(1)?;
+    let amount = input
+        .get(..unit_at)?
+        .parse::<i64>()
+        .ok()
+        .filter(|n| *n > 0)?;
+
+    match input.get(unit_at..)? {
+        "m" => Some(chrono::Duration::minutes(amount)),
+        "h" => Some(chrono::Duration::hours(amount)),
+        "d" => Some(chrono::Duration::days(amount)),
+        "w" => Some(chrono::Duration::weeks(amount)),
+        _ => None,
+    }
+}
+
+/// Slack user id from a mention like `<@U024BE7LH|alice>`, or the bare id
+pub fn parse_user_id(input: &str) -> Option<&str> {
+    let input = input.trim();
+    let id = match input.strip_prefix("<@").and_then(|m| m.strip_suffix('>')) {
+        Some(mention) => mention.split('|').next()?,
+        None => input,
+    };
+
+    let is_user_id = id.len() > 1
+        && id.starts_with(&['U', 'W'][..])
+        && id
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+    if is_user_id {
+        Some(id)
+    } else {
+        None
+    }
+}
+
+/// Parse a yes or no option value
+pub fn parse_switch(input: &str) -> Option<bool> {
+    match input.to_lowercase().as_str() {
+        "yes" | "on" | "true" => Some(true),
+        "no" | "off" | "false" => Some(false),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::slack::model::{cmd_payload, SlackRequestCommand};
+    use crate::slack::model::{
+        cmd_payload, parse_duration, parse_user_id, season_options, start_options, BanCommand,
+        BotSubCommand, CmdVisitor, HostCommand, RoleCommand, SlackRequestCommand,
+    };
+    use crate::sotw_db::model::{CompetitionRef, Role, SongRef, VoteChoice};
+    use serde::de::value::Error;
+    use serde::de::Visitor;
+    use uuid::Uuid;
 
     #[test]
     fn test_dez() {
@@ -143,4 +507,324 @@ mod tests {
             "should not contain a cmd argument "
         );
     }
+
+    #[test]
+    fn test_vote_ballot() {
+        let id = Uuid::new_v4();
+
+        let choice = |song: SongRef, value: Option<i32>| VoteChoice { song, value };
+
+        let ranked = CmdVisitor.visit_str::<Error>("vote 3 #1 5").unwrap();
+        let by_id = CmdVisitor
+            .visit_str::<Error>(&format!("vote {}", id))
+            .unwrap();
+        let scored = CmdVisitor.visit_str::<Error>("vote 3=5 1=0").unwrap();
+        let category = CmdVisitor.visit_str::<Error>("vote lyrics 3").unwrap();
+        let invalid = CmdVisitor.visit_str::<Error>("vote 0");
+        let invalid_score = CmdVisitor.visit_str::<Error>("vote 1=high");
+        let missing_song = CmdVisitor.visit_str::<Error>("vote lyrics");
+
+        assert_eq!(
+            ranked,
+            Some(BotSubCommand::Vote(
+                None,
+                vec![
+                    choice(SongRef::Number(3), None),
+                    choice(SongRef::Number(1), None),
+                    choice(SongRef::Number(5), None)
+                ]
+            )),
+            "songs should be ranked in the order given"
+        );
+        assert_eq!(
+            by_id,
+            Some(BotSubCommand::Vote(
+                None,
+                vec![choice(SongRef::Id(id), None)]
+            ))
+        );
+        assert_eq!(
+            scored,
+            Some(BotSubCommand::Vote(
+                None,
+                vec![
+                    choice(SongRef::Number(3), Some(5)),
+                    choice(SongRef::Number(1), Some(0))
+                ]
+            ))
+        );
+        assert_eq!(
+            category,
+            Some(BotSubCommand::Vote(
+                Some("lyrics".to_string()),
+                vec![choice(SongRef::Number(3), None)]
+            ))
+        );
+        assert!(invalid_score.is_err(), "values must be numbers");
+        assert!(invalid.is_err(), "song numbers start at 1");
+        assert!(missing_song.is_err(), "a category vote needs a song");
+    }
+
+    #[test]
+    fn test_stop_casting_vote() {
+        let stop = CmdVisitor.visit_str::<Error>("stop").unwrap();
+        let casting_vote = CmdVisitor.visit_str::<Error>("stop #2").unwrap();
+        let invalid = CmdVisitor.visit_str::<Error>("stop now");
+
+        assert_eq!(stop, Some(BotSubCommand::Stop(None)));
+        assert_eq!(
+            casting_vote,
+            Some(BotSubCommand::Stop(Some(SongRef::Number(2))))
+        );
+        assert!(invalid.is_err(), "the casting vote must name a song");
+    }
+
+    #[test]
+    fn test_start_options() {
+        let (options, description) = start_options("voting=ranked  Songs about trains");
+        let (no_options, plain) = start_options("1+1=2 songs");
+
+        assert_eq!(options, vec![("voting", "ranked")]);
+        assert_eq!(description, "Songs about trains");
+        assert!(
+            no_options.is_empty(),
+            "unknown keys are part of the description"
+        );
+        assert_eq!(plain, "1+1=2 songs");
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90m"), Some(chrono::Duration::minutes(90)));
+        assert_eq!(parse_duration("3d"), Some(chrono::Duration::days(3)));
+        assert_eq!(parse_duration("1w"), Some(chrono::Duration::weeks(1)));
+        assert_eq!(parse_duration("0h"), None, "durations must be positive");
+        assert_eq!(parse_duration("3"), None, "a unit is required");
+        assert_eq!(parse_duration("d"), None);
+        assert_eq!(parse_duration("2y"), None);
+    }
+
+    #[test]
+    fn test_judge() {
+        let judged = CmdVisitor
+            .visit_str::<Error>("judge #2 lyrics=4 Production=5")
+            .unwrap();
+        let no_scores = CmdVisitor.visit_str::<Error>("judge 2");
+        let invalid = CmdVisitor.visit_str::<Error>("judge 2 lyrics=great");
+
+        assert_eq!(
+            judged,
+            Some(BotSubCommand::Judge(
+                SongRef::Number(2),
+                vec![("lyrics".to_string(), 4), ("production".to_string(), 5)]
+            ))
+        );
+        assert!(no_scores.is_err(), "a judge gives at least one score");
+        assert!(invalid.is_err(), "scores must be numbers");
+    }
+
+    #[test]
+    fn test_season() {
+        let start = CmdVisitor
+            .visit_str::<Error>("season start competitions=13 2026 Q4")
+            .unwrap();
+        let end = CmdVisitor.visit_str::<Error>("season end").unwrap();
+        let unnamed = CmdVisitor.visit_str::<Error>("season start");
+
+        assert_eq!(
+            start,
+            Some(BotSubCommand::SeasonStart(
+                "competitions=13 2026 Q4".to_string()
+            ))
+        );
+        assert_eq!(end, Some(BotSubCommand::SeasonEnd));
+        assert!(unnamed.is_err(), "a season needs a name");
+        assert_eq!(
+            season_options("competitions=13 points=5,3,1 2026 Q4"),
+            (vec![("competitions", "13"), ("points", "5,3,1")], "2026 Q4")
+        );
+    }
+
+    #[test]
+    fn test_history_and_show() {
+        let history = CmdVisitor.visit_str::<Error>("history").unwrap();
+        let page = CmdVisitor.visit_str::<Error>("history 3").unwrap();
+        let show = CmdVisitor.visit_str::<Error>("show #2").unwrap();
+
+        assert_eq!(history, Some(BotSubCommand::History(1)));
+        assert_eq!(page, Some(BotSubCommand::History(3)));
+        assert_eq!(show, Some(BotSubCommand::Show(CompetitionRef::Recent(2))));
+        assert!(CmdVisitor.visit_str::<Error>("history 0").is_err());
+        assert!(CmdVisitor.visit_str::<Error>("show").is_err());
+    }
+
+    #[test]
+    fn test_stats() {
+        let me = CmdVisitor.visit_str::<Error>("me").unwrap();
+        let other = CmdVisitor
+            .visit_str::<Error>("stats <@U024BE7LH|alice>")
+            .unwrap();
+
+        assert_eq!(me, Some(BotSubCommand::Stats(None)));
+        assert_eq!(
+            other,
+            Some(BotSubCommand::Stats(Some("U024BE7LH".to_string())))
+        );
+        assert!(CmdVisitor.visit_str::<Error>("stats alice").is_err());
+    }
+
+    #[test]
+    fn test_host() {
+        let join = CmdVisitor.visit_str::<Error>("host join").unwrap();
+        let queue = CmdVisitor.visit_str::<Error>("host").unwrap();
+        let skip = CmdVisitor.visit_str::<Error>("host skip").unwrap();
+
+        assert_eq!(join, Some(BotSubCommand::Host(HostCommand::Join)));
+        assert_eq!(queue, Some(BotSubCommand::Host(HostCommand::Queue)));
+        assert_eq!(skip, Some(BotSubCommand::Host(HostCommand::Skip)));
+        assert!(CmdVisitor.visit_str::<Error>("host next").is_err());
+        assert_eq!(
+            start_options("override=yes Trains"),
+            (vec![("override", "yes")], "Trains")
+        );
+    }
+
+    #[test]
+    fn test_role() {
+        let add = CmdVisitor
+            .visit_str::<Error>("role add channel-admin <@U024BE7LH|alice>")
+            .unwrap();
+        let remove = CmdVisitor
+            .visit_str::<Error>("role remove cohost <@U024BE7LH>")
+            .unwrap();
+        let list = CmdVisitor.visit_str::<Error>("roles").unwrap();
+
+        assert_eq!(
+            add,
+            Some(BotSubCommand::Role(RoleCommand::Add(
+                Role::ChannelAdmin,
+                "U024BE7LH".to_string()
+            )))
+        );
+        assert_eq!(
+            remove,
+            Some(BotSubCommand::Role(RoleCommand::Remove(
+                Role::CoHost,
+                "U024BE7LH".to_string()
+            )))
+        );
+        assert_eq!(list, Some(BotSubCommand::Role(RoleCommand::List)));
+        assert!(CmdVisitor
+            .visit_str::<Error>("role add judge @alice")
+            .is_err());
+    }
+
+    #[test]
+    fn test_edit_extend_reopen() {
+        let edit = CmdVisitor
+            .visit_str::<Error>("edit description Songs about trains")
+            .unwrap();
+        let extend = CmdVisitor.visit_str::<Error>("extend 2d").unwrap();
+        let reopen = CmdVisitor.visit_str::<Error>("reopen").unwrap();
+        let reopen_past = CmdVisitor.visit_str::<Error>("reopen #2").unwrap();
+
+        assert_eq!(
+            edit,
+            Some(BotSubCommand::EditDescription(
+                "Songs about trains".to_string()
+            ))
+        );
+        assert_eq!(extend, Some(BotSubCommand::Extend(2 * 24 * 60)));
+        assert_eq!(reopen, Some(BotSubCommand::Reopen(None)));
+        assert_eq!(
+            reopen_past,
+            Some(BotSubCommand::Reopen(Some(CompetitionRef::Recent(2))))
+        );
+        assert!(CmdVisitor.visit_str::<Error>("edit description").is_err());
+        assert!(CmdVisitor.visit_str::<Error>("extend soon").is_err());
+    }
+
+    #[test]
+    fn test_handover_and_cohost() {
+        let handover = CmdVisitor
+            .visit_str::<Error>("handover <@U024BE7LH|alice>")
+            .unwrap();
+        let cohost = CmdVisitor
+            .visit_str::<Error>("cohost add <@U024BE7LH>")
+            .unwrap();
+
+        assert_eq!(
+            handover,
+            Some(BotSubCommand::Handover("U024BE7LH".to_string()))
+        );
+        assert_eq!(
+            cohost,
+            Some(BotSubCommand::Role(RoleCommand::Add(
+                Role::CoHost,
+                "U024BE7LH".to_string()
+            )))
+        );
+        assert!(CmdVisitor.visit_str::<Error>("handover").is_err());
+        assert!(CmdVisitor.visit_str::<Error>("cohost add alice").is_err());
+    }
+
+    #[test]
+    fn test_moderation() {
+        let remove = CmdVisitor
+            .visit_str::<Error>("remove #3 not a song")
+            .unwrap();
+        let ban = CmdVisitor
+            .visit_str::<Error>("ban <@U024BE7LH|alice> spamming links")
+            .unwrap();
+
+        assert_eq!(
+            remove,
+            Some(BotSubCommand::Remove(
+                SongRef::Number(3),
+                "not a song".to_string()
+            ))
+        );
+        assert_eq!(
+            CmdVisitor.visit_str::<Error>("disqualify 2").unwrap(),
+            Some(BotSubCommand::Disqualify(SongRef::Number(2)))
+        );
+        assert_eq!(
+            ban,
+            Some(BotSubCommand::Ban(BanCommand::Add(
+                "U024BE7LH".to_string(),
+                Some("spamming links".to_string())
+            )))
+        );
+        assert_eq!(
+            CmdVisitor.visit_str::<Error>("ban <@U024BE7LH>").unwrap(),
+            Some(BotSubCommand::Ban(BanCommand::Add(
+                "U024BE7LH".to_string(),
+                None
+            )))
+        );
+        assert_eq!(
+            CmdVisitor.visit_str::<Error>("unban U024BE7LH").unwrap(),
+            Some(BotSubCommand::Ban(BanCommand::Remove(
+                "U024BE7LH".to_string()
+            )))
+        );
+        assert_eq!(
+            CmdVisitor.visit_str::<Error>("bans").unwrap(),
+            Some(BotSubCommand::Ban(BanCommand::List))
+        );
+        assert!(
+            CmdVisitor.visit_str::<Error>("remove 3").is_err(),
+            "a reason must be given"
+        );
+        assert!(CmdVisitor.visit_str::<Error>("ban alice").is_err());
+    }
+
+    #[test]
+    fn test_parse_user_id() {
+        assert_eq!(parse_user_id("<@U024BE7LH|alice>"), Some("U024BE7LH"));
+        assert_eq!(parse_user_id("<@W024BE7LH>"), Some("W024BE7LH"));
+        assert_eq!(parse_user_id("U024BE7LH"), Some("U024BE7LH"));
+        assert_eq!(parse_user_id("@alice"), None);
+        assert_eq!(parse_user_id("<#C024BE7LR|general>"), None);
+    }
 }
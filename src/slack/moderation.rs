@@ -0,0 +1,51 @@
+use crate::sotw_db::model::Ban;
+
+/// Users banned in a channel, with the reason they were banned for
+pub fn format_bans(bans: &[Ban]) -> String {
+    if bans.is_empty() {
+        return "No one is banned in this channel".to_string();
+    }
+
+    let mut lines = vec!["*Banned:*".to_string()];
+    lines.extend(bans.iter().map(|ban| {
+        let banned = format!(
+            "<@{}>, by <@{}> on {}",
+            ban.user_id,
+            ban.banned_by,
+            ban.banned.format("%Y-%m-%d")
+        );
+        match &ban.reason {
+            Some(reason) => format!("{}: {}", banned, reason),
+            None => banned,
+        }
+    }));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::slack::moderation::format_bans;
+    use crate::sotw_db::model::Ban;
+    use chrono::{DateTime, Utc};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_format_bans() {
+        let ban = |user_id: &str, reason: Option<&str>| Ban {
+            id: Uuid::new_v4(),
+            user_id: user_id.to_string(),
+            channel_id: "CSOTW".to_string(),
+            reason: reason.map(String::from),
+            banned_by: "UHOST".to_string(),
+            banned: "2026-10-18T18:00:00Z".parse::<DateTime<Utc>>().unwrap(),
+        };
+
+        assert_eq!(
+            format_bans(&[ban("UA", Some("spamming links")), ban("UB", None)]),
+            "*Banned:*\n\
+            <@UA>, by <@UHOST> on 2026-10-18: spamming links\n\
+            <@UB>, by <@UHOST> on 2026-10-18"
+        );
+        assert_eq!(format_bans(&[]), "No one is banned in this channel");
+    }
+}
@@ -1,29 +1,98 @@
-use crate::slack::model::SlackResponseCommand;
+use crate::metrics::SLACK_DELIVERY_FAILURES_TOTAL;
+use crate::slack::model::{SlackAttachment, SlackResponseCommand};
 use reqwest::Client;
+use tracing::Instrument;
 
 /// Simple default responses to Slack channels
 /// One method for each response type, nothing fancy.
 
 pub async fn in_channel_response(response_url: String, text: String, http_client: &Client) {
-    response(response_url, "in_channel".to_string(), text, http_client).await
+    response(
+        response_url,
+        "in_channel".to_string(),
+        text,
+        vec![],
+        http_client,
+    )
+    .await
+}
+
+pub async fn ephemeral_response(response_url: String, text: String, http_client: &Client) {
+    response(
+        response_url,
+        "ephemeral".to_string(),
+        text,
+        vec![],
+        http_client,
+    )
+    .await
+}
+
+/// Announcements go to the whole channel unless disabled in the feature config
+pub async fn announce_response(
+    in_channel: bool,
+    response_url: String,
+    text: String,
+    http_client: &Client,
+) {
+    if in_channel {
+        in_channel_response(response_url, text, http_client).await
+    } else {
+        ephemeral_response(response_url, text, http_client).await
+    }
+}
+
+/// Same as `announce_response`, with attachments below the text
+pub async fn announce_with_attachments(
+    in_channel: bool,
+    response_url: String,
+    text: String,
+    attachments: Vec<SlackAttachment>,
+    http_client: &Client,
+) {
+    let response_type = if in_channel {
+        "in_channel"
+    } else {
+        "ephemeral"
+    };
+
+    response(
+        response_url,
+        response_type.to_string(),
+        text,
+        attachments,
+        http_client,
+    )
+    .await
 }
 
 pub async fn response(
     response_url: String,
     response_type: String,
     text: String,
+    attachments: Vec<SlackAttachment>,
     http_client: &Client,
 ) {
+    let span = info_span!(
+        "slack_response",
+        otel.kind = "client",
+        response_type = response_type.as_str()
+    );
+
     let result = http_client
         .post(&response_url)
         .json::<SlackResponseCommand>(&SlackResponseCommand {
             response_type,
             text,
+            attachments,
         })
         .send()
-        .await;
+        .instrument(span)
+        .await
+        .and_then(|r| r.error_for_status());
 
     if result.is_err() {
+        SLACK_DELIVERY_FAILURES_TOTAL.inc();
         warn!("Unable to send response to slack!")
     }
 
@@ -0,0 +1,467 @@
+use crate::slack::model::SlackAttachment;
+use crate::sotw_db::model::{Competition, Song, VotingMethod};
+use crate::sotw_db::tally::{Combined, Tally};
+use uuid::Uuid;
+
+/// Announcement for a closed competition, with the count for every round
+/// of the tally and the winning song. A tie for the win lists the tied songs,
+/// the runoff vote if one was held, and how the tie was broken.
+/// The winners of the award categories follow the overall winner.
+/// With a panel of judges the audience and panel shares of every song are
+/// shown, and the combined result decides the winner.
+pub fn format_results(
+    competition: &Competition,
+    songs: &[Song],
+    tally: &Tally,
+    runoff: Option<&Tally>,
+    categories: &[(String, Tally)],
+    panel: Option<&Combined>,
+) -> String {
+    let mut lines = vec![format!(
+        "<@{}> *ended* competition with description: *{}*",
+        competition.user_id, competition.description
+    )];
+
+    if competition.rules.submitters_must_vote {
+        let left_out: Vec<String> = songs
+            .iter()
+            .filter(|song| !tally.ranking.iter().flatten().any(|id| *id == song.id))
+            .map(|song| format!("#{}", song.number))
+            .collect();
+        if !left_out.is_empty() {
+            lines.push(format!(
+                "Left out, the submitter did not vote: {}",
+                left_out.join(", ")
+            ));
+        }
+    }
+
+    let result_winners = panel.map_or(&tally.winners, |combined| &combined.winners);
+    if result_winners.is_empty() {
+        lines.push("No votes were cast.".to_string());
+        lines.extend(format_categories(songs, categories));
+        return lines.join("\n");
+    }
+
+    match tally.method {
+        VotingMethod::Plurality
+        | VotingMethod::Approval
+        | VotingMethod::Score
+        | VotingMethod::Points => {
+            if let Some(round) = tally.rounds.first() {
+                lines.push(format!(
+                    "{}: {}",
+                    totals_label(tally.method),
+                    format_counts(songs, &round.counts)
+                ));
+            }
+        }
+        VotingMethod::InstantRunoff => {
+            lines.push("Instant runoff:".to_string());
+            for (index, round) in tally.rounds.iter().enumerate() {
+                let mut line = format!(
+                    "Round {}: {}",
+                    index + 1,
+                    format_counts(songs, &round.counts)
+                );
+                if !round.eliminated.is_empty() {
+                    let eliminated: Vec<String> = round
+                        .eliminated
+                        .iter()
+                        .map(|song_id| song_number(songs, song_id))
+                        .collect();
+                    line.push_str(&format!(" - eliminated {}", eliminated.join(", ")));
+                }
+                lines.push(line);
+            }
+        }
+        VotingMethod::Schulze => {
+            lines.push("Schulze ranking:".to_string());
+            for (index, place) in tally.ranking.iter().enumerate() {
+                let place: Vec<String> = place
+                    .iter()
+                    .map(|song_id| song_number(songs, song_id))
+                    .collect();
+                lines.push(format!("{}. {}", index + 1, place.join(", ")));
+            }
+        }
+    }
+
+    if let Some(combined) = panel {
+        lines.push(format!(
+            "Audience {}%, panel {}%:",
+            100 - combined.panel_weight,
+            combined.panel_weight
+        ));
+        for song_id in combined.ranking.iter().flatten() {
+            let (audience, panel, result) = combined.shares(song_id);
+            lines.push(format!(
+                "{} audience {:.1}%, panel {:.1}%, combined {:.1}%",
+                song_number(songs, song_id),
+                audience,
+                panel,
+                result
+            ));
+        }
+    }
+
+    let winners: Vec<String> = result_winners
+        .iter()
+        .map(|song_id| format_song(songs, song_id))
+        .collect();
+    if winners.len() > 1 {
+        lines.push(format!("*Tied:* {}", winners.join(", ")));
+    }
+    if let Some(runoff) = runoff {
+        match runoff.rounds.first() {
+            Some(round) if !runoff.winners.is_empty() => {
+                lines.push(format!("Runoff: {}", format_counts(songs, &round.counts)))
+            }
+            _ => lines.push("Runoff: no votes were cast".to_string()),
+        }
+    }
+
+    let winner = match (competition.winner_song_id, result_winners.as_slice()) {
+        (Some(winner), _) => Some(winner),
+        (None, [winner]) => Some(*winner),
+        _ => None,
+    };
+    if let Some(winner) = winner {
+        let mut line = format!("*Winner:* {}", format_song(songs, &winner));
+        if let Some(used) = &competition.tie_break_used {
+            line.push_str(&format!(" - tie broken by {}", used));
+            if let Some(seed) = competition.tie_break_seed {
+                line.push_str(&format!(", seed {}", seed));
+            }
+        }
+        lines.push(line);
+    }
+    lines.extend(format_categories(songs, categories));
+
+    lines.join("\n")
+}
+
+fn format_categories(songs: &[Song], categories: &[(String, Tally)]) -> Vec<String> {
+    categories
+        .iter()
+        .map(|(category, category_tally)| {
+            let votes = |song_id: &Uuid| {
+                category_tally
+                    .rounds
+                    .first()
+                    .and_then(|round| round.counts.iter().find(|(id, _)| id == song_id))
+                    .map_or(0, |(_, count)| *count)
+            };
+            match category_tally.winners.as_slice() {
+                [] => format!("*{}:* no votes", category),
+                [winner] => format!(
+                    "*{}:* {} ({} votes)",
+                    category,
+                    format_song(songs, winner),
+                    votes(winner)
+                ),
+                tied => {
+                    let tied: Vec<String> = tied
+                        .iter()
+                        .map(|song_id| format_song(songs, song_id))
+                        .collect();
+                    format!(
+                        "*{}:* tied {} ({} votes each)",
+                        category,
+                        tied.join(", "),
+                        votes(&category_tally.winners[0])
+                    )
+                }
+            }
+        })
+        .collect()
+}
+
+/// Table of the pairwise preferences behind a Schulze ranking,
+/// each cell counts the ballots preferring the row's song over the column's.
+pub fn pairwise_attachment(songs: &[Song], tally: &Tally) -> Option<SlackAttachment> {
+    let pairwise = tally.pairwise.as_ref()?;
+    if tally.winners.is_empty() {
+        return None;
+    }
+
+    let labels: Vec<String> = pairwise
+        .songs
+        .iter()
+        .map(|song_id| song_number(songs, song_id))
+        .collect();
+    let width = labels
+        .iter()
+        .map(|label| label.len())
+        .chain(
+            pairwise
+                .preferences
+                .iter()
+                .flatten()
+                .map(|count| count.to_string().len()),
+        )
+        .max()
+        .unwrap_or(1);
+
+    let header: Vec<String> = labels
+        .iter()
+        .map(|label| format!("{:>w$}", label, w = width))
+        .collect();
+    let mut rows = vec![format!("{:w$} {}", "", header.join(" "), w = width)];
+    for (i, label) in labels.iter().enumerate() {
+        let cells: Vec<String> = pairwise.preferences[i]
+            .iter()
+            .enumerate()
+            .map(|(j, count)| {
+                if i == j {
+                    format!("{:>w$}", "-", w = width)
+                } else {
+                    format!("{:>w$}", count, w = width)
+                }
+            })
+            .collect();
+        rows.push(format!("{:<w$} {}", label, cells.join(" "), w = width));
+    }
+
+    Some(SlackAttachment {
+        title: "Pairwise preferences, row over column".to_string(),
+        text: format!("```\n{}\n```", rows.join("\n")),
+    })
+}
+
+fn totals_label(method: VotingMethod) -> &'static str {
+    match method {
+        VotingMethod::Approval => "Approvals",
+        VotingMethod::Score => "Scores",
+        VotingMethod::Points => "Points",
+        _ => "Votes",
+    }
+}
+
+fn format_counts(songs: &[Song], counts: &[(Uuid, u32)]) -> String {
+    counts
+        .iter()
+        .map(|(song_id, count)| format!("{} ({})", song_number(songs, song_id), count))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn song_number(songs: &[Song], song_id: &Uuid) -> String {
+    songs
+        .iter()
+        .find(|song| song.id == *song_id)
+        .map_or_else(|| song_id.to_string(), |song| format!("#{}", song.number))
+}
+
+pub fn format_song(songs: &[Song], song_id: &Uuid) -> String {
+    songs.iter().find(|song| song.id == *song_id).map_or_else(
+        || song_id.to_string(),
+        |song| format!("#{} {} by <@{}>", song.number, song.song_uri, song.user_id),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::slack::results::{format_results, pairwise_attachment};
+    use crate::sotw_db::model::{Competition, Song, TieBreak, VotingMethod};
+    use crate::sotw_db::tally::{combine, points, tally, Ballot};
+    use uuid::Uuid;
+
+    fn song(number: i32, competition_id: Uuid) -> Song {
+        Song {
+            id: Uuid::new_v4(),
+            user_id: format!("U{}", number),
+            song_uri: format!("http://example.org/{}", number),
+            competition_id,
+            number,
+            place: None,
+            disqualified: false,
+        }
+    }
+
+    fn competition(voting_method: VotingMethod) -> Competition {
+        Competition {
+            voting_method,
+            ended: Some(chrono::Utc::now()),
+            is_active: false,
+            ..Competition::for_test("UHOST")
+        }
+    }
+
+    #[test]
+    fn test_instant_runoff_rounds() {
+        let competition = competition(VotingMethod::InstantRunoff);
+        let songs: Vec<Song> = (1..=3).map(|n| song(n, competition.id)).collect();
+        let ids: Vec<Uuid> = songs.iter().map(|s| s.id).collect();
+        let ballots = vec![
+            Ballot::ranked(vec![ids[0]]),
+            Ballot::ranked(vec![ids[1]]),
+            Ballot::ranked(vec![ids[2], ids[1]]),
+            Ballot::ranked(vec![ids[1]]),
+            Ballot::ranked(vec![ids[0]]),
+        ];
+
+        let results = format_results(
+            &competition,
+            &songs,
+            &tally(VotingMethod::InstantRunoff, &ids, &ballots),
+            None,
+            &[],
+            None,
+        );
+
+        assert_eq!(
+            results,
+            "<@UHOST> *ended* competition with description: *trains*\n\
+            Instant runoff:\n\
+            Round 1: #1 (2), #2 (2), #3 (1) - eliminated #3\n\
+            Round 2: #2 (3), #1 (2)\n\
+            *Winner:* #2 http://example.org/2 by <@U2>"
+        );
+    }
+
+    #[test]
+    fn test_schulze_ranking() {
+        let competition = competition(VotingMethod::Schulze);
+        let songs: Vec<Song> = (1..=3).map(|n| song(n, competition.id)).collect();
+        let ids: Vec<Uuid> = songs.iter().map(|s| s.id).collect();
+        let ballots = vec![
+            Ballot::ranked(vec![ids[2], ids[0]]),
+            Ballot::ranked(vec![ids[2]]),
+        ];
+        let tally = tally(VotingMethod::Schulze, &ids, &ballots);
+
+        let results = format_results(&competition, &songs, &tally, None, &[], None);
+        let attachment = pairwise_attachment(&songs, &tally).unwrap();
+
+        assert!(results.contains("Schulze ranking:\n1. #3\n2. #1\n3. #2\n"));
+        assert_eq!(
+            attachment.text,
+            "```\n   #1 #2 #3\n#1  -  1  0\n#2  0  -  0\n#3  2  2  -\n```"
+        );
+    }
+
+    #[test]
+    fn test_tie_broken_by_draw() {
+        let mut competition = competition(VotingMethod::Plurality);
+        let songs: Vec<Song> = (1..=2).map(|n| song(n, competition.id)).collect();
+        let ids: Vec<Uuid> = songs.iter().map(|s| s.id).collect();
+        let ballots = vec![Ballot::ranked(vec![ids[0]]), Ballot::ranked(vec![ids[1]])];
+        competition.tie_break = TieBreak::Random;
+        competition.winner_song_id = Some(ids[1]);
+        competition.tie_break_used = Some("seeded random draw".to_string());
+        competition.tie_break_seed = Some(42);
+
+        let results = format_results(
+            &competition,
+            &songs,
+            &tally(VotingMethod::Plurality, &ids, &ballots),
+            None,
+            &[],
+            None,
+        );
+
+        assert!(results.ends_with(
+            "Votes: #1 (1), #2 (1)\n\
+            *Tied:* #1 http://example.org/1 by <@U1>, #2 http://example.org/2 by <@U2>\n\
+            *Winner:* #2 http://example.org/2 by <@U2> - tie broken by seeded random draw, seed 42"
+        ));
+    }
+
+    #[test]
+    fn test_runoff_results() {
+        let mut competition = competition(VotingMethod::Plurality);
+        let songs: Vec<Song> = (1..=2).map(|n| song(n, competition.id)).collect();
+        let ids: Vec<Uuid> = songs.iter().map(|s| s.id).collect();
+        let ballots = vec![Ballot::ranked(vec![ids[0]]), Ballot::ranked(vec![ids[1]])];
+        let runoff_ballots = vec![Ballot::ranked(vec![ids[1]])];
+        competition.winner_song_id = Some(ids[1]);
+        competition.tie_break_used = Some("runoff vote".to_string());
+
+        let results = format_results(
+            &competition,
+            &songs,
+            &tally(VotingMethod::Plurality, &ids, &ballots),
+            Some(&tally(VotingMethod::Plurality, &ids, &runoff_ballots)),
+            &[],
+            None,
+        );
+
+        assert!(results.contains("Runoff: #2 (1), #1 (0)\n*Winner:* #2"));
+        assert!(results.ends_with("tie broken by runoff vote"));
+    }
+
+    #[test]
+    fn test_category_winners() {
+        let competition = competition(VotingMethod::Plurality);
+        let songs: Vec<Song> = (1..=3).map(|n| song(n, competition.id)).collect();
+        let ids: Vec<Uuid> = songs.iter().map(|s| s.id).collect();
+        let ballots = vec![Ballot::ranked(vec![ids[0]])];
+        let lyrics = vec![
+            Ballot::ranked(vec![ids[2]]),
+            Ballot::ranked(vec![ids[2]]),
+            Ballot::ranked(vec![ids[0]]),
+        ];
+        let banger = vec![Ballot::ranked(vec![ids[0]]), Ballot::ranked(vec![ids[1]])];
+        let categories = vec![
+            (
+                "lyrics".to_string(),
+                tally(VotingMethod::Plurality, &ids, &lyrics),
+            ),
+            (
+                "banger".to_string(),
+                tally(VotingMethod::Plurality, &ids, &banger),
+            ),
+            (
+                "vibes".to_string(),
+                tally(VotingMethod::Plurality, &ids, &[]),
+            ),
+        ];
+
+        let results = format_results(
+            &competition,
+            &songs,
+            &tally(VotingMethod::Plurality, &ids, &ballots),
+            None,
+            &categories,
+            None,
+        );
+
+        assert!(results.ends_with(
+            "*Winner:* #1 http://example.org/1 by <@U1>\n\
+            *lyrics:* #3 http://example.org/3 by <@U3> (2 votes)\n\
+            *banger:* tied #1 http://example.org/1 by <@U1>, #2 http://example.org/2 by <@U2> (1 votes each)\n\
+            *vibes:* no votes"
+        ));
+    }
+
+    #[test]
+    fn test_panel_shares() {
+        let mut competition = competition(VotingMethod::Plurality);
+        let songs: Vec<Song> = (1..=3).map(|n| song(n, competition.id)).collect();
+        let ids: Vec<Uuid> = songs.iter().map(|s| s.id).collect();
+        let ballots = vec![
+            Ballot::ranked(vec![ids[0]]),
+            Ballot::ranked(vec![ids[0]]),
+            Ballot::ranked(vec![ids[0]]),
+            Ballot::ranked(vec![ids[1]]),
+        ];
+        let audience = tally(VotingMethod::Plurality, &ids, &ballots);
+        // Rubric scores summed over the judges
+        let panel_points = vec![(ids[0], 3), (ids[1], 12), (ids[2], 5)];
+        let combined = combine(&ids, points(&ids, &audience), panel_points, 40);
+        competition.judges = vec!["UJ1".to_string(), "UJ2".to_string()];
+        competition.panel_weight = 40;
+
+        let results = format_results(&competition, &songs, &audience, None, &[], Some(&combined));
+
+        assert!(results.ends_with(
+            "Votes: #1 (3), #2 (1), #3 (0)\n\
+            Audience 60%, panel 40%:\n\
+            #1 audience 75.0%, panel 15.0%, combined 51.0%\n\
+            #2 audience 25.0%, panel 60.0%, combined 39.0%\n\
+            #3 audience 0.0%, panel 25.0%, combined 10.0%\n\
+            *Winner:* #1 http://example.org/1 by <@U1>"
+        ));
+    }
+}
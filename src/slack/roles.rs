@@ -0,0 +1,69 @@
+use crate::sotw_db::model::{Role, UserRole};
+
+/// Roles which apply in a channel by role, bot admins from the configuration
+/// first
+pub fn format_roles(roles: &[UserRole], admin_users: &[String]) -> String {
+    let groups = [
+        (Role::BotAdmin, "Bot admins"),
+        (Role::ChannelAdmin, "Channel admins"),
+        (Role::CoHost, "Co-hosts"),
+    ];
+
+    let mut lines = vec![];
+    for (role, title) in groups.iter() {
+        let mut users: Vec<&str> = vec![];
+        if *role == Role::BotAdmin {
+            users.extend(admin_users.iter().map(String::as_str));
+        }
+        for granted in roles.iter().filter(|granted| granted.role == *role) {
+            if !users.contains(&granted.user_id.as_str()) {
+                users.push(&granted.user_id);
+            }
+        }
+
+        if !users.is_empty() {
+            let users: Vec<String> = users.iter().map(|user| format!("<@{}>", user)).collect();
+            lines.push(format!("*{}:* {}", title, users.join(", ")));
+        }
+    }
+
+    if lines.is_empty() {
+        return "No roles apply in this channel".to_string();
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::slack::roles::format_roles;
+    use crate::sotw_db::model::{Role, UserRole};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn granted(user_id: &str, role: Role) -> UserRole {
+        UserRole {
+            id: Uuid::new_v4(),
+            user_id: user_id.to_string(),
+            role,
+            channel_id: None,
+            competition_id: None,
+            granted_by: "UADMIN".to_string(),
+            granted: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_format_roles() {
+        let roles = vec![
+            granted("UA", Role::CoHost),
+            granted("UADMIN", Role::BotAdmin),
+            granted("UB", Role::BotAdmin),
+        ];
+
+        assert_eq!(
+            format_roles(&roles, &["UADMIN".to_string()]),
+            "*Bot admins:* <@UADMIN>, <@UB>\n*Co-hosts:* <@UA>"
+        );
+        assert_eq!(format_roles(&[], &[]), "No roles apply in this channel");
+    }
+}
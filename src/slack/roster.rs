@@ -0,0 +1,66 @@
+use crate::sotw_db::database::Roster;
+
+/// Host queue of a channel in turn order, starting with the nominated host
+pub fn format_roster(roster: &Roster) -> String {
+    if roster.members.is_empty() {
+        return "No one is in the host queue, join it with `/sotw host join`".to_string();
+    }
+
+    let nominee = roster.nominee();
+    let members: Vec<String> = roster
+        .members
+        .iter()
+        .map(|member| {
+            if Some(member.user_id.as_str()) == nominee {
+                format!("<@{}> (next)", member.user_id)
+            } else {
+                format!("<@{}>", member.user_id)
+            }
+        })
+        .collect();
+
+    format!("*Host queue:* {}", members.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::slack::roster::format_roster;
+    use crate::sotw_db::database::Roster;
+    use crate::sotw_db::model::HostRosterMember;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn member(user_id: &str, position: i32, nominated: bool) -> HostRosterMember {
+        HostRosterMember {
+            id: Uuid::new_v4(),
+            channel_id: "CSOTW".to_string(),
+            user_id: user_id.to_string(),
+            position,
+            nominated: if nominated { Some(Utc::now()) } else { None },
+            joined: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_format_roster() {
+        let mut roster = Roster {
+            channel_id: "CSOTW".to_string(),
+            members: vec![
+                member("UB", 2, true),
+                member("UC", 3, false),
+                member("UA", 1, false),
+            ],
+        };
+
+        assert_eq!(
+            format_roster(&roster),
+            "*Host queue:* <@UB> (next), <@UC>, <@UA>"
+        );
+
+        roster.members.clear();
+        assert_eq!(
+            format_roster(&roster),
+            "No one is in the host queue, join it with `/sotw host join`"
+        );
+    }
+}
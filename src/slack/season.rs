@@ -0,0 +1,129 @@
+use crate::sotw_db::database::SeasonStandings;
+use crate::sotw_db::season::champions;
+
+/// Leaderboard of a season, users with the same points and wins share a place
+pub fn format_standings(season_standings: &SeasonStandings) -> String {
+    let season = &season_standings.season;
+    let progress = if season.is_active {
+        format!(
+            "{} of {} competitions held",
+            season_standings.closed, season.competitions
+        )
+    } else {
+        format!("ended after {} competitions", season_standings.closed)
+    };
+    let mut lines = vec![format!("*Season:* {}, {}", season.name, progress)];
+
+    if season_standings.standings.is_empty() {
+        lines.push("No one has placed yet".to_string());
+    }
+    let mut place = 0;
+    for (index, standing) in season_standings.standings.iter().enumerate() {
+        let shared = index > 0 && {
+            let above = &season_standings.standings[index - 1];
+            above.points == standing.points && above.wins == standing.wins
+        };
+        if !shared {
+            place = index + 1;
+        }
+        lines.push(format!(
+            "{}. <@{}> {}, {}",
+            place,
+            standing.user_id,
+            plural(standing.points, "point"),
+            plural(standing.wins, "win")
+        ));
+    }
+
+    let points: Vec<String> = season.points.iter().map(|p| p.to_string()).collect();
+    lines.push(format!("Points by place: {}", points.join(", ")));
+
+    lines.join("\n")
+}
+
+/// Announcement of a finished season with its champion
+pub fn format_season_end(season_standings: &SeasonStandings) -> String {
+    let leaders: Vec<String> = champions(&season_standings.standings)
+        .iter()
+        .map(|standing| format!("<@{}>", standing.user_id))
+        .collect();
+    let header = match leaders.len() {
+        0 => format!(
+            "The season *{}* ended without a champion",
+            season_standings.season.name
+        ),
+        1 => format!(
+            "*Season champion of {}:* {}",
+            season_standings.season.name, leaders[0]
+        ),
+        _ => format!(
+            "*Season champions of {}:* {}",
+            season_standings.season.name,
+            leaders.join(" and ")
+        ),
+    };
+
+    format!("{}\n{}", header, format_standings(season_standings))
+}
+
+fn plural(count: i32, noun: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::slack::season::format_season_end;
+    use crate::sotw_db::database::SeasonStandings;
+    use crate::sotw_db::model::Season;
+    use crate::sotw_db::season::Standing;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn standing(user_id: &str, points: i32, wins: i32) -> Standing {
+        Standing {
+            user_id: user_id.to_string(),
+            points,
+            wins,
+        }
+    }
+
+    #[test]
+    fn test_format_season_end() {
+        let season_standings = SeasonStandings {
+            season: Season {
+                id: Uuid::new_v4(),
+                name: "2026 Q4".to_string(),
+                user_id: "UHOST".to_string(),
+                started: Utc::now(),
+                ended: Some(Utc::now()),
+                is_active: false,
+                competitions: 3,
+                points: vec![10, 7, 5],
+            },
+            closed: 3,
+            standings: vec![
+                standing("UA", 24, 2),
+                standing("UB", 17, 1),
+                standing("UC", 7, 0),
+                standing("UD", 7, 0),
+                standing("UE", 0, 0),
+            ],
+        };
+
+        assert_eq!(
+            format_season_end(&season_standings),
+            "*Season champion of 2026 Q4:* <@UA>\n\
+            *Season:* 2026 Q4, ended after 3 competitions\n\
+            1. <@UA> 24 points, 2 wins\n\
+            2. <@UB> 17 points, 1 win\n\
+            3. <@UC> 7 points, 0 wins\n\
+            3. <@UD> 7 points, 0 wins\n\
+            5. <@UE> 0 points, 0 wins\n\
+            Points by place: 10, 7, 5"
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use crate::sotw_db::achievements::achievement;
+use crate::sotw_db::database::UserStats;
+use crate::sotw_db::model::Badge;
+
+/// A user's record over past competitions
+pub fn format_stats(stats: &UserStats) -> String {
+    if stats.entered == 0 && stats.badges.is_empty() {
+        return format!("<@{}> has not entered a competition yet", stats.user_id);
+    }
+
+    let mut lines = vec![
+        format!("*Stats for <@{}>*", stats.user_id),
+        format!(
+            "Entered {}, {}, {}",
+            count(stats.entered, "competition"),
+            count(stats.wins, "win"),
+            count(stats.podiums, "podium finish")
+        ),
+    ];
+    if let Some(share) = stats.average_share {
+        lines.push(format!("Average vote share {:.1}%", share));
+    }
+    lines.push(format!(
+        "Current streak: {} in a row",
+        count(stats.streak, "competition")
+    ));
+    if let Some((provider, songs)) = &stats.provider {
+        lines.push(format!(
+            "Most songs from {} ({})",
+            provider,
+            count(*songs, "song")
+        ));
+    }
+    if !stats.badges.is_empty() {
+        let names: Vec<&str> = stats.badges.iter().map(badge_name).collect();
+        lines.push(format!("Badges: {}", names.join(", ")));
+    }
+
+    lines.join("\n")
+}
+
+/// Badges earned with the close of a competition, for the results message
+pub fn format_badges(badges: &[Badge]) -> String {
+    let mut lines = vec!["*Badges earned:*".to_string()];
+    lines.extend(
+        badges
+            .iter()
+            .map(|badge| match achievement(&badge.achievement) {
+                Some(achievement) => format!(
+                    "<@{}> *{}*, {}",
+                    badge.user_id, achievement.name, achievement.description
+                ),
+                None => format!("<@{}> *{}*", badge.user_id, badge.achievement),
+            }),
+    );
+
+    lines.join("\n")
+}
+
+// Badges of achievements which were since removed keep their key as the name
+fn badge_name(badge: &Badge) -> &str {
+    achievement(&badge.achievement)
+        .map_or(badge.achievement.as_str(), |achievement| achievement.name)
+}
+
+fn count(number: i64, noun: &str) -> String {
+    match number {
+        1 => format!("1 {}", noun),
+        _ if noun.ends_with("sh") => format!("{} {}es", number, noun),
+        _ => format!("{} {}s", number, noun),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::slack::stats::{format_badges, format_stats};
+    use crate::sotw_db::database::UserStats;
+    use crate::sotw_db::model::Badge;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn badge(user_id: &str, achievement: &str) -> Badge {
+        Badge {
+            id: Uuid::new_v4(),
+            user_id: user_id.to_string(),
+            achievement: achievement.to_string(),
+            competition_id: Uuid::new_v4(),
+            awarded: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_format_stats() {
+        let stats = UserStats {
+            user_id: "UA".to_string(),
+            entered: 12,
+            wins: 1,
+            podiums: 4,
+            average_share: Some(23.456),
+            streak: 3,
+            provider: Some(("Spotify".to_string(), 9)),
+            badges: vec![badge("UA", "first_win"), badge("UA", "retired")],
+        };
+
+        assert_eq!(
+            format_stats(&stats),
+            "*Stats for <@UA>*\n\
+            Entered 12 competitions, 1 win, 4 podium finishes\n\
+            Average vote share 23.5%\n\
+            Current streak: 3 competitions in a row\n\
+            Most songs from Spotify (9 songs)\n\
+            Badges: First win, retired"
+        );
+        assert_eq!(
+            format_badges(&stats.badges[..1]),
+            "*Badges earned:*\n<@UA> *First win*, won a competition"
+        );
+    }
+}
@@ -2,6 +2,10 @@ use actix_http::error::ErrorBadRequest;
 use actix_http::http::HeaderMap;
 use actix_web::Error;
 
+use crate::metrics::SIGNATURE_FAILURES_TOTAL;
+use crate::telemetry::redact::Redacted;
+use crate::SlackSecret;
+
 use ring::hmac;
 
 static TIMESTAMP_FIVE_MINUTES: i64 = 300;
@@ -69,26 +73,38 @@ pub fn validate_request_headers(headers: &HeaderMap) -> Result<SlackValidationHe
     })
 }
 
+/// Verify the request against every active signing secret, so secrets can be rotated
+/// by adding the new one before removing the old one.
+/// The signature check itself is constant time.
 pub fn validate_slack_signature(
-    key_value: &str,
+    key_values: &[SlackSecret],
     slack_signature: String,
     body: String,
     timestamp: i64,
 ) -> Result<(), Error> {
-    let key = hmac::Key::new(hmac::HMAC_SHA256, &key_value.as_bytes());
-
-    let base = format!(
-        "v0={}",
-        hex::encode(hmac::sign(
-            &key,
-            format!("v0:{}:{}", timestamp, body).as_bytes()
-        ))
-    );
+    let span = info_span!("validate_slack_signature");
+    let _enter = span.enter();
+
+    let message = format!("v0:{}:{}", timestamp, body);
+    let verified = match slack_signature
+        .strip_prefix("v0=")
+        .and_then(|signature| hex::decode(signature).ok())
+    {
+        Some(tag) => key_values.iter().any(|key_value| {
+            let key = hmac::Key::new(hmac::HMAC_SHA256, key_value.as_bytes());
+            hmac::verify(&key, message.as_bytes(), &tag).is_ok()
+        }),
+        None => false,
+    };
 
-    if base != slack_signature {
+    if !verified {
+        SIGNATURE_FAILURES_TOTAL.inc();
         warn!(
-            "Unable to verify signature for incoming request body={:#?} base={:#?} slack_signature={:#?}",
-            body, base, slack_signature
+            timestamp,
+            body = %Redacted(&body),
+            slack_signature = %Redacted(&slack_signature),
+            secrets = key_values.len(),
+            "Unable to verify signature for incoming request"
         );
         return Err(ErrorBadRequest("could not verify slack request signature"));
     }
@@ -111,7 +127,7 @@ mod tests {
         let body = "1234".to_string();
 
         let timestamp: i64 = 1;
-        let result = validate_slack_signature(&key_value, header_signature, body, timestamp);
+        let result = validate_slack_signature(&[key_value], header_signature, body, timestamp);
 
         assert!(result.is_err(), "signature should not be verified");
     }
@@ -126,11 +142,31 @@ mod tests {
             .to_string();
 
         let timestamp: i64 = 1531420618;
-        let result = validate_slack_signature(&key_value, header_signature, body, timestamp);
+        let result = validate_slack_signature(&[key_value], header_signature, body, timestamp);
 
         assert!(result.is_ok(), "signature should be verified");
     }
 
+    #[test]
+    fn test_validate_slack_signature_rotated_secret() {
+        let key_values: Vec<SlackSecret> = vec![
+            "new-secret-not-yet-used-by-slack".to_string(),
+            "8f742231b10e8888abcd99yyyzzz85a5".to_string(),
+        ];
+
+        let header_signature =
+            "v0=a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503".to_string();
+        let body = "token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&team_domain=testteamnow&channel_id=G8PSS9T3V&channel_name=foobar&user_id=U2CERLKJA&user_name=roadrunner&command=%2Fwebhook-collect&text=&response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2FT1DC2JH3J%2F397700885554%2F96rGlfmibIGlgcZRskXaIFfN&trigger_id=398738663015.47445629121.803a0bc887a14d10d2c447fce8b6703c"
+            .to_string();
+
+        let result = validate_slack_signature(&key_values, header_signature, body, 1531420618);
+
+        assert!(
+            result.is_ok(),
+            "any active secret should be able to verify the signature"
+        );
+    }
+
     #[test]
     fn test_check_headers_should_succeed() {
         let mut headers = HeaderMap::new();
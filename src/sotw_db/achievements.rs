@@ -0,0 +1,150 @@
+// The achievements users earn badges for. New achievements only need a row in
+// ACHIEVEMENTS, and a new kind of rule when none of the existing ones fit.
+
+/// What a user did in a closed competition
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct Participation {
+    pub entered: bool,
+    pub won: bool,
+    pub voted: bool,
+    pub hosted: bool,
+}
+
+/// Condition for earning an achievement, checked on a user's competitions
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Rule {
+    // Won this many competitions
+    Wins(usize),
+    // Won this many of the latest competitions in a row
+    WinsInARow(usize),
+    // Entered this many of the latest competitions in a row
+    EnteredInARow(usize),
+    // Voted in this many of the latest competitions in a row
+    VotedInARow(usize),
+    // Hosted this many competitions
+    Hosted(usize),
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct Achievement {
+    // Stored with the badge, never change it once badges were awarded
+    pub key: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub rule: Rule,
+}
+
+pub static ACHIEVEMENTS: &[Achievement] = &[
+    Achievement {
+        key: "first_win",
+        name: "First win",
+        description: "won a competition",
+        rule: Rule::Wins(1),
+    },
+    Achievement {
+        key: "hat_trick",
+        name: "Hat trick",
+        description: "won three competitions in a row",
+        rule: Rule::WinsInARow(3),
+    },
+    Achievement {
+        key: "regular",
+        name: "Regular",
+        description: "entered ten competitions in a row",
+        rule: Rule::EnteredInARow(10),
+    },
+    Achievement {
+        key: "theme_host",
+        name: "Theme host",
+        description: "hosted a competition",
+        rule: Rule::Hosted(1),
+    },
+    Achievement {
+        key: "perfect_voter",
+        name: "Perfect voter",
+        description: "voted in ten competitions in a row",
+        rule: Rule::VotedInARow(10),
+    },
+];
+
+/// Achievement for the key stored with a badge
+pub fn achievement(key: &str) -> Option<&'static Achievement> {
+    ACHIEVEMENTS
+        .iter()
+        .find(|achievement| achievement.key == key)
+}
+
+impl Rule {
+    /// Whether a user's competitions, latest first, meet the rule
+    pub fn is_met(&self, competitions: &[Participation]) -> bool {
+        let total =
+            |done: fn(&Participation) -> bool| competitions.iter().filter(|p| done(p)).count();
+        let in_a_row =
+            |done: fn(&Participation) -> bool| competitions.iter().take_while(|p| done(p)).count();
+
+        match *self {
+            Rule::Wins(wins) => total(|p| p.won) >= wins,
+            Rule::WinsInARow(wins) => in_a_row(|p| p.won) >= wins,
+            Rule::EnteredInARow(entered) => in_a_row(|p| p.entered) >= entered,
+            Rule::VotedInARow(voted) => in_a_row(|p| p.voted) >= voted,
+            Rule::Hosted(hosted) => total(|p| p.hosted) >= hosted,
+        }
+    }
+}
+
+/// Achievements a user's competitions, latest first, earn
+pub fn earned(competitions: &[Participation]) -> Vec<&'static Achievement> {
+    ACHIEVEMENTS
+        .iter()
+        .filter(|achievement| achievement.rule.is_met(competitions))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sotw_db::achievements::{achievement, earned, Participation, Rule, ACHIEVEMENTS};
+
+    fn entered(won: bool) -> Participation {
+        Participation {
+            entered: true,
+            won,
+            voted: true,
+            hosted: false,
+        }
+    }
+
+    #[test]
+    fn test_rules() {
+        let competitions = vec![
+            entered(true),
+            entered(true),
+            Participation::default(),
+            entered(true),
+        ];
+
+        assert!(Rule::Wins(3).is_met(&competitions));
+        assert!(
+            !Rule::WinsInARow(3).is_met(&competitions),
+            "the run was broken"
+        );
+        assert!(Rule::WinsInARow(2).is_met(&competitions));
+        assert!(Rule::VotedInARow(2).is_met(&competitions));
+        assert!(!Rule::Hosted(1).is_met(&competitions));
+    }
+
+    #[test]
+    fn test_earned() {
+        let mut competitions = vec![entered(false); 10];
+        competitions[9].won = true;
+        competitions[0].hosted = true;
+
+        let keys: Vec<&str> = earned(&competitions).iter().map(|a| a.key).collect();
+
+        assert_eq!(
+            keys,
+            vec!["first_win", "regular", "theme_host", "perfect_voter"]
+        );
+        assert!(earned(&[]).is_empty());
+        assert_eq!(achievement("first_win"), Some(&ACHIEVEMENTS[0]));
+    }
+}
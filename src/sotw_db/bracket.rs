@@ -0,0 +1,94 @@
+// Seeding and advancing of single-elimination brackets.
+
+/// Seeds in the order they are placed in the first round of a bracket of `size`,
+/// a power of two. Neighbours meet in the first round, and the top seeds can only
+/// meet in the late rounds: 1, 8, 4, 5, 2, 7, 3, 6 for eight songs.
+pub fn seed_order(size: usize) -> Vec<usize> {
+    let mut order = vec![1];
+    while order.len() < size {
+        let mirror = order.len() * 2 + 1;
+        order = order
+            .into_iter()
+            .flat_map(|seed| vec![seed, mirror - seed])
+            .collect();
+    }
+
+    order
+}
+
+/// First round matches for the given number of seeded songs, in slot order.
+/// The bracket is filled up to a power of two with byes, which go to the top seeds.
+pub fn first_round(songs: usize) -> Vec<(usize, Option<usize>)> {
+    seed_order(songs.next_power_of_two())
+        .chunks(2)
+        .map(|pair| match pair {
+            [home, away] if *away <= songs => (*home, Some(*away)),
+            _ => (pair[0], None),
+        })
+        .collect()
+}
+
+/// Number of rounds up to and including the final
+pub fn rounds(songs: usize) -> usize {
+    songs.next_power_of_two().trailing_zeros() as usize
+}
+
+/// Next round matches between the winners of a round, given in slot order.
+/// The winners of neighbouring slots meet.
+pub fn next_round<T: Copy>(winners: &[T]) -> Vec<(T, T)> {
+    winners
+        .chunks(2)
+        .filter_map(|pair| match pair {
+            [home, away] => Some((*home, *away)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Winner of a match between two seeds, by votes. The better seed wins a tie.
+pub fn match_winner(home: (i32, u32), away: (i32, u32)) -> i32 {
+    let (home_seed, home_votes) = home;
+    let (away_seed, away_votes) = away;
+
+    if home_votes > away_votes || (home_votes == away_votes && home_seed < away_seed) {
+        home_seed
+    } else {
+        away_seed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sotw_db::bracket::{first_round, match_winner, next_round, rounds, seed_order};
+
+    #[test]
+    fn test_seed_order() {
+        assert_eq!(seed_order(1), vec![1]);
+        assert_eq!(seed_order(4), vec![1, 4, 2, 3]);
+        assert_eq!(seed_order(8), vec![1, 8, 4, 5, 2, 7, 3, 6]);
+    }
+
+    #[test]
+    fn test_first_round_byes() {
+        assert_eq!(
+            first_round(5),
+            vec![(1, None), (4, Some(5)), (2, None), (3, None)],
+            "the top seeds get the byes"
+        );
+        assert_eq!(first_round(2), vec![(1, Some(2))]);
+        assert_eq!(rounds(5), 3);
+        assert_eq!(rounds(2), 1);
+    }
+
+    #[test]
+    fn test_advance() {
+        assert_eq!(next_round(&[1, 4, 2, 3]), vec![(1, 4), (2, 3)]);
+        assert_eq!(match_winner((1, 2), (8, 3)), 8);
+        assert_eq!(
+            match_winner((4, 2), (5, 2)),
+            4,
+            "the better seed wins a tie"
+        );
+        assert_eq!(match_winner((6, 1), (3, 1)), 3);
+    }
+}
@@ -1,11 +1,38 @@
+use crate::sotw_db::achievements::{earned, Participation};
+use crate::sotw_db::bracket::{first_round, match_winner, next_round};
 use crate::sotw_db::errors::*;
 use crate::sotw_db::model::{
-    Competition, CompetitionInsert, Song, SongInsert, SongVote, SongVoteInsert,
+    Badge, BadgeInsert, Ban, BanInsert, BracketMatch, BracketMatchInsert, Competition,
+    CompetitionInsert, CompetitionKind, CompetitionRef, Handover, HandoverInsert, HostRosterInsert,
+    HostRosterMember, Role, Season, SeasonInsert, SlackRequest, SlackRequestInsert,
+    SlackSignatureInsert, Song, SongInsert, SongRef, SongVote, SongVoteInsert, TieBreak, UserRole,
+    UserRoleInsert, VoteChoice, VotingMethod, MAX_SCORE,
 };
+use crate::sotw_db::permissions::{is_allowed, Action, Actor, Scope};
+use crate::sotw_db::season::{placements, standings, Standing};
+use crate::sotw_db::stats::{average_share, favourite_provider, streak};
+use crate::sotw_db::tally::{combine, points, seeded_draw, tally, Ballot, Combined, Tally};
+use crate::telemetry::redact::Redacted;
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
-use diesel::{delete, insert_into, update, PgConnection, QueryDsl, RunQueryDsl};
+use diesel::{delete, insert_into, sql_query, update, PgConnection, QueryDsl, RunQueryDsl};
+use ring::hmac;
 use uuid::Uuid;
 
+// Handled requests are kept long enough to cover Slack's retries with a wide margin
+static REQUEST_RETENTION_HOURS: i64 = 24;
+// A claim this old without an outcome belongs to a request that never finished
+static REQUEST_ABANDONED_SECS: i64 = 60;
+// Slack requests are only accepted within five minutes of their timestamp
+static SIGNATURE_WINDOW_SECS: i64 = 300;
+
+/// Cheap round trip used by the readiness probe
+pub fn ping(connection: &PgConnection) -> Result<(), BotError> {
+    sql_query("SELECT 1").execute(connection)?;
+
+    Ok(())
+}
+
 pub fn save_competition(
     mut competition_insert: CompetitionInsert,
     connection: &PgConnection,
@@ -32,17 +59,22 @@ pub fn save_competition(
         }
     }
 
+    // Open competitions count towards the running season, brackets stand apart
+    if competition_insert.kind == CompetitionKind::Open {
+        competition_insert.season_id = find_active_season(connection)?.map(|season| season.id);
+    }
+
     let saved_competition = insert_into(competition)
         .values(&competition_insert)
         .get_result::<Competition>(connection)?;
 
-    info!("Created new competition with id={:?}", saved_competition);
+    info!(competition_id = %saved_competition.id, "Created new competition");
 
     Ok(saved_competition)
 }
 
 pub fn close_competition(
-    cmd_user_id: String,
+    actor: &Actor,
     connection: &PgConnection,
 ) -> Result<Competition, BotError> {
     use crate::schema::sotw::competition::dsl::*;
@@ -50,12 +82,12 @@ pub fn close_competition(
     let result = find_active_competition(connection)?;
 
     if let Some(active_competition) = result {
-        if active_competition.user_id != cmd_user_id {
-            return Err(BotError {
-                data_error: DataError::UserDoesNotOwnEntity(active_competition.id),
-                message: "User does not own currently active competition".to_string(),
-            });
-        }
+        authorize(
+            actor,
+            Action::Stop,
+            Scope::Competition(&active_competition),
+            connection,
+        )?;
 
         let closed_id = update(competition.filter(id.eq(active_competition.id)))
             .set((is_active.eq(false), ended.eq(Some(chrono::Utc::now()))))
@@ -65,225 +97,4021 @@ pub fn close_competition(
     }
 
     Err(BotError {
-        data_error: DataError::DieselError("Not found".to_string()),
+        data_error: DataError::NoActiveCompetition,
         message: "Unable to find an existing active competition".to_string(),
     })
 }
 
-fn find_active_competition(connection: &PgConnection) -> Result<Option<Competition>, BotError> {
+/// Hand the active competition over to a new host, recorded on the competition
+/// and in the handover history with who handed it over and when
+pub fn handover_competition(
+    actor: &Actor,
+    new_host_user_id: String,
+    connection: &PgConnection,
+) -> Result<Handover, BotError> {
     use crate::schema::sotw::competition::dsl::*;
+    use crate::schema::sotw::handover;
 
-    let result = competition
+    connection.transaction(|| {
+        let active_competition = find_active_competition(connection)?.ok_or_else(|| BotError {
+            data_error: DataError::NoActiveCompetition,
+            message: "Unable to find an existing active competition".to_string(),
+        })?;
+        authorize(
+            actor,
+            Action::Handover,
+            Scope::Competition(&active_competition),
+            connection,
+        )?;
+        if active_competition.user_id == new_host_user_id {
+            return Err(BotError {
+                data_error: DataError::InvalidArgument(format!(
+                    "<@{}> already hosts the competition",
+                    new_host_user_id
+                )),
+                message: "Unable to hand the competition over".to_string(),
+            });
+        }
+
+        let now = Utc::now();
+        update(competition.find(active_competition.id))
+            .set((
+                user_id.eq(&new_host_user_id),
+                previous_user_id.eq(&active_competition.user_id),
+                handed_over.eq(now),
+            ))
+            .execute(connection)?;
+        let recorded = insert_into(handover::table)
+            .values(&HandoverInsert {
+                competition_id: active_competition.id,
+                from_user_id: active_competition.user_id,
+                to_user_id: new_host_user_id,
+                user_id: actor.user_id.clone(),
+                handed_over: now,
+            })
+            .get_result::<Handover>(connection)?;
+
+        info!(competition_id = %recorded.competition_id, "Handed competition over");
+
+        Ok(recorded)
+    })
+}
+
+/// Change the description of the active competition, returning the old one
+/// along with the changed competition
+pub fn edit_description(
+    actor: &Actor,
+    new_description: String,
+    connection: &PgConnection,
+) -> Result<(String, Competition), BotError> {
+    use crate::schema::sotw::competition::dsl::*;
+
+    connection.transaction(|| {
+        let active_competition = find_active_competition(connection)?.ok_or_else(|| BotError {
+            data_error: DataError::NoActiveCompetition,
+            message: "Unable to find an existing active competition".to_string(),
+        })?;
+        authorize(
+            actor,
+            Action::Edit,
+            Scope::Competition(&active_competition),
+            connection,
+        )?;
+
+        let edited = update(competition.find(active_competition.id))
+            .set(description.eq(new_description))
+            .get_result::<Competition>(connection)?;
+        info!(competition_id = %edited.id, "Edited competition description");
+
+        Ok((active_competition.description, edited))
+    })
+}
+
+/// New deadline after extending the active competition
+#[derive(PartialEq, Debug)]
+pub enum Extension {
+    // Songs are accepted until the new submission deadline
+    Submissions(DateTime<Utc>),
+    // The open matches of the bracket are decided at the new deadline
+    Matches(DateTime<Utc>),
+}
+
+/// Push the deadline of the active competition back. A deadline which already
+/// passed is extended from now.
+pub fn extend_deadline(
+    actor: &Actor,
+    extension: chrono::Duration,
+    now: DateTime<Utc>,
+    connection: &PgConnection,
+) -> Result<Extension, BotError> {
+    use crate::schema::sotw::{bracket_match, competition};
+
+    connection.transaction(|| {
+        let active_competition = find_active_competition(connection)?.ok_or_else(|| BotError {
+            data_error: DataError::NoActiveCompetition,
+            message: "Unable to find an existing active competition".to_string(),
+        })?;
+        authorize(
+            actor,
+            Action::Edit,
+            Scope::Competition(&active_competition),
+            connection,
+        )?;
+        let extended = |deadline: DateTime<Utc>| deadline.max(now) + extension;
+
+        match active_competition.kind {
+            CompetitionKind::Open => {
+                let deadline = active_competition
+                    .submission_deadline
+                    .map(extended)
+                    .ok_or_else(|| BotError {
+                        data_error: DataError::InvalidArgument(
+                            "the competition has no submission deadline".to_string(),
+                        ),
+                        message: "Unable to extend the deadline".to_string(),
+                    })?;
+                update(competition::table.find(active_competition.id))
+                    .set(competition::submission_deadline.eq(Some(deadline)))
+                    .execute(connection)?;
+                info!(competition_id = %active_competition.id, %deadline, "Extended submission deadline");
+
+                Ok(Extension::Submissions(deadline))
+            }
+            CompetitionKind::Bracket => {
+                let open_matches = bracket_match::table
+                    .filter(bracket_match::competition_id.eq(active_competition.id))
+                    .filter(bracket_match::closed.is_null())
+                    .load::<BracketMatch>(connection)?;
+                let deadline = open_matches
+                    .iter()
+                    .map(|open| open.deadline)
+                    .max()
+                    .map(extended)
+                    .ok_or_else(|| BotError {
+                        data_error: DataError::InvalidArgument(
+                            "the bracket has no open matches".to_string(),
+                        ),
+                        message: "Unable to extend the deadline".to_string(),
+                    })?;
+                update(
+                    bracket_match::table
+                        .filter(bracket_match::competition_id.eq(active_competition.id))
+                        .filter(bracket_match::closed.is_null()),
+                )
+                .set(bracket_match::deadline.eq(deadline))
+                .execute(connection)?;
+                info!(competition_id = %active_competition.id, %deadline, "Extended match deadline");
+
+                Ok(Extension::Matches(deadline))
+            }
+        }
+    })
+}
+
+/// Open a closed competition again, the one which closed last in the channel
+/// unless given. Only the latest competition of its channel can be reopened. The
+/// results are cleared and decided again when it is stopped.
+pub fn reopen_competition(
+    actor: &Actor,
+    competition_ref: Option<CompetitionRef>,
+    reopen_channel_id: String,
+    connection: &PgConnection,
+) -> Result<Competition, BotError> {
+    use crate::schema::sotw::{competition, season, song};
+
+    connection.transaction(|| {
+        let invalid = |reason: &str| BotError {
+            data_error: DataError::InvalidArgument(reason.to_string()),
+            message: "Unable to reopen competition".to_string(),
+        };
+        if let Some(active_competition) = find_active_competition(connection)? {
+            return Err(BotError {
+                data_error: DataError::ActiveCompetitionExists(active_competition.id),
+                message: "Unable to reopen competition".to_string(),
+            });
+        }
+
+        let latest = match competition_ref {
+            Some(competition_ref) => find_closed_competition(competition_ref, connection)?,
+            None => competition::table
+                .filter(competition::channel_id.eq(&reopen_channel_id))
+                .filter(competition::is_active.eq(false))
+                .order(competition::ended.desc().nulls_last())
+                .first::<Competition>(connection)
+                .optional()?
+                .ok_or_else(|| invalid("no competition has closed in this channel"))?,
+        };
+        authorize(actor, Action::Edit, Scope::Competition(&latest), connection)?;
+        // Competitions from before channels were recorded share a single scope
+        let newer = match &latest.channel_id {
+            Some(latest_channel_id) => competition::table
+                .filter(competition::channel_id.eq(latest_channel_id))
+                .filter(competition::started.gt(latest.started))
+                .count()
+                .get_result::<i64>(connection)?,
+            None => competition::table
+                .filter(competition::channel_id.is_null())
+                .filter(competition::started.gt(latest.started))
+                .count()
+                .get_result::<i64>(connection)?,
+        };
+        if newer > 0 {
+            return Err(invalid("a newer competition was started in its channel"));
+        }
+        if latest.kind == CompetitionKind::Bracket {
+            return Err(invalid("a bracket can't be reopened"));
+        }
+        if let Some(latest_season_id) = latest.season_id {
+            let ended_season = season::table
+                .find(latest_season_id)
+                .select(season::ended)
+                .first::<Option<DateTime<Utc>>>(connection)?;
+            if ended_season.is_some() {
+                return Err(invalid("its season has ended"));
+            }
+        }
+
+        update(song::table.filter(song::competition_id.eq(latest.id)))
+            .set(song::place.eq(None::<i32>))
+            .execute(connection)?;
+        let reopened = update(competition::table.find(latest.id))
+            .set((
+                competition::is_active.eq(true),
+                competition::ended.eq(None::<DateTime<Utc>>),
+                competition::winner_song_id.eq(None::<Uuid>),
+                competition::tie_break_used.eq(None::<String>),
+                competition::tie_break_seed.eq(None::<i64>),
+                competition::runoff.eq(false),
+            ))
+            .get_result::<Competition>(connection)?;
+        info!(competition_id = %reopened.id, "Reopened competition");
+
+        Ok(reopened)
+    })
+}
+
+/// Outcome of stopping the active competition
+#[derive(PartialEq, Debug)]
+pub enum Closing {
+    Closed(Box<CompetitionResults>),
+    // The winners tied, the competition stays open for a runoff vote between them
+    Runoff(Box<Competition>, Vec<Song>),
+}
+
+#[derive(PartialEq, Debug)]
+pub struct CompetitionResults {
+    pub competition: Competition,
+    pub songs: Vec<Song>,
+    pub tally: Tally,
+    pub runoff: Option<Tally>,
+    // Winners of each award category, in the order the categories were defined
+    pub categories: Vec<(String, Tally)>,
+    // The audience combined with the judges, when the competition has a panel
+    pub panel: Option<Combined>,
+    // Final standings, when this was the last competition of its season
+    pub season_end: Option<SeasonStandings>,
+    // Badges earned with the close of the competition
+    pub badges: Vec<Badge>,
+    // Whose turn it is to host next, when the channel has a host roster
+    pub next_host: Option<String>,
+}
+
+/// Close the active competition with its results. A tie for the win is broken
+/// with the tie-break of the competition, which is recorded along with the winner.
+/// The host gives the casting vote when stopping, if that is the tie-break.
+pub fn stop_competition(
+    actor: Actor,
+    casting_vote: Option<SongRef>,
+    ballot_key: &[u8],
+    connection: &PgConnection,
+) -> Result<Closing, BotError> {
+    use crate::schema::sotw::competition::dsl::*;
+
+    connection.transaction(|| {
+        let active_competition = find_active_competition(connection)?.ok_or_else(|| BotError {
+            data_error: DataError::NoActiveCompetition,
+            message: "Unable to find an existing active competition".to_string(),
+        })?;
+        authorize(
+            &actor,
+            Action::Stop,
+            Scope::Competition(&active_competition),
+            connection,
+        )?;
+        if active_competition.kind == CompetitionKind::Bracket {
+            return Err(BotError {
+                data_error: DataError::InvalidArgument(
+                    "a bracket closes when its final is decided".to_string(),
+                ),
+                message: "Unable to stop competition".to_string(),
+            });
+        }
+
+        let mut results = tally_results(active_competition, ballot_key, connection)?;
+        let result_winners = results
+            .panel
+            .as_ref()
+            .map_or(&results.tally.winners, |combined| &combined.winners);
+
+        // A runoff without votes leaves the songs tied as they were
+        let leaders = match &results.runoff {
+            Some(runoff_tally) if !runoff_tally.winners.is_empty() => &runoff_tally.winners,
+            _ => result_winners,
+        };
+        let tied: Vec<&Song> = results
+            .songs
+            .iter()
+            .filter(|song| leaders.contains(&song.id))
+            .collect();
+
+        let (winner, used, seed) = match tied.as_slice() {
+            [] => (None, None, None),
+            [only] => (
+                Some(only.id),
+                results.runoff.as_ref().map(|_| "runoff vote".to_string()),
+                None,
+            ),
+            [earliest, ..] => match results.competition.tie_break {
+                TieBreak::Runoff if !results.competition.runoff => {
+                    let in_runoff = update(competition.find(results.competition.id))
+                        .set(runoff.eq(true))
+                        .get_result::<Competition>(connection)?;
+                    info!(competition_id = %in_runoff.id, tied = tied.len(), "Started runoff vote");
+
+                    return Ok(Closing::Runoff(
+                        Box::new(in_runoff),
+                        tied.into_iter().cloned().collect(),
+                    ));
+                }
+                TieBreak::Earliest | TieBreak::Runoff => (
+                    Some(earliest.id),
+                    Some("earliest submission".to_string()),
+                    None,
+                ),
+                TieBreak::CastingVote => {
+                    let cast = casting_vote
+                        .and_then(|vote| tied.iter().find(|song| vote.matches(song)))
+                        .ok_or_else(|| {
+                            let numbers: Vec<String> =
+                                tied.iter().map(|song| format!("#{}", song.number)).collect();
+                            BotError {
+                                data_error: DataError::InvalidArgument(format!(
+                                    "tied between {}, stop with the casting vote, for example `stop {}`",
+                                    numbers.join(", "),
+                                    earliest.number
+                                )),
+                                message: "Unable to stop competition".to_string(),
+                            }
+                        })?;
+                    (
+                        Some(cast.id),
+                        Some(format!("casting vote by <@{}>", actor.user_id)),
+                        None,
+                    )
+                }
+                TieBreak::Random => {
+                    let draw_seed = random_seed();
+                    let tied_ids: Vec<Uuid> = tied.iter().map(|song| song.id).collect();
+                    (
+                        seeded_draw(draw_seed as u64, &tied_ids),
+                        Some("seeded random draw".to_string()),
+                        Some(draw_seed),
+                    )
+                }
+            },
+        };
+
+        close_competition(&actor, connection)?;
+        let closed = update(competition.find(results.competition.id))
+            .set((
+                winner_song_id.eq(winner),
+                tie_break_used.eq(used),
+                tie_break_seed.eq(seed),
+            ))
+            .get_result::<Competition>(connection)?;
+
+        info!(competition_id = %closed.id, winner_song_id = ?winner, "Closed competition");
+
+        let result_ranking = results
+            .panel
+            .as_ref()
+            .map_or(&results.tally.ranking, |combined| &combined.ranking);
+        place_songs(&placements(result_ranking, winner), connection)?;
+        results.season_end = match closed.season_id {
+            Some(closed_season_id) => close_season_if_complete(closed_season_id, connection)?,
+            None => None,
+        };
+        results.badges = award_badges(&closed, ballot_key, connection)?;
+        results.next_host = match &closed.channel_id {
+            Some(channel) => advance_roster(channel, &closed.user_id, connection)?,
+            None => None,
+        };
+        results.competition = closed;
+
+        Ok(Closing::Closed(Box::new(results)))
+    })
+}
+
+/// Tally every vote of a competition the way its results are announced: the
+/// audience, the panel, a runoff vote and the award categories
+fn tally_results(
+    competition: Competition,
+    ballot_key: &[u8],
+    connection: &PgConnection,
+) -> Result<CompetitionResults, BotError> {
+    let songs = list_songs(competition.id, connection)?;
+    let main_tally = tally_competition(&competition, &songs, ballot_key, connection)?;
+    let counted_song_ids = counted_song_ids(&songs, &main_tally);
+    let panel_result = tally_panel(&competition, &counted_song_ids, &main_tally, connection)?;
+    let runoff_tally = if competition.runoff {
+        let result_winners = panel_result
+            .as_ref()
+            .map_or(&main_tally.winners, |combined| &combined.winners);
+        Some(tally_runoff(result_winners, connection)?)
+    } else {
+        None
+    };
+    let category_tallies = competition
+        .categories
+        .iter()
+        .map(|category| {
+            tally_category(category, &counted_song_ids, connection)
+                .map(|category_tally| (category.clone(), category_tally))
+        })
+        .collect::<Result<Vec<(String, Tally)>, BotError>>()?;
+
+    Ok(CompetitionResults {
+        competition,
+        songs,
+        tally: main_tally,
+        runoff: runoff_tally,
+        categories: category_tallies,
+        panel: panel_result,
+        season_end: None,
+        badges: vec![],
+        next_host: None,
+    })
+}
+
+/// Award the badges earned with the close of a competition to its host, its
+/// submitters and its voters. Votes in a secret ballot can't be traced back to
+/// a voter, so those voters are only checked when they took part otherwise.
+fn award_badges(
+    closed: &Competition,
+    ballot_key: &[u8],
+    connection: &PgConnection,
+) -> Result<Vec<Badge>, BotError> {
+    use crate::schema::sotw::{badge, competition, song, song_vote};
+
+    let past = competition::table
+        .filter(competition::is_active.eq(false))
+        .filter(competition::kind.eq(CompetitionKind::Open));
+    let competitions: Vec<Competition> =
+        past.order(competition::started.desc()).load(connection)?;
+    let songs: Vec<(Uuid, Uuid, String)> = song::table
+        .filter(song::competition_id.eq_any(past.select(competition::id)))
+        .select((song::id, song::competition_id, song::user_id))
+        .load(connection)?;
+    let voters: Vec<(Uuid, String)> = song_vote::table
+        .inner_join(song::table)
+        .filter(song::competition_id.eq_any(past.select(competition::id)))
+        .filter(song_vote::runoff.eq(false))
+        .filter(song_vote::category.is_null())
+        .filter(song_vote::panel.eq(false))
+        .select((song::competition_id, song_vote::user_id))
+        .distinct()
+        .load(connection)?;
+
+    let mut candidates: Vec<String> = songs
+        .iter()
+        .filter(|(_, competition_id, _)| *competition_id == closed.id)
+        .map(|(_, _, submitter)| submitter.clone())
+        .collect();
+    candidates.push(closed.user_id.clone());
+    if !closed.rules.secret_ballot {
+        candidates.extend(
+            voters
+                .iter()
+                .filter(|(competition_id, _)| *competition_id == closed.id)
+                .map(|(_, voter)| voter.clone()),
+        );
+    }
+    let mut seen = vec![];
+    candidates.retain(|candidate| {
+        let first = !seen.contains(candidate);
+        seen.push(candidate.clone());
+        first
+    });
+
+    let owned: Vec<(String, String)> = badge::table
+        .filter(badge::user_id.eq_any(&candidates))
+        .select((badge::user_id, badge::achievement))
+        .load(connection)?;
+
+    let mut new_badges = vec![];
+    for candidate in &candidates {
+        let record: Vec<Participation> = competitions
+            .iter()
+            .map(|past_competition| {
+                let mut own_songs = songs.iter().filter(|(_, competition_id, submitter)| {
+                    *competition_id == past_competition.id && submitter == candidate
+                });
+                let voter = voter_id(past_competition, candidate, ballot_key);
+                Participation {
+                    entered: own_songs.clone().next().is_some(),
+                    won: own_songs.any(|(id, _, _)| Some(*id) == past_competition.winner_song_id),
+                    voted: voters.contains(&(past_competition.id, voter)),
+                    hosted: past_competition.user_id == *candidate,
+                }
+            })
+            .collect();
+
+        new_badges.extend(
+            earned(&record)
+                .into_iter()
+                .filter(|achievement| {
+                    !owned
+                        .iter()
+                        .any(|(owner, key)| owner == candidate && key.as_str() == achievement.key)
+                })
+                .map(|achievement| BadgeInsert {
+                    user_id: candidate.clone(),
+                    achievement: achievement.key.to_string(),
+                    competition_id: closed.id,
+                    awarded: Utc::now(),
+                }),
+        );
+    }
+
+    if new_badges.is_empty() {
+        return Ok(vec![]);
+    }
+    let awarded = insert_into(badge::table)
+        .values(&new_badges)
+        .get_results::<Badge>(connection)?;
+
+    info!(competition_id = %closed.id, badges = awarded.len(), "Awarded badges");
+
+    Ok(awarded)
+}
+
+/// Check the actor may take the action with the roles granted to them. Refusing
+/// an action on a competition reports it as not owned by the actor.
+pub fn authorize(
+    actor: &Actor,
+    action: Action,
+    scope: Scope,
+    connection: &PgConnection,
+) -> Result<(), BotError> {
+    let roles = load_roles(&actor.user_id, connection)?;
+    if is_allowed(actor, &roles, action, scope) {
+        return Ok(());
+    }
+
+    let data_error = match scope {
+        Scope::Competition(competition) => DataError::UserDoesNotOwnEntity(competition.id),
+        Scope::Channel(_) => DataError::NotPermitted(format!("only admins can {}", action)),
+    };
+    Err(BotError {
+        data_error,
+        message: format!("User is not allowed to {}", action),
+    })
+}
+
+fn load_roles(role_user_id: &str, connection: &PgConnection) -> Result<Vec<UserRole>, BotError> {
+    use crate::schema::sotw::user_role::dsl::*;
+
+    let roles = user_role
+        .filter(user_id.eq(role_user_id))
+        .order(granted)
+        .load::<UserRole>(connection)?;
+
+    Ok(roles)
+}
+
+/// Grant a role to a user. Channel admins are granted the channel the command
+/// was given in and co-hosts the active competition.
+pub fn grant_role(
+    actor: &Actor,
+    role_user_id: String,
+    granted_role: Role,
+    command_channel_id: String,
+    connection: &PgConnection,
+) -> Result<UserRole, BotError> {
+    use crate::schema::sotw::user_role::dsl::*;
+
+    connection.transaction(|| {
+        let active_competition = find_active_competition(connection)?;
+        let (scope, role_channel_id, role_competition_id) = role_target(
+            granted_role,
+            &command_channel_id,
+            active_competition.as_ref(),
+        )?;
+        authorize(actor, Action::Grant(granted_role), scope, connection)?;
+
+        let granted_before = load_roles(&role_user_id, connection)?.into_iter().any(|r| {
+            r.role == granted_role
+                && r.channel_id == role_channel_id
+                && r.competition_id == role_competition_id
+        });
+        if granted_before {
+            return Err(BotError {
+                data_error: DataError::InvalidArgument(format!(
+                    "<@{}> is already a {}",
+                    role_user_id, granted_role
+                )),
+                message: "Unable to grant role".to_string(),
+            });
+        }
+
+        let saved = insert_into(user_role)
+            .values(&UserRoleInsert {
+                user_id: role_user_id,
+                role: granted_role,
+                channel_id: role_channel_id,
+                competition_id: role_competition_id,
+                granted_by: actor.user_id.clone(),
+                granted: Utc::now(),
+            })
+            .get_result::<UserRole>(connection)?;
+        info!(user_role_id = %saved.id, role = saved.role.as_str(), "Granted role");
+
+        Ok(saved)
+    })
+}
+
+/// Revoke a role granted with `grant_role` in the same channel
+pub fn revoke_role(
+    actor: &Actor,
+    role_user_id: String,
+    revoked_role: Role,
+    command_channel_id: String,
+    connection: &PgConnection,
+) -> Result<UserRole, BotError> {
+    use crate::schema::sotw::user_role::dsl::*;
+
+    connection.transaction(|| {
+        let active_competition = find_active_competition(connection)?;
+        let (scope, role_channel_id, role_competition_id) = role_target(
+            revoked_role,
+            &command_channel_id,
+            active_competition.as_ref(),
+        )?;
+        authorize(actor, Action::Grant(revoked_role), scope, connection)?;
+
+        let revoked = load_roles(&role_user_id, connection)?
+            .into_iter()
+            .find(|r| {
+                r.role == revoked_role
+                    && r.channel_id == role_channel_id
+                    && r.competition_id == role_competition_id
+            })
+            .ok_or_else(|| BotError {
+                data_error: DataError::InvalidArgument(format!(
+                    "<@{}> is not a {}",
+                    role_user_id, revoked_role
+                )),
+                message: "Unable to revoke role".to_string(),
+            })?;
+        delete(user_role.find(revoked.id)).execute(connection)?;
+        info!(user_role_id = %revoked.id, role = revoked.role.as_str(), "Revoked role");
+
+        Ok(revoked)
+    })
+}
+
+/// Roles that apply in a channel: bot admins, the channel's admins and the
+/// co-hosts of the active competition
+pub fn list_roles(
+    role_channel_id: String,
+    connection: &PgConnection,
+) -> Result<Vec<UserRole>, BotError> {
+    use crate::schema::sotw::user_role::dsl::*;
+
+    let active_id = find_active_competition(connection)?.map(|active| active.id);
+    let roles = user_role
+        .filter(
+            role.eq(Role::BotAdmin)
+                .or(channel_id.eq(role_channel_id))
+                .or(competition_id.eq(active_id)),
+        )
+        .order((role, granted))
+        .load::<UserRole>(connection)?;
+
+    Ok(roles)
+}
+
+// Where a role is granted, as the scope it is authorised in and the channel or
+// competition stored with it
+fn role_target<'a>(
+    target_role: Role,
+    command_channel_id: &'a str,
+    active_competition: Option<&'a Competition>,
+) -> Result<(Scope<'a>, Option<String>, Option<Uuid>), BotError> {
+    match target_role {
+        Role::BotAdmin => Ok((Scope::Channel(command_channel_id), None, None)),
+        Role::ChannelAdmin => Ok((
+            Scope::Channel(command_channel_id),
+            Some(command_channel_id.to_string()),
+            None,
+        )),
+        Role::CoHost => {
+            let active = active_competition.ok_or_else(|| BotError {
+                data_error: DataError::NoActiveCompetition,
+                message: "Co-hosts are added to the active competition".to_string(),
+            })?;
+            Ok((Scope::Competition(active), None, Some(active.id)))
+        }
+    }
+}
+
+/// Host roster of a channel in turn order, starting with the nominated host
+#[derive(PartialEq, Debug)]
+pub struct Roster {
+    pub channel_id: String,
+    pub members: Vec<HostRosterMember>,
+}
+
+impl Roster {
+    pub fn nominee(&self) -> Option<&str> {
+        self.members
+            .first()
+            .filter(|member| member.nominated.is_some())
+            .map(|member| member.user_id.as_str())
+    }
+}
+
+pub fn load_roster(roster_channel_id: &str, connection: &PgConnection) -> Result<Roster, BotError> {
+    use crate::schema::sotw::host_roster::dsl::*;
+
+    let mut members = host_roster
+        .filter(channel_id.eq(roster_channel_id))
+        .order(position)
+        .load::<HostRosterMember>(connection)?;
+    if let Some(index) = members.iter().position(|m| m.nominated.is_some()) {
+        members.rotate_left(index);
+    }
+
+    Ok(Roster {
+        channel_id: roster_channel_id.to_string(),
+        members,
+    })
+}
+
+/// Join the end of the host queue of a channel. The first member to join an
+/// empty roster is nominated right away.
+pub fn join_roster(
+    roster_channel_id: String,
+    member_user_id: String,
+    connection: &PgConnection,
+) -> Result<Roster, BotError> {
+    use crate::schema::sotw::host_roster::dsl::*;
+
+    connection.transaction(|| {
+        let roster = load_roster(&roster_channel_id, connection)?;
+        if roster.members.iter().any(|m| m.user_id == member_user_id) {
+            return Err(invalid_roster("you are already in the host queue"));
+        }
+
+        let last_position = roster.members.iter().map(|m| m.position).max();
+        let now = Utc::now();
+        insert_into(host_roster)
+            .values(&HostRosterInsert {
+                channel_id: roster_channel_id.clone(),
+                user_id: member_user_id,
+                position: last_position.unwrap_or(0) + 1,
+                nominated: match roster.nominee() {
+                    Some(_) => None,
+                    None => Some(now),
+                },
+                joined: now,
+            })
+            .execute(connection)?;
+
+        load_roster(&roster_channel_id, connection)
+    })
+}
+
+/// Leave the host queue of a channel, the next member is nominated when it was
+/// the leaving member's turn
+pub fn leave_roster(
+    roster_channel_id: String,
+    member_user_id: String,
+    connection: &PgConnection,
+) -> Result<Roster, BotError> {
+    use crate::schema::sotw::host_roster::dsl::*;
+
+    connection.transaction(|| {
+        let roster = load_roster(&roster_channel_id, connection)?;
+        if !roster.members.iter().any(|m| m.user_id == member_user_id) {
+            return Err(invalid_roster("you are not in the host queue"));
+        }
+
+        if roster.nominee() == Some(member_user_id.as_str()) {
+            nominate_next(&roster, connection)?;
+        }
+        delete(host_roster)
+            .filter(channel_id.eq(&roster_channel_id))
+            .filter(user_id.eq(&member_user_id))
+            .execute(connection)?;
+
+        load_roster(&roster_channel_id, connection)
+    })
+}
+
+/// Pass the turn of the nominated host to the next member of the queue. Only
+/// the nominated host and those managing the roster can skip a turn.
+pub fn skip_host(
+    actor: &Actor,
+    roster_channel_id: String,
+    connection: &PgConnection,
+) -> Result<Roster, BotError> {
+    connection.transaction(|| {
+        let roster = load_roster(&roster_channel_id, connection)?;
+        match roster.nominee() {
+            None => return Err(invalid_roster("no one is nominated to host")),
+            Some(nominee) if nominee != actor.user_id => authorize(
+                actor,
+                Action::ManageRoster,
+                Scope::Channel(&roster_channel_id),
+                connection,
+            )?,
+            Some(_) => {}
+        }
+
+        nominate_next(&roster, connection)?;
+
+        load_roster(&roster_channel_id, connection)
+    })
+}
+
+/// Nominate the next host after a competition closed. Once the nominated host
+/// hosted, the turn passes to the next member. A competition started by someone
+/// else leaves the turn where it was.
+fn advance_roster(
+    roster_channel_id: &str,
+    host_user_id: &str,
+    connection: &PgConnection,
+) -> Result<Option<String>, BotError> {
+    let roster = load_roster(roster_channel_id, connection)?;
+    match roster.nominee() {
+        Some(nominee) if nominee != host_user_id => Ok(Some(nominee.to_string())),
+        _ => nominate_next(&roster, connection),
+    }
+}
+
+// The member after the nominee gets the turn, wrapping around to the start of
+// the queue. Without a nominee the first member gets it.
+fn nominate_next(roster: &Roster, connection: &PgConnection) -> Result<Option<String>, BotError> {
+    use crate::schema::sotw::host_roster::dsl::*;
+
+    let next = match roster.nominee() {
+        Some(_) => roster.members.get(1).or_else(|| roster.members.first()),
+        None => roster.members.first(),
+    };
+
+    // The current nominee is cleared first, only one member of a channel can be nominated
+    update(host_roster.filter(channel_id.eq(&roster.channel_id)))
+        .set(nominated.eq(None::<DateTime<Utc>>))
+        .execute(connection)?;
+    if let Some(next) = next {
+        update(host_roster.find(next.id))
+            .set(nominated.eq(Some(Utc::now())))
+            .execute(connection)?;
+    }
+
+    Ok(next.map(|next| next.user_id.clone()))
+}
+
+fn invalid_roster(reason: &str) -> BotError {
+    BotError {
+        data_error: DataError::InvalidArgument(reason.to_string()),
+        message: "Unable to update the host roster".to_string(),
+    }
+}
+
+pub fn list_badges(badge_user_id: &str, connection: &PgConnection) -> Result<Vec<Badge>, BotError> {
+    use crate::schema::sotw::badge::dsl::*;
+
+    let badges = badge
+        .filter(user_id.eq(badge_user_id))
+        .order(awarded)
+        .load::<Badge>(connection)?;
+
+    Ok(badges)
+}
+
+fn place_songs(places: &[(Uuid, i32)], connection: &PgConnection) -> Result<(), BotError> {
+    use crate::schema::sotw::song::dsl::*;
+
+    for (song_id, song_place) in places {
+        update(song.find(song_id))
+            .set(place.eq(Some(song_place)))
+            .execute(connection)?;
+    }
+
+    Ok(())
+}
+
+/// Standings of a season over its closed competitions
+#[derive(PartialEq, Debug)]
+pub struct SeasonStandings {
+    pub season: Season,
+    // Closed competitions counted in the standings
+    pub closed: i64,
+    pub standings: Vec<Standing>,
+}
+
+/// Start a season, which open competitions join until it has as many closed
+/// competitions as planned. Only one season runs at a time.
+pub fn start_season(
+    season_insert: SeasonInsert,
+    connection: &PgConnection,
+) -> Result<Season, BotError> {
+    use crate::schema::sotw::season::dsl::*;
+
+    let invalid = |reason: String| BotError {
+        data_error: DataError::InvalidArgument(reason),
+        message: "Unable to start season".to_string(),
+    };
+    if season_insert.competitions < 1 {
+        return Err(invalid("a season needs at least 1 competition".to_string()));
+    }
+    if let Some(running) = find_active_season(connection)? {
+        return Err(invalid(format!(
+            "the season {} is still running",
+            running.name
+        )));
+    }
+
+    let saved_season = insert_into(season)
+        .values(&season_insert)
+        .get_result::<Season>(connection)?;
+
+    info!(season_id = %saved_season.id, "Started season");
+
+    Ok(saved_season)
+}
+
+pub fn find_active_season(connection: &PgConnection) -> Result<Option<Season>, BotError> {
+    use crate::schema::sotw::season::dsl::*;
+
+    let result = season
         .filter(is_active.eq(true))
-        .first::<Competition>(connection)
+        .first::<Season>(connection)
         .optional()?;
 
-    //Todo: add error message instead of passing the error along
-
     Ok(result)
 }
 
-pub fn list_songs_active_competition(connection: &PgConnection) -> Result<Vec<Song>, BotError> {
-    use crate::schema::sotw::song::columns::competition_id;
-    use crate::schema::sotw::song::dsl::song;
+/// Standings of the running season, or of the latest one when none is running
+pub fn find_season_standings(connection: &PgConnection) -> Result<SeasonStandings, BotError> {
+    use crate::schema::sotw::season::dsl::*;
 
-    let active_competition = find_active_competition(connection)?;
+    let latest = season
+        .order((is_active.desc(), started.desc()))
+        .first::<Season>(connection)
+        .optional()?
+        .ok_or_else(|| BotError {
+            data_error: DataError::InvalidArgument(
+                "there is no season yet, start one with `/sotw season start <name>`".to_string(),
+            ),
+            message: "Unable to find a season".to_string(),
+        })?;
 
-    match active_competition {
-        Some(active_competition) => {
-            let songs = song
-                .filter(competition_id.eq(active_competition.id))
-                .load::<Song>(connection)?;
-            Ok(songs)
+    load_standings(latest, connection)
+}
+
+/// End the running season before all its competitions are held
+pub fn end_season(
+    cmd_user_id: String,
+    connection: &PgConnection,
+) -> Result<SeasonStandings, BotError> {
+    connection.transaction(|| {
+        let running = find_active_season(connection)?.ok_or_else(|| BotError {
+            data_error: DataError::InvalidArgument("no season is running".to_string()),
+            message: "Unable to end season".to_string(),
+        })?;
+        if running.user_id != cmd_user_id {
+            return Err(BotError {
+                data_error: DataError::UserDoesNotOwnEntity(running.id),
+                message: "User does not own the running season".to_string(),
+            });
         }
-        None => Err(BotError {
-            data_error: DataError::NoActiveCompetition,
-            message: "Unable to find active competition when trying to list songs".to_string(),
-        }),
+
+        close_season(running.id, connection)
+    })
+}
+
+fn close_season_if_complete(
+    closed_season_id: Uuid,
+    connection: &PgConnection,
+) -> Result<Option<SeasonStandings>, BotError> {
+    use crate::schema::sotw::season::dsl::*;
+
+    let current = season.find(closed_season_id).first::<Season>(connection)?;
+    let closed = count_closed_competitions(current.id, connection)?;
+    if !current.is_active || closed < current.competitions as i64 {
+        return Ok(None);
     }
+
+    close_season(current.id, connection).map(Some)
 }
 
-pub fn save_song(
-    new_song_uri: String,
-    new_song_user_id: String,
+fn close_season(
+    closed_season_id: Uuid,
     connection: &PgConnection,
-) -> Result<Song, BotError> {
-    use crate::schema::sotw::song::columns::*;
-    use crate::schema::sotw::song::dsl::song;
+) -> Result<SeasonStandings, BotError> {
+    use crate::schema::sotw::season::dsl::*;
 
-    let result = find_active_competition(connection)?;
+    let closed = update(season.find(closed_season_id))
+        .set((is_active.eq(false), ended.eq(Some(Utc::now()))))
+        .get_result::<Season>(connection)?;
 
-    match result {
+    info!(season_id = %closed.id, "Closed season");
+
+    load_standings(closed, connection)
+}
+
+fn count_closed_competitions(
+    in_season_id: Uuid,
+    connection: &PgConnection,
+) -> Result<i64, BotError> {
+    use crate::schema::sotw::competition::dsl::*;
+
+    let closed = competition
+        .filter(season_id.eq(in_season_id))
+        .filter(is_active.eq(false))
+        .count()
+        .get_result(connection)?;
+
+    Ok(closed)
+}
+
+fn load_standings(
+    standing_season: Season,
+    connection: &PgConnection,
+) -> Result<SeasonStandings, BotError> {
+    use crate::schema::sotw::{competition, song};
+
+    let places: Vec<(String, Option<i32>)> = song::table
+        .inner_join(competition::table)
+        .filter(competition::season_id.eq(standing_season.id))
+        .filter(competition::is_active.eq(false))
+        .filter(song::place.is_not_null())
+        .order((competition::started.asc(), song::number.asc()))
+        .select((song::user_id, song::place))
+        .load(connection)?;
+    let places: Vec<(String, i32)> = places
+        .into_iter()
+        .filter_map(|(user, song_place)| song_place.map(|song_place| (user, song_place)))
+        .collect();
+
+    Ok(SeasonStandings {
+        closed: count_closed_competitions(standing_season.id, connection)?,
+        standings: standings(&standing_season.points, &places),
+        season: standing_season,
+    })
+}
+
+/// A page of closed competitions, the latest first
+#[derive(PartialEq, Debug)]
+pub struct HistoryPage {
+    // Competitions with their winning song, when there was one
+    pub competitions: Vec<(Competition, Option<Song>)>,
+    // First page is 1
+    pub page: i64,
+    pub page_size: i64,
+    // Closed competitions over all pages
+    pub total: i64,
+}
+
+/// A closed competition as it was announced
+#[derive(PartialEq, Debug)]
+pub enum PastCompetition {
+    Open(Box<CompetitionResults>),
+    Bracket(Box<Bracket>),
+}
+
+/// List closed competitions a page at a time, the latest started first
+pub fn list_past_competitions(
+    page: i64,
+    page_size: i64,
+    connection: &PgConnection,
+) -> Result<HistoryPage, BotError> {
+    use crate::schema::sotw::{competition, song};
+
+    if page < 1 || page_size < 1 {
+        return Err(BotError {
+            data_error: DataError::InvalidArgument("pages start at 1".to_string()),
+            message: "Unable to list competitions".to_string(),
+        });
+    }
+
+    let total = competition::table
+        .filter(competition::is_active.eq(false))
+        .count()
+        .get_result(connection)?;
+    let competitions = competition::table
+        .filter(competition::is_active.eq(false))
+        .order((competition::started.desc(), competition::id))
+        .offset((page - 1) * page_size)
+        .limit(page_size)
+        .load::<Competition>(connection)?;
+
+    let winner_ids: Vec<Uuid> = competitions
+        .iter()
+        .filter_map(|past| past.winner_song_id)
+        .collect();
+    let winners = song::table
+        .filter(song::id.eq_any(winner_ids))
+        .load::<Song>(connection)?;
+
+    let competitions = competitions
+        .into_iter()
+        .map(|past| {
+            let winner = winners
+                .iter()
+                .find(|winner| Some(winner.id) == past.winner_song_id)
+                .cloned();
+            (past, winner)
+        })
+        .collect();
+
+    Ok(HistoryPage {
+        competitions,
+        page,
+        page_size,
+        total,
+    })
+}
+
+/// Results of a closed competition, by its number in the history or by id
+pub fn find_past_competition(
+    competition_ref: CompetitionRef,
+    ballot_key: &[u8],
+    connection: &PgConnection,
+) -> Result<PastCompetition, BotError> {
+    let past = find_closed_competition(competition_ref, connection)?;
+
+    match past.kind {
+        CompetitionKind::Open => tally_results(past, ballot_key, connection)
+            .map(|results| PastCompetition::Open(Box::new(results))),
+        CompetitionKind::Bracket => load_bracket(past, connection)
+            .map(|bracket| PastCompetition::Bracket(Box::new(bracket))),
+    }
+}
+
+// A closed competition by its number in the history or its id
+fn find_closed_competition(
+    competition_ref: CompetitionRef,
+    connection: &PgConnection,
+) -> Result<Competition, BotError> {
+    use crate::schema::sotw::competition::dsl::*;
+
+    let closed = competition.filter(is_active.eq(false));
+    match competition_ref {
+        CompetitionRef::Recent(number) => closed
+            .order((started.desc(), id))
+            .offset(number - 1)
+            .first::<Competition>(connection)
+            .optional()?,
+        CompetitionRef::Id(competition_id) => closed
+            .filter(id.eq(competition_id))
+            .first::<Competition>(connection)
+            .optional()?,
+    }
+    .ok_or_else(|| BotError {
+        data_error: DataError::InvalidArgument(format!(
+            "there is no closed competition {}",
+            competition_ref
+        )),
+        message: "Unable to find competition".to_string(),
+    })
+}
+
+/// A user's record over the closed competitions they entered. Brackets between
+/// past winners are left out.
+#[derive(PartialEq, Debug)]
+pub struct UserStats {
+    pub user_id: String,
+    pub entered: i64,
+    pub wins: i64,
+    // Songs placed in the top three, counted once per competition
+    pub podiums: i64,
+    // Percentage of the votes, averaged over the competitions which had votes
+    pub average_share: Option<f64>,
+    // Competitions in a row entered, up to the latest closed one
+    pub streak: i64,
+    // Service most songs were shared from, with its number of songs
+    pub provider: Option<(String, i64)>,
+    pub badges: Vec<Badge>,
+}
+
+#[derive(QueryableByName)]
+struct VoteSum {
+    #[sql_type = "diesel::sql_types::Uuid"]
+    competition_id: Uuid,
+    #[sql_type = "diesel::sql_types::Varchar"]
+    user_id: String,
+    #[sql_type = "diesel::sql_types::BigInt"]
+    votes: i64,
+}
+
+pub fn user_stats(
+    target_user_id: String,
+    connection: &PgConnection,
+) -> Result<UserStats, BotError> {
+    use crate::schema::sotw::{competition, song};
+    use diesel::sql_types::{self, Array, Text};
+
+    let closed = competition::table
+        .filter(competition::is_active.eq(false))
+        .filter(competition::kind.eq(CompetitionKind::Open));
+    let competitions: Vec<(Uuid, Option<Uuid>)> = closed
+        .order(competition::started.desc())
+        .select((competition::id, competition::winner_song_id))
+        .load(connection)?;
+    let songs: Vec<Song> = song::table
+        .filter(song::user_id.eq(&target_user_id))
+        .filter(song::competition_id.eq_any(closed.select(competition::id)))
+        .load(connection)?;
+
+    let mut entered: Vec<Uuid> = songs.iter().map(|s| s.competition_id).collect();
+    entered.sort();
+    entered.dedup();
+    let mut podiums: Vec<Uuid> = songs
+        .iter()
+        .filter(|s| matches!(s.place, Some(1..=3)))
+        .map(|s| s.competition_id)
+        .collect();
+    podiums.sort();
+    podiums.dedup();
+    let wins = competitions
+        .iter()
+        .filter(|(_, winner)| songs.iter().any(|s| Some(s.id) == *winner))
+        .count();
+
+    // Votes of the audience per submitter. Ranked ballots count first
+    // preferences, other ballots the score or points, which is 1 for a plain vote.
+    let vote_sums: Vec<VoteSum> = sql_query(
+        "SELECT s.competition_id, s.user_id, COALESCE(SUM(v.score), 0) AS votes \
+         FROM sotw.song_vote v \
+         JOIN sotw.song s ON s.id = v.song_id \
+         JOIN sotw.competition c ON c.id = s.competition_id \
+         WHERE s.competition_id = ANY($1) \
+         AND NOT v.runoff AND NOT v.panel AND v.category IS NULL \
+         AND (v.rank = 1 OR c.voting_method <> ALL($2)) \
+         GROUP BY s.competition_id, s.user_id",
+    )
+    .bind::<Array<sql_types::Uuid>, _>(&entered)
+    .bind::<Array<Text>, _>(vec![
+        VotingMethod::InstantRunoff.as_str(),
+        VotingMethod::Schulze.as_str(),
+    ])
+    .load(connection)?;
+    let votes: Vec<(i64, i64)> = entered
+        .iter()
+        .map(|entered_id| {
+            let in_competition = vote_sums
+                .iter()
+                .filter(|vote_sum| vote_sum.competition_id == *entered_id);
+            let own = in_competition
+                .clone()
+                .filter(|vote_sum| vote_sum.user_id == target_user_id)
+                .map(|vote_sum| vote_sum.votes)
+                .sum();
+            let total = in_competition.map(|vote_sum| vote_sum.votes).sum();
+            (own, total)
+        })
+        .collect();
+
+    let competition_ids: Vec<Uuid> = competitions.iter().map(|(id, _)| *id).collect();
+    let song_uris: Vec<&str> = songs.iter().map(|s| s.song_uri.as_str()).collect();
+    Ok(UserStats {
+        entered: entered.len() as i64,
+        wins: wins as i64,
+        podiums: podiums.len() as i64,
+        average_share: average_share(&votes),
+        streak: streak(&competition_ids, &entered),
+        provider: favourite_provider(&song_uris),
+        badges: list_badges(&target_user_id, connection)?,
+        user_id: target_user_id,
+    })
+}
+
+// Seeds are kept positive so they read the same when published
+fn random_seed() -> i64 {
+    let bytes = Uuid::new_v4();
+    let mut seed = [0; 8];
+    seed.copy_from_slice(&bytes.as_bytes()[..8]);
+
+    i64::from_le_bytes(seed) & i64::MAX
+}
+
+pub fn find_active_competition(connection: &PgConnection) -> Result<Option<Competition>, BotError> {
+    use crate::schema::sotw::competition::dsl::*;
+
+    let result = competition
+        .filter(is_active.eq(true))
+        .first::<Competition>(connection)
+        .optional()?;
+
+    //Todo: add error message instead of passing the error along
+
+    Ok(result)
+}
+
+/// Songs of a competition, in the order they were submitted
+pub fn list_songs(
+    song_competition_id: Uuid,
+    connection: &PgConnection,
+) -> Result<Vec<Song>, BotError> {
+    use crate::schema::sotw::song::dsl::*;
+
+    let songs = song
+        .filter(competition_id.eq(song_competition_id))
+        .order(number)
+        .load::<Song>(connection)?;
+
+    Ok(songs)
+}
+
+/// Songs of the active competition, along with the competition
+pub fn list_songs_active_competition(
+    connection: &PgConnection,
+) -> Result<(Competition, Vec<Song>), BotError> {
+    let active_competition = find_active_competition(connection)?;
+
+    match active_competition {
+        Some(active_competition) => {
+            let songs = list_songs(active_competition.id, connection)?;
+            Ok((active_competition, songs))
+        }
+        None => Err(BotError {
+            data_error: DataError::NoActiveCompetition,
+            message: "Unable to find active competition when trying to list songs".to_string(),
+        }),
+    }
+}
+
+/// Remove a song from the active competition along with the votes for it,
+/// returning the removed song
+pub fn remove_song(
+    actor: &Actor,
+    removed: SongRef,
+    connection: &PgConnection,
+) -> Result<Song, BotError> {
+    use crate::schema::sotw::{song, song_vote};
+
+    connection.transaction(|| {
+        let removed_song = find_moderated_song(actor, removed, connection)?;
+
+        delete(song_vote::table.filter(song_vote::song_id.eq(removed_song.id)))
+            .execute(connection)?;
+        delete(song::table.find(removed_song.id)).execute(connection)?;
+        info!(song_id = %removed_song.id, "Removed song");
+
+        Ok(removed_song)
+    })
+}
+
+/// Disqualify a song of the active competition. It stays listed, but is left
+/// out of the tally and can no longer be voted for.
+pub fn disqualify_song(
+    actor: &Actor,
+    disqualified_song: SongRef,
+    connection: &PgConnection,
+) -> Result<Song, BotError> {
+    use crate::schema::sotw::song::dsl::*;
+
+    connection.transaction(|| {
+        let found = find_moderated_song(actor, disqualified_song, connection)?;
+        if found.disqualified {
+            return Err(BotError {
+                data_error: DataError::InvalidArgument(format!(
+                    "song #{} is already disqualified",
+                    found.number
+                )),
+                message: "Unable to disqualify song".to_string(),
+            });
+        }
+
+        let updated = update(song.find(found.id))
+            .set(disqualified.eq(true))
+            .get_result::<Song>(connection)?;
+        info!(song_id = %updated.id, "Disqualified song");
+
+        Ok(updated)
+    })
+}
+
+// Song of the active competition the actor may moderate. The songs of a bracket
+// are seeded into its matches and stay as they are.
+fn find_moderated_song(
+    actor: &Actor,
+    moderated: SongRef,
+    connection: &PgConnection,
+) -> Result<Song, BotError> {
+    let (active_competition, songs) = list_songs_active_competition(connection)?;
+    authorize(
+        actor,
+        Action::Moderate,
+        Scope::Competition(&active_competition),
+        connection,
+    )?;
+    let invalid = |reason: String| BotError {
+        data_error: DataError::InvalidArgument(reason),
+        message: "Unable to moderate song".to_string(),
+    };
+    if active_competition.kind == CompetitionKind::Bracket {
+        return Err(invalid(
+            "the songs of a bracket can not be moderated".to_string(),
+        ));
+    }
+
+    songs
+        .into_iter()
+        .find(|song| moderated.matches(song))
+        .ok_or_else(|| invalid(format!("there is no song {}", moderated)))
+}
+
+/// Ban a user from submitting songs and voting in a channel
+pub fn ban_user(
+    actor: &Actor,
+    banned_user_id: String,
+    ban_channel_id: String,
+    ban_reason: Option<String>,
+    connection: &PgConnection,
+) -> Result<Ban, BotError> {
+    use crate::schema::sotw::ban::dsl::*;
+
+    connection.transaction(|| {
+        authorize_ban(actor, &ban_channel_id, connection)?;
+        let banned_before = ban
+            .filter(channel_id.eq(&ban_channel_id))
+            .filter(user_id.eq(&banned_user_id))
+            .count()
+            .get_result::<i64>(connection)?;
+        if banned_before > 0 {
+            return Err(BotError {
+                data_error: DataError::InvalidArgument(format!(
+                    "<@{}> is already banned in this channel",
+                    banned_user_id
+                )),
+                message: "Unable to ban user".to_string(),
+            });
+        }
+
+        let saved = insert_into(ban)
+            .values(&BanInsert {
+                user_id: banned_user_id,
+                channel_id: ban_channel_id,
+                reason: ban_reason,
+                banned_by: actor.user_id.clone(),
+                banned: Utc::now(),
+            })
+            .get_result::<Ban>(connection)?;
+        info!(ban_id = %saved.id, "Banned user");
+
+        Ok(saved)
+    })
+}
+
+/// Lift the ban of a user in a channel
+pub fn unban_user(
+    actor: &Actor,
+    banned_user_id: String,
+    ban_channel_id: String,
+    connection: &PgConnection,
+) -> Result<Ban, BotError> {
+    use crate::schema::sotw::ban::dsl::*;
+
+    connection.transaction(|| {
+        authorize_ban(actor, &ban_channel_id, connection)?;
+        let lifted = ban
+            .filter(channel_id.eq(&ban_channel_id))
+            .filter(user_id.eq(&banned_user_id))
+            .first::<Ban>(connection)
+            .optional()?
+            .ok_or_else(|| BotError {
+                data_error: DataError::InvalidArgument(format!(
+                    "<@{}> is not banned in this channel",
+                    banned_user_id
+                )),
+                message: "Unable to lift ban".to_string(),
+            })?;
+        delete(ban.find(lifted.id)).execute(connection)?;
+        info!(ban_id = %lifted.id, "Lifted ban");
+
+        Ok(lifted)
+    })
+}
+
+/// Users banned in a channel, in the order they were banned
+pub fn list_bans(ban_channel_id: String, connection: &PgConnection) -> Result<Vec<Ban>, BotError> {
+    use crate::schema::sotw::ban::dsl::*;
+
+    let bans = ban
+        .filter(channel_id.eq(ban_channel_id))
+        .order(banned)
+        .load::<Ban>(connection)?;
+
+    Ok(bans)
+}
+
+// Bans are managed by the admins of the channel, and by those running the
+// competition active in it
+fn authorize_ban(
+    actor: &Actor,
+    ban_channel_id: &str,
+    connection: &PgConnection,
+) -> Result<(), BotError> {
+    let active_competition = find_active_competition(connection)?
+        .filter(|active| active.channel_id.as_deref() == Some(ban_channel_id));
+    let scope = active_competition
+        .as_ref()
+        .map_or(Scope::Channel(ban_channel_id), Scope::Competition);
+
+    authorize(actor, Action::Ban, scope, connection)
+}
+
+// Users banned in the channel of a competition. Competitions started before
+// their channel was recorded have no bans.
+fn banned_users(
+    banned_competition: &Competition,
+    connection: &PgConnection,
+) -> Result<Vec<String>, BotError> {
+    use crate::schema::sotw::ban::dsl::*;
+
+    match &banned_competition.channel_id {
+        Some(competition_channel_id) => Ok(ban
+            .filter(channel_id.eq(competition_channel_id))
+            .select(user_id)
+            .load::<String>(connection)?),
+        None => Ok(vec![]),
+    }
+}
+
+pub fn save_song(
+    new_song_uri: String,
+    new_song_user_id: String,
+    connection: &PgConnection,
+) -> Result<Song, BotError> {
+    use crate::schema::sotw::competition;
+    use crate::schema::sotw::song::columns::*;
+    use crate::schema::sotw::song::dsl::song;
+
+    let result = find_active_competition(connection)?;
+
+    match result {
         None => Err(BotError {
             data_error: DataError::NotImplementedError,
             message: "".to_string(),
         }),
-        Some(active_competition) => {
+        Some(active_competition) if active_competition.kind == CompetitionKind::Bracket => {
+            Err(BotError {
+                data_error: DataError::InvalidArgument(
+                    "the songs of a bracket are seeded from past winners".to_string(),
+                ),
+                message: "Unable to save song".to_string(),
+            })
+        }
+        Some(active_competition) if active_competition.runoff => Err(BotError {
+            data_error: DataError::InvalidArgument(
+                "songs can not be added during a runoff vote".to_string(),
+            ),
+            message: "Unable to save song".to_string(),
+        }),
+        Some(active_competition) => connection.transaction(|| {
+            let rules = &active_competition.rules;
+            let invalid = |reason: String| BotError {
+                data_error: DataError::InvalidArgument(reason),
+                message: "Unable to save song".to_string(),
+            };
+
+            if banned_users(&active_competition, connection)?.contains(&new_song_user_id) {
+                return Err(invalid("you are banned in this channel".to_string()));
+            }
+            if !rules.late_submissions && active_competition.is_late(chrono::Utc::now()) {
+                return Err(invalid(format!(
+                    "submissions closed {}",
+                    active_competition
+                        .submission_deadline
+                        .map_or_else(String::new, |deadline| deadline
+                            .format("%Y-%m-%d %H:%M UTC")
+                            .to_string())
+                )));
+            }
+
+            // Songs are numbered in the order they are submitted, numbers are never
+            // reused. Taking a number locks the competition, so concurrent submissions
+            // wait for each other before counting the user's songs.
+            let new_number = update(competition::table.find(active_competition.id))
+                .set(competition::next_song_number.eq(competition::next_song_number + 1))
+                .returning(competition::next_song_number)
+                .get_result::<i32>(connection)?
+                - 1;
+
+            // A single song per user is replaced when submitting again
+            if rules.songs_per_user == 1 {
+                delete(song)
+                    .filter(competition_id.eq(active_competition.id))
+                    .filter(user_id.eq(&new_song_user_id))
+                    .execute(connection)?;
+            } else {
+                let submitted = song
+                    .filter(competition_id.eq(active_competition.id))
+                    .filter(user_id.eq(&new_song_user_id))
+                    .count()
+                    .get_result::<i64>(connection)?;
+                if submitted >= i64::from(rules.songs_per_user) {
+                    return Err(invalid(format!(
+                        "at most {} songs per user",
+                        rules.songs_per_user
+                    )));
+                }
+            }
+
             let new_song_insert = SongInsert {
                 user_id: new_song_user_id.clone(),
                 song_uri: new_song_uri,
                 competition_id: active_competition.id,
+                number: new_number,
             };
 
-            delete(song)
-                .filter(competition_id.eq(active_competition.id))
-                .filter(user_id.eq(&new_song_user_id))
-                .execute(connection)?;
+            let saved_song = insert_into(song)
+                .values(&new_song_insert)
+                .get_result::<Song>(connection)?;
+
+            info!(
+                user_id = new_song_user_id.as_str(),
+                song_id = %saved_song.id,
+                song_uri = %Redacted(&saved_song.song_uri),
+                "Saved song"
+            );
+
+            Ok(saved_song)
+        }),
+    }
+}
+
+pub fn save_song_vote(
+    new_song_vote: SongVoteInsert,
+    ballot_key: &[u8],
+    connection: &PgConnection,
+) -> Result<SongVote, BotError> {
+    use crate::schema::sotw::song_vote::dsl::song_vote;
+    use crate::schema::sotw::{competition, song};
+
+    let (voted_song, song_competition) = song::table
+        .inner_join(competition::table)
+        .filter(song::id.eq(new_song_vote.song_id))
+        .first::<(Song, Competition)>(connection)?;
+    let invalid = |reason: String| BotError {
+        data_error: DataError::InvalidArgument(reason),
+        message: "Unable to save vote".to_string(),
+    };
+    // Secret ballots are cast under a hash of the voter, banned users are hashed alike
+    let banned = banned_users(&song_competition, connection)?
+        .iter()
+        .any(|banned| voter_id(&song_competition, banned, ballot_key) == new_song_vote.user_id);
+    if banned {
+        return Err(invalid("you are banned in this channel".to_string()));
+    }
+    if voted_song.disqualified {
+        return Err(invalid(format!(
+            "song #{} is disqualified",
+            voted_song.number
+        )));
+    }
+    let submitter = voter_id(&song_competition, &voted_song.user_id, ballot_key);
+    if !song_competition.rules.self_vote && submitter == new_song_vote.user_id {
+        return Err(invalid(format!(
+            "voting for your own song #{} is not allowed",
+            voted_song.number
+        )));
+    }
+
+    let saved_song_vote = insert_into(song_vote)
+        .values(&new_song_vote)
+        .get_result::<SongVote>(connection)?;
+
+    Ok(saved_song_vote)
+}
+
+/// Cast a user's ballot in the active competition, replacing any earlier ballot.
+/// The songs on the ballot, and the values given, must fit the voting method.
+/// During a runoff the ballot is a single vote for one of the tied songs.
+/// Secret ballots are stored under a hash of the user keyed with `ballot_key`.
+pub fn save_ballot(
+    choices: Vec<VoteChoice>,
+    cmd_user_id: String,
+    ballot_key: &[u8],
+    connection: &PgConnection,
+) -> Result<(Competition, Vec<SongVote>), BotError> {
+    use crate::schema::sotw::song_vote::dsl::*;
+
+    let active_competition = find_active_competition(connection)?.ok_or_else(|| BotError {
+        data_error: DataError::NoActiveCompetition,
+        message: "Unable to find active competition when trying to vote".to_string(),
+    })?;
+    if active_competition.rules.secret_ballot && ballot_key.is_empty() {
+        return Err(BotError {
+            data_error: DataError::InvalidArgument(
+                "secret ballots need a ballot key to be configured".to_string(),
+            ),
+            message: "Unable to save vote".to_string(),
+        });
+    }
+    if active_competition.kind == CompetitionKind::Bracket {
+        let ballot_user_id = voter_id(&active_competition, &cmd_user_id, ballot_key);
+        let votes = save_match_votes(
+            &active_competition,
+            &choices,
+            ballot_user_id,
+            ballot_key,
+            connection,
+        )?;
+        return Ok((active_competition, votes));
+    }
+    // Judges vote for the panel, but like everyone else in a runoff
+    let panel_vote = active_competition.is_judge(&cmd_user_id) && !active_competition.runoff;
+    if panel_vote && !active_competition.rubric.is_empty() {
+        return Err(BotError {
+            data_error: DataError::InvalidArgument(format!(
+                "judges score the songs on {}, for example `judge 1 {}=3`",
+                active_competition.rubric.join(", "),
+                active_competition.rubric[0]
+            )),
+            message: "Unable to save vote".to_string(),
+        });
+    }
+    let ballot_user_id = voter_id(&active_competition, &cmd_user_id, ballot_key);
+
+    let songs = list_songs(active_competition.id, connection)?;
+    let (method, songs) = if active_competition.runoff {
+        let audience = tally_competition(&active_competition, &songs, ballot_key, connection)?;
+        let counted = counted_song_ids(&songs, &audience);
+        let tied = match tally_panel(&active_competition, &counted, &audience, connection)? {
+            Some(combined) => combined.winners,
+            None => audience.winners,
+        };
+        let tied_songs = songs
+            .into_iter()
+            .filter(|song| tied.contains(&song.id))
+            .collect();
+        (VotingMethod::Plurality, tied_songs)
+    } else {
+        (active_competition.voting_method, songs)
+    };
+    let ballot = resolve_ballot(method, active_competition.points_budget, &songs, &choices)?;
+
+    connection.transaction(|| {
+        let competition_song_ids: Vec<Uuid> = songs.iter().map(|s| s.id).collect();
+        delete(song_vote)
+            .filter(user_id.eq(&ballot_user_id))
+            .filter(song_id.eq_any(competition_song_ids))
+            .filter(runoff.eq(active_competition.runoff))
+            .filter(category.is_null())
+            .filter(panel.eq(panel_vote))
+            .execute(connection)?;
+
+        let votes = ballot
+            .into_iter()
+            .enumerate()
+            .map(|(index, (chosen_song_id, chosen_score))| {
+                let new_song_vote = SongVoteInsert {
+                    user_id: ballot_user_id.clone(),
+                    song_id: chosen_song_id,
+                    rank: index as i32 + 1,
+                    score: chosen_score,
+                    runoff: active_competition.runoff,
+                    category: None,
+                    panel: panel_vote,
+                    criterion: None,
+                    bracket_match_id: None,
+                };
+                save_song_vote(new_song_vote, ballot_key, connection)
+            })
+            .collect::<Result<Vec<SongVote>, BotError>>()?;
+
+        info!(
+            user_id = ballot_user_id.as_str(),
+            competition_id = %active_competition.id,
+            choices = votes.len(),
+            "Saved ballot"
+        );
+
+        Ok((active_competition, votes))
+    })
+}
+
+/// Cast a user's vote in an award category of the active competition,
+/// replacing any earlier vote in the category. Users vote for a single song.
+pub fn save_category_vote(
+    award: String,
+    choice: VoteChoice,
+    cmd_user_id: String,
+    ballot_key: &[u8],
+    connection: &PgConnection,
+) -> Result<(Competition, SongVote), BotError> {
+    use crate::schema::sotw::song_vote::dsl::*;
+
+    let active_competition = find_active_competition(connection)?.ok_or_else(|| BotError {
+        data_error: DataError::NoActiveCompetition,
+        message: "Unable to find active competition when trying to vote".to_string(),
+    })?;
+    if !active_competition.categories.contains(&award) {
+        let reason = if active_competition.categories.is_empty() {
+            format!("no category {}, the competition has no categories", award)
+        } else {
+            format!(
+                "no category {}, vote in one of {}",
+                award,
+                active_competition.categories.join(", ")
+            )
+        };
+        return Err(BotError {
+            data_error: DataError::InvalidArgument(reason),
+            message: "Unable to save vote".to_string(),
+        });
+    }
+    if active_competition.rules.secret_ballot && ballot_key.is_empty() {
+        return Err(BotError {
+            data_error: DataError::InvalidArgument(
+                "secret ballots need a ballot key to be configured".to_string(),
+            ),
+            message: "Unable to save vote".to_string(),
+        });
+    }
+    let ballot_user_id = voter_id(&active_competition, &cmd_user_id, ballot_key);
+
+    let songs = list_songs(active_competition.id, connection)?;
+    let ballot = resolve_ballot(
+        VotingMethod::Plurality,
+        active_competition.points_budget,
+        &songs,
+        &[choice],
+    )?;
+
+    connection.transaction(|| {
+        let competition_song_ids: Vec<Uuid> = songs.iter().map(|s| s.id).collect();
+        delete(song_vote)
+            .filter(user_id.eq(&ballot_user_id))
+            .filter(song_id.eq_any(competition_song_ids))
+            .filter(category.eq(&award))
+            .execute(connection)?;
+
+        let (chosen_song_id, chosen_score) = ballot[0];
+        let new_song_vote = SongVoteInsert {
+            user_id: ballot_user_id.clone(),
+            song_id: chosen_song_id,
+            rank: 1,
+            score: chosen_score,
+            runoff: false,
+            category: Some(award.clone()),
+            panel: false,
+            criterion: None,
+            bracket_match_id: None,
+        };
+        let vote = save_song_vote(new_song_vote, ballot_key, connection)?;
+
+        info!(
+            user_id = ballot_user_id.as_str(),
+            competition_id = %active_competition.id,
+            category = award.as_str(),
+            "Saved category vote"
+        );
+
+        Ok((active_competition, vote))
+    })
+}
+
+/// Record a judge's scores for a song on the rubric of the active competition,
+/// replacing earlier scores for the same criteria
+pub fn save_rubric_scores(
+    choice: SongRef,
+    scores: Vec<(String, i32)>,
+    cmd_user_id: String,
+    ballot_key: &[u8],
+    connection: &PgConnection,
+) -> Result<(Competition, Vec<SongVote>), BotError> {
+    use crate::schema::sotw::song_vote::dsl::*;
+
+    let active_competition = find_active_competition(connection)?.ok_or_else(|| BotError {
+        data_error: DataError::NoActiveCompetition,
+        message: "Unable to find active competition when trying to judge".to_string(),
+    })?;
+    let invalid = |reason: String| BotError {
+        data_error: DataError::InvalidArgument(reason),
+        message: "Unable to save scores".to_string(),
+    };
+
+    if !active_competition.is_judge(&cmd_user_id) {
+        return Err(invalid("only judges score songs".to_string()));
+    }
+    if active_competition.rubric.is_empty() {
+        return Err(invalid(
+            "the competition has no rubric, judges vote like everyone else".to_string(),
+        ));
+    }
+    if active_competition.runoff {
+        return Err(invalid(
+            "the panel can not change its scores during a runoff vote".to_string(),
+        ));
+    }
+    if active_competition.rules.secret_ballot && ballot_key.is_empty() {
+        return Err(invalid(
+            "secret ballots need a ballot key to be configured".to_string(),
+        ));
+    }
+    for (index, (scored, value)) in scores.iter().enumerate() {
+        if !active_competition.rubric.contains(scored) {
+            return Err(invalid(format!(
+                "no criterion {}, score one of {}",
+                scored,
+                active_competition.rubric.join(", ")
+            )));
+        }
+        if scores[..index].iter().any(|(earlier, _)| earlier == scored) {
+            return Err(invalid(format!("{} is scored more than once", scored)));
+        }
+        if !(0..=MAX_SCORE).contains(value) {
+            return Err(invalid(format!("scores go from 0 to {}", MAX_SCORE)));
+        }
+    }
+    let judge_id = voter_id(&active_competition, &cmd_user_id, ballot_key);
+
+    let songs = list_songs(active_competition.id, connection)?;
+    let judged = songs
+        .iter()
+        .find(|song| choice.matches(song))
+        .ok_or_else(|| invalid(format!("no song {} in the active competition", choice)))?;
+
+    connection.transaction(|| {
+        let scored_criteria: Vec<&String> = scores.iter().map(|(scored, _)| scored).collect();
+        delete(song_vote)
+            .filter(user_id.eq(&judge_id))
+            .filter(song_id.eq(judged.id))
+            .filter(criterion.eq_any(scored_criteria))
+            .execute(connection)?;
+
+        let votes = scores
+            .iter()
+            .map(|(scored, value)| {
+                let new_song_vote = SongVoteInsert {
+                    user_id: judge_id.clone(),
+                    song_id: judged.id,
+                    rank: 1,
+                    score: *value,
+                    runoff: false,
+                    category: None,
+                    panel: true,
+                    criterion: Some(scored.clone()),
+                    bracket_match_id: None,
+                };
+                save_song_vote(new_song_vote, ballot_key, connection)
+            })
+            .collect::<Result<Vec<SongVote>, BotError>>()?;
+
+        info!(
+            user_id = judge_id.as_str(),
+            competition_id = %active_competition.id,
+            song_id = %judged.id,
+            criteria = votes.len(),
+            "Saved rubric scores"
+        );
+
+        Ok((active_competition, votes))
+    })
+}
+
+/// The seeded songs and the matches of a bracket, in round and slot order
+#[derive(PartialEq, Debug)]
+pub struct Bracket {
+    pub competition: Competition,
+    pub songs: Vec<Song>,
+    pub matches: Vec<BracketMatch>,
+}
+
+/// Matches closed by advancing a bracket, and the matches of the next round
+#[derive(PartialEq, Debug)]
+pub struct BracketAdvance {
+    pub bracket: Bracket,
+    pub closed: Vec<BracketMatch>,
+    pub opened: Vec<BracketMatch>,
+}
+
+/// Start a bracket between the winners of the latest competitions. The most recent
+/// winner is the first seed, and a song's number in the bracket is its seed.
+/// The first round opens right away, top seeds get a bye when the number of songs
+/// is not a power of two.
+pub fn start_bracket(
+    mut competition_insert: CompetitionInsert,
+    seeds: usize,
+    connection: &PgConnection,
+) -> Result<Bracket, BotError> {
+    use crate::schema::sotw::{bracket_match, competition, song};
+
+    let invalid = |reason: String| BotError {
+        data_error: DataError::InvalidArgument(reason),
+        message: "Unable to start bracket".to_string(),
+    };
+    let match_minutes = competition_insert
+        .match_minutes
+        .filter(|minutes| *minutes > 0)
+        .ok_or_else(|| invalid("a bracket needs a match duration".to_string()))?;
+    if seeds < 2 {
+        return Err(invalid("a bracket needs at least 2 songs".to_string()));
+    }
+
+    connection.transaction(|| {
+        let winner_ids: Vec<Uuid> = competition::table
+            .filter(competition::kind.eq(CompetitionKind::Open))
+            .filter(competition::is_active.eq(false))
+            .filter(competition::winner_song_id.is_not_null())
+            .order(competition::ended.desc())
+            .limit(seeds as i64)
+            .select(competition::winner_song_id)
+            .load::<Option<Uuid>>(connection)?
+            .into_iter()
+            .flatten()
+            .collect();
+        if winner_ids.len() < seeds {
+            return Err(invalid(format!(
+                "only {} past winners to seed the bracket",
+                winner_ids.len()
+            )));
+        }
+        let winners = song::table
+            .filter(song::id.eq_any(&winner_ids))
+            .load::<Song>(connection)?;
+
+        competition_insert.kind = CompetitionKind::Bracket;
+        competition_insert.voting_method = VotingMethod::Plurality;
+        let deadline = competition_insert.started + chrono::Duration::minutes(match_minutes.into());
+        let started = save_competition(competition_insert, connection)?;
+
+        let seeded = winner_ids
+            .iter()
+            .enumerate()
+            .filter_map(|(index, winner_id)| {
+                let winner = winners.iter().find(|winner| winner.id == *winner_id)?;
+                Some(SongInsert {
+                    user_id: winner.user_id.clone(),
+                    song_uri: winner.song_uri.clone(),
+                    competition_id: started.id,
+                    number: index as i32 + 1,
+                })
+            })
+            .collect::<Vec<SongInsert>>();
+        let songs = insert_into(song::table)
+            .values(&seeded)
+            .get_results::<Song>(connection)?;
+        update(competition::table.find(started.id))
+            .set(competition::next_song_number.eq(songs.len() as i32 + 1))
+            .execute(connection)?;
+        let seed_id = |seed: usize| {
+            songs
+                .iter()
+                .find(|song| song.number == seed as i32)
+                .map(|song| song.id)
+        };
+
+        let first_matches = first_round(songs.len())
+            .into_iter()
+            .enumerate()
+            .filter_map(|(slot, (home, away))| {
+                let home_song_id = seed_id(home)?;
+                let away_song_id = away.and_then(seed_id);
+                // A bye is decided before the round opens
+                let bye = away_song_id.map_or(Some(home_song_id), |_| None);
+                Some(BracketMatchInsert {
+                    competition_id: started.id,
+                    round: 1,
+                    slot: slot as i32 + 1,
+                    home_song_id,
+                    away_song_id,
+                    deadline,
+                    closed: bye.map(|_| started.started),
+                    winner_song_id: bye,
+                })
+            })
+            .collect::<Vec<BracketMatchInsert>>();
+        insert_into(bracket_match::table)
+            .values(&first_matches)
+            .execute(connection)?;
+
+        info!(competition_id = %started.id, seeds = songs.len(), "Started bracket");
+
+        load_bracket(started, connection)
+    })
+}
+
+/// The active competition as a bracket
+pub fn find_active_bracket(connection: &PgConnection) -> Result<Bracket, BotError> {
+    match find_active_competition(connection)? {
+        Some(active) if active.kind == CompetitionKind::Bracket => load_bracket(active, connection),
+        Some(_) => Err(BotError {
+            data_error: DataError::InvalidArgument(
+                "the active competition is not a bracket".to_string(),
+            ),
+            message: "Unable to show bracket".to_string(),
+        }),
+        None => Err(BotError {
+            data_error: DataError::NoActiveCompetition,
+            message: "Unable to find active competition when trying to show bracket".to_string(),
+        }),
+    }
+}
+
+fn load_bracket(competition: Competition, connection: &PgConnection) -> Result<Bracket, BotError> {
+    use crate::schema::sotw::bracket_match::dsl::*;
+
+    let songs = list_songs(competition.id, connection)?;
+    let matches = bracket_match
+        .filter(competition_id.eq(competition.id))
+        .order((round, slot))
+        .load::<BracketMatch>(connection)?;
+
+    Ok(Bracket {
+        competition,
+        songs,
+        matches,
+    })
+}
+
+/// Close the matches of the active bracket which are past their deadline. Once every
+/// match of a round is decided the winners meet in the next round, and the winner of
+/// the final wins the competition. Nothing happens before a deadline has passed.
+pub fn advance_bracket(
+    now: DateTime<Utc>,
+    connection: &PgConnection,
+) -> Result<Option<BracketAdvance>, BotError> {
+    use crate::schema::sotw::{bracket_match, competition, song_vote};
+
+    connection.transaction(|| {
+        let active = match find_active_competition(connection)? {
+            Some(active) if active.kind == CompetitionKind::Bracket => active,
+            _ => return Ok(None),
+        };
+        // Locked, so a match is only closed once when several instances advance the bracket
+        let active = competition::table
+            .find(active.id)
+            .for_update()
+            .first::<Competition>(connection)?;
+        let bracket = load_bracket(active, connection)?;
+        let due: Vec<&BracketMatch> = bracket
+            .matches
+            .iter()
+            .filter(|due| due.is_open() && due.deadline <= now)
+            .collect();
+        if due.is_empty() {
+            return Ok(None);
+        }
+
+        let seed = |song_id: Uuid| {
+            bracket
+                .songs
+                .iter()
+                .find(|song| song.id == song_id)
+                .map_or(i32::MAX, |song| song.number)
+        };
+        let mut closed = Vec::new();
+        for due_match in due {
+            let votes = song_vote::table
+                .filter(song_vote::bracket_match_id.eq(due_match.id))
+                .select(song_vote::song_id)
+                .load::<Uuid>(connection)?;
+            let count = |song_id: Uuid| votes.iter().filter(|vote| **vote == song_id).count();
+            let home = due_match.home_song_id;
+            let away = due_match.away_song_id.unwrap_or(home);
+            let winner_seed = match_winner(
+                (seed(home), count(home) as u32),
+                (seed(away), count(away) as u32),
+            );
+            let winner = if seed(away) == winner_seed { away } else { home };
+
+            closed.push(
+                update(bracket_match::table.find(due_match.id))
+                    .set((
+                        bracket_match::closed.eq(Some(now)),
+                        bracket_match::winner_song_id.eq(Some(winner)),
+                        bracket_match::home_votes.eq(count(home) as i32),
+                        bracket_match::away_votes.eq(count(away) as i32),
+                    ))
+                    .get_result::<BracketMatch>(connection)?,
+            );
+        }
+
+        let last_round = bracket.matches.iter().map(|m| m.round).max().unwrap_or(1);
+        let round_matches = bracket_match::table
+            .filter(bracket_match::competition_id.eq(bracket.competition.id))
+            .filter(bracket_match::round.eq(last_round))
+            .order(bracket_match::slot)
+            .load::<BracketMatch>(connection)?;
+        let winners: Option<Vec<Uuid>> = round_matches
+            .iter()
+            .map(|decided| decided.winner_song_id)
+            .collect();
+
+        let mut opened = Vec::new();
+        match winners.as_deref() {
+            Some([champion]) => {
+                update(competition::table.find(bracket.competition.id))
+                    .set((
+                        competition::is_active.eq(false),
+                        competition::ended.eq(Some(now)),
+                        competition::winner_song_id.eq(Some(*champion)),
+                    ))
+                    .execute(connection)?;
+                info!(competition_id = %bracket.competition.id, winner_song_id = %champion, "Closed bracket");
+            }
+            Some(winners) => {
+                let minutes = bracket.competition.match_minutes.unwrap_or(0);
+                let deadline = now + chrono::Duration::minutes(minutes.into());
+                let next_matches = next_round(winners)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(slot, (home, away))| BracketMatchInsert {
+                        competition_id: bracket.competition.id,
+                        round: last_round + 1,
+                        slot: slot as i32 + 1,
+                        home_song_id: home,
+                        away_song_id: Some(away),
+                        deadline,
+                        closed: None,
+                        winner_song_id: None,
+                    })
+                    .collect::<Vec<BracketMatchInsert>>();
+                opened = insert_into(bracket_match::table)
+                    .values(&next_matches)
+                    .get_results::<BracketMatch>(connection)?;
+                info!(competition_id = %bracket.competition.id, round = last_round + 1, "Opened bracket round");
+            }
+            None => {}
+        }
+
+        let competition = competition::table
+            .find(bracket.competition.id)
+            .first::<Competition>(connection)?;
+
+        Ok(Some(BracketAdvance {
+            bracket: load_bracket(competition, connection)?,
+            closed,
+            opened,
+        }))
+    })
+}
+
+/// Votes for songs in the open matches of a bracket, a single vote per match.
+/// Voting again in a match replaces the earlier vote.
+fn save_match_votes(
+    bracket: &Competition,
+    choices: &[VoteChoice],
+    ballot_user_id: String,
+    ballot_key: &[u8],
+    connection: &PgConnection,
+) -> Result<Vec<SongVote>, BotError> {
+    use crate::schema::sotw::bracket_match;
+    use crate::schema::sotw::song_vote::dsl::*;
+
+    let invalid = |reason: String| BotError {
+        data_error: DataError::InvalidArgument(reason),
+        message: "Unable to save vote".to_string(),
+    };
+
+    let songs = list_songs(bracket.id, connection)?;
+    let open_matches = bracket_match::table
+        .filter(bracket_match::competition_id.eq(bracket.id))
+        .filter(bracket_match::closed.is_null())
+        .load::<BracketMatch>(connection)?;
+    let now = Utc::now();
+
+    let mut chosen: Vec<(Uuid, &BracketMatch)> = Vec::new();
+    for choice in choices {
+        if choice.value.is_some() {
+            return Err(invalid("bracket votes do not take values".to_string()));
+        }
+        let voted = songs
+            .iter()
+            .find(|song| choice.song.matches(song))
+            .ok_or_else(|| invalid(format!("no song {} in the bracket", choice.song)))?;
+        let voted_match = open_matches
+            .iter()
+            .find(|open| open.has_song(voted.id))
+            .ok_or_else(|| invalid(format!("#{} is not in an open match", voted.number)))?;
+        if voted_match.deadline < now {
+            return Err(invalid(format!(
+                "voting in the match of #{} has closed",
+                voted.number
+            )));
+        }
+        if chosen
+            .iter()
+            .any(|(_, earlier)| earlier.id == voted_match.id)
+        {
+            return Err(invalid(format!(
+                "vote for a single song in the match of #{}",
+                voted.number
+            )));
+        }
+        chosen.push((voted.id, voted_match));
+    }
+
+    connection.transaction(|| {
+        chosen
+            .iter()
+            .map(|(chosen_song_id, voted_match)| {
+                delete(song_vote)
+                    .filter(user_id.eq(&ballot_user_id))
+                    .filter(bracket_match_id.eq(voted_match.id))
+                    .execute(connection)?;
+                let new_song_vote = SongVoteInsert {
+                    user_id: ballot_user_id.clone(),
+                    song_id: *chosen_song_id,
+                    rank: 1,
+                    score: 1,
+                    runoff: false,
+                    category: None,
+                    panel: false,
+                    criterion: None,
+                    bracket_match_id: Some(voted_match.id),
+                };
+                save_song_vote(new_song_vote, ballot_key, connection)
+            })
+            .collect::<Result<Vec<SongVote>, BotError>>()
+    })
+}
+
+/// Check a ballot against the songs and voting method of the competition,
+/// giving the chosen songs in order with their scores
+fn resolve_ballot(
+    method: VotingMethod,
+    points_budget: i32,
+    songs: &[Song],
+    choices: &[VoteChoice],
+) -> Result<Vec<(Uuid, i32)>, BotError> {
+    let invalid = |reason: String| BotError {
+        data_error: DataError::InvalidArgument(reason),
+        message: "Unable to save vote".to_string(),
+    };
+
+    if choices.is_empty() {
+        return Err(invalid("no songs given".to_string()));
+    }
+    if method == VotingMethod::Plurality && choices.len() > 1 {
+        return Err(invalid(format!(
+            "{} voting takes a single song",
+            method.as_str()
+        )));
+    }
+
+    let mut ballot: Vec<(Uuid, i32)> = Vec::new();
+    for choice in choices {
+        let chosen = songs
+            .iter()
+            .find(|song| choice.song.matches(song))
+            .ok_or_else(|| invalid(format!("no song {} in the active competition", choice.song)))?;
+        if ballot.iter().any(|(chosen_id, _)| *chosen_id == chosen.id) {
+            return Err(invalid(format!(
+                "song {} is on the ballot more than once",
+                choice.song
+            )));
+        }
+
+        let score = match (method.is_scored(), choice.value) {
+            (true, Some(value)) => value,
+            (true, None) => {
+                return Err(invalid(format!(
+                    "{} voting needs a value for every song, for example {}=3",
+                    method.as_str(),
+                    choice.song
+                )))
+            }
+            (false, Some(_)) => {
+                return Err(invalid(format!(
+                    "{} voting does not take values",
+                    method.as_str()
+                )))
+            }
+            (false, None) => 1,
+        };
+        if method == VotingMethod::Score && !(0..=MAX_SCORE).contains(&score) {
+            return Err(invalid(format!("scores go from 0 to {}", MAX_SCORE)));
+        }
+        if method == VotingMethod::Points && score < 0 {
+            return Err(invalid("points can not be negative".to_string()));
+        }
+
+        ballot.push((chosen.id, score));
+    }
+
+    if method == VotingMethod::Points {
+        let spent: i32 = ballot.iter().map(|(_, points)| points).sum();
+        if spent > points_budget {
+            return Err(invalid(format!(
+                "{} points given, the budget is {}",
+                spent, points_budget
+            )));
+        }
+    }
+
+    Ok(ballot)
+}
+
+/// Count the votes cast in a competition with its voting method.
+/// Disqualified songs are left out, and when submitters must vote so are the
+/// songs of submitters without a ballot.
+pub fn tally_competition(
+    competition: &Competition,
+    songs: &[Song],
+    ballot_key: &[u8],
+    connection: &PgConnection,
+) -> Result<Tally, BotError> {
+    let competition_song_ids: Vec<Uuid> = songs.iter().map(|s| s.id).collect();
+    let ballots = load_ballots(&competition_song_ids, false, None, false, connection)?;
+
+    let counted_song_ids: Vec<Uuid> = songs
+        .iter()
+        .filter(|song| !song.disqualified)
+        .filter(|song| {
+            !competition.rules.submitters_must_vote
+                || ballots
+                    .iter()
+                    .any(|(voter, _)| *voter == voter_id(competition, &song.user_id, ballot_key))
+        })
+        .map(|song| song.id)
+        .collect();
+    let ballots: Vec<Ballot> = ballots.into_iter().map(|(_, ballot)| ballot).collect();
+
+    Ok(tally(
+        competition.voting_method,
+        &counted_song_ids,
+        &ballots,
+    ))
+}
+
+/// Combine the tally of the audience with the judges' votes, when the competition
+/// has a panel. Without a rubric the judges vote like the audience does, otherwise
+/// a song gets the sum of the scores the judges gave it.
+pub fn tally_panel(
+    competition: &Competition,
+    counted_song_ids: &[Uuid],
+    audience: &Tally,
+    connection: &PgConnection,
+) -> Result<Option<Combined>, BotError> {
+    if competition.judges.is_empty() {
+        return Ok(None);
+    }
+
+    let ballots: Vec<Ballot> = load_ballots(counted_song_ids, false, None, true, connection)?
+        .into_iter()
+        .map(|(_, ballot)| ballot)
+        .collect();
+    let panel_points = if competition.rubric.is_empty() {
+        points(
+            counted_song_ids,
+            &tally(competition.voting_method, counted_song_ids, &ballots),
+        )
+    } else {
+        counted_song_ids
+            .iter()
+            .map(|counted| {
+                let scores = ballots.iter().flat_map(|ballot| {
+                    ballot
+                        .ranking
+                        .iter()
+                        .zip(&ballot.scores)
+                        .filter(move |(scored, _)| *scored == counted)
+                        .map(|(_, score)| score)
+                });
+                (*counted, scores.sum())
+            })
+            .collect()
+    };
+
+    Ok(Some(combine(
+        counted_song_ids,
+        points(counted_song_ids, audience),
+        panel_points,
+        competition.panel_weight.max(0) as u32,
+    )))
+}
+
+// Songs which made it into the tally, in the order they were submitted
+fn counted_song_ids(songs: &[Song], counted: &Tally) -> Vec<Uuid> {
+    songs
+        .iter()
+        .map(|song| song.id)
+        .filter(|song_id| counted.ranking.iter().flatten().any(|id| id == song_id))
+        .collect()
+}
+
+/// Count the runoff votes between tied songs
+fn tally_runoff(tied: &[Uuid], connection: &PgConnection) -> Result<Tally, BotError> {
+    let ballots: Vec<Ballot> = load_ballots(tied, true, None, false, connection)?
+        .into_iter()
+        .map(|(_, ballot)| ballot)
+        .collect();
+
+    Ok(tally(VotingMethod::Plurality, tied, &ballots))
+}
+
+/// Id the votes of a user are stored under. In a secret ballot this is a hash of
+/// the user, keyed with the ballot key and different in every competition.
+/// A user keeps a single ballot, but stored votes can't be traced back to them.
+pub fn voter_id(competition: &Competition, user: &str, ballot_key: &[u8]) -> String {
+    if !competition.rules.secret_ballot {
+        return user.to_string();
+    }
+
+    let key = hmac::Key::new(hmac::HMAC_SHA256, ballot_key);
+    let mut context = hmac::Context::with_key(&key);
+    context.update(competition.id.as_bytes());
+    context.update(user.as_bytes());
+
+    hex::encode(context.sign().as_ref())
+}
+
+/// Count the votes in an award category, a single vote per user
+fn tally_category(
+    award: &str,
+    counted_song_ids: &[Uuid],
+    connection: &PgConnection,
+) -> Result<Tally, BotError> {
+    let ballots: Vec<Ballot> =
+        load_ballots(counted_song_ids, false, Some(award), false, connection)?
+            .into_iter()
+            .map(|(_, ballot)| ballot)
+            .collect();
+
+    Ok(tally(VotingMethod::Plurality, counted_song_ids, &ballots))
+}
+
+// Ballots for the given songs, along with the voter id they were cast by.
+// Without an award category these are the ballots for the overall winner,
+// cast by the audience or by the panel of judges.
+fn load_ballots(
+    ballot_song_ids: &[Uuid],
+    runoff_votes: bool,
+    award: Option<&str>,
+    panel_votes: bool,
+    connection: &PgConnection,
+) -> Result<Vec<(String, Ballot)>, BotError> {
+    use crate::schema::sotw::song_vote::dsl::*;
+
+    let query = song_vote
+        .filter(song_id.eq_any(ballot_song_ids))
+        .filter(runoff.eq(runoff_votes))
+        .filter(panel.eq(panel_votes))
+        .order((user_id, rank))
+        .into_boxed();
+    let votes = match award {
+        Some(award) => query.filter(category.eq(award)),
+        None => query.filter(category.is_null()),
+    }
+    .load::<SongVote>(connection)?;
+
+    let mut ballots: Vec<(String, Ballot)> = Vec::new();
+    for vote in votes {
+        let vote_score = vote.score.max(0) as u32;
+        match ballots.last_mut() {
+            Some((voter, ballot)) if *voter == vote.user_id => {
+                ballot.ranking.push(vote.song_id);
+                ballot.scores.push(vote_score);
+            }
+            _ => ballots.push((
+                vote.user_id,
+                Ballot {
+                    ranking: vec![vote.song_id],
+                    scores: vec![vote_score],
+                },
+            )),
+        }
+    }
+
+    Ok(ballots)
+}
+
+#[derive(PartialEq, Debug)]
+pub enum RequestClaim {
+    Claimed,
+    InProgress,
+    Completed(SlackRequest),
+}
+
+/// Claim a Slack request before handling it.
+/// Only the first claim for a key gets to run, later ones see the
+/// stored outcome, or that the first is still in progress.
+pub fn claim_request(key: String, connection: &PgConnection) -> Result<RequestClaim, BotError> {
+    use crate::schema::sotw::slack_request::dsl::*;
+
+    let now = chrono::Utc::now();
+
+    delete(slack_request)
+        .filter(created.lt(now - chrono::Duration::hours(REQUEST_RETENTION_HOURS)))
+        .execute(connection)?;
+
+    let inserted = insert_into(slack_request)
+        .values(&SlackRequestInsert {
+            request_key: key.clone(),
+        })
+        .on_conflict_do_nothing()
+        .execute(connection)?;
+
+    if inserted == 1 {
+        return Ok(RequestClaim::Claimed);
+    }
+
+    let taken_over = update(
+        slack_request
+            .filter(request_key.eq(&key))
+            .filter(completed.is_null())
+            .filter(created.lt(now - chrono::Duration::seconds(REQUEST_ABANDONED_SECS))),
+    )
+    .set(created.eq(now))
+    .execute(connection)?;
+
+    if taken_over == 1 {
+        warn!("Taking over abandoned request");
+        return Ok(RequestClaim::Claimed);
+    }
+
+    let existing = slack_request.find(&key).first::<SlackRequest>(connection)?;
+
+    match existing.completed {
+        Some(_) => Ok(RequestClaim::Completed(existing)),
+        None => Ok(RequestClaim::InProgress),
+    }
+}
+
+/// Store the outcome of a claimed request
+pub fn complete_request(
+    key: String,
+    outcome_status_code: i32,
+    outcome_content_type: Option<String>,
+    outcome_body: String,
+    connection: &PgConnection,
+) -> Result<(), BotError> {
+    use crate::schema::sotw::slack_request::dsl::*;
+
+    update(slack_request.find(key))
+        .set((
+            status_code.eq(outcome_status_code),
+            content_type.eq(outcome_content_type),
+            response_body.eq(outcome_body),
+            completed.eq(chrono::Utc::now()),
+        ))
+        .execute(connection)?;
+
+    Ok(())
+}
+
+/// Drop a claim so a retry runs the request again
+pub fn release_request(key: String, connection: &PgConnection) -> Result<(), BotError> {
+    use crate::schema::sotw::slack_request::dsl::*;
+
+    delete(slack_request.find(key)).execute(connection)?;
+
+    Ok(())
+}
+
+/// Remember the signature of an accepted request until its timestamp falls
+/// out of the window, returns false when it was already used.
+pub fn record_signature(
+    request_signature: String,
+    timestamp: i64,
+    connection: &PgConnection,
+) -> Result<bool, BotError> {
+    use crate::schema::sotw::slack_signature::dsl::*;
+
+    delete(slack_signature)
+        .filter(request_timestamp.lt(Utc::now().timestamp() - SIGNATURE_WINDOW_SECS))
+        .execute(connection)?;
+
+    let inserted = insert_into(slack_signature)
+        .values(&SlackSignatureInsert {
+            signature: request_signature,
+            request_timestamp: timestamp,
+        })
+        .on_conflict_do_nothing()
+        .execute(connection)?;
+
+    Ok(inserted == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sotw_db::database::{
+        advance_bracket, ban_user, claim_request, close_competition, complete_request,
+        disqualify_song, edit_description, extend_deadline, find_active_competition,
+        find_past_competition, find_season_standings, grant_role, handover_competition,
+        join_roster, leave_roster, list_badges, list_bans, list_past_competitions, list_roles,
+        list_songs, list_songs_active_competition, record_signature, release_request, remove_song,
+        reopen_competition, revoke_role, save_ballot, save_category_vote, save_competition,
+        save_rubric_scores, save_song, save_song_vote, skip_host, start_bracket, start_season,
+        stop_competition, tally_competition, unban_user, user_stats, voter_id, Closing, Extension,
+        HistoryPage, PastCompetition, RequestClaim,
+    };
+    use crate::sotw_db::errors::{BotError, DataError};
+    use crate::sotw_db::model::{
+        Competition, CompetitionInsert, CompetitionKind, CompetitionRef, Role, SeasonInsert,
+        SongRef, SongVoteInsert, TieBreak, VoteChoice, VotingMethod, MAX_SCORE,
+    };
+    use crate::sotw_db::permissions::Actor;
+    use diesel::{Connection, PgConnection};
+
+    static BALLOT_KEY: &[u8] = b"test ballot key";
+
+    fn random_user_id() -> String {
+        uuid::Uuid::new_v4().to_string()
+    }
+
+    fn test_db_connection() -> PgConnection {
+        dotenv::dotenv().ok();
+
+        let connection_string =
+            std::env::var("DATABASE_URL").expect("Database connection string missing!");
+        PgConnection::establish(&connection_string).unwrap()
+    }
+
+    fn create_competition_insert(user_id: String, is_active: bool) -> CompetitionInsert {
+        CompetitionInsert {
+            ended: if !is_active {
+                Some(chrono::Utc::now())
+            } else {
+                None
+            },
+            is_active,
+            ..CompetitionInsert::for_test(&user_id)
+        }
+    }
+
+    fn choices(input: &str) -> Vec<VoteChoice> {
+        input
+            .split_whitespace()
+            .map(|choice| choice.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_save() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, DataError, _>(|| {
+            let competition = create_competition_insert(random_user_id(), true);
+
+            let result = save_competition(competition, connection);
+
+            assert!(
+                !result.is_err(),
+                "should not be an error when doing a clean save"
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_save_exists() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let user_id_owner = random_user_id();
+
+            let result_owner_ok = save_competition(
+                create_competition_insert(user_id_owner.clone(), true),
+                connection,
+            );
+            let result_owner_err =
+                save_competition(create_competition_insert(user_id_owner, true), connection);
+
+            assert!(
+                &result_owner_ok.is_ok(),
+                "owner should be able to insert the first competition fine"
+            );
+
+            assert_eq!(
+                result_owner_err.err().unwrap().data_error,
+                BotError {
+                    data_error: DataError::ActiveCompetitionExists(result_owner_ok.unwrap().id),
+                    message: "".to_string(),
+                }
+                .data_error,
+                "should not be able to save while active competition exists"
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_close() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let user_id_owner = random_user_id();
+            let user_id_other = random_user_id();
+
+            let result_owner = save_competition(
+                create_competition_insert(user_id_owner.clone(), true),
+                connection,
+            );
+
+            let result_close_other = close_competition(&Actor::user(user_id_other), connection);
+            let result_close_owner = close_competition(&Actor::user(user_id_owner), connection);
+
+            assert!(
+                result_close_owner.is_ok(),
+                "owner should be able to close competition"
+            );
+
+            assert!(
+                result_close_owner.unwrap().ended.is_some(),
+                "should contain an ended date when closed"
+            );
+
+            assert_eq!(
+                result_close_other.err().unwrap().data_error,
+                BotError {
+                    data_error: DataError::UserDoesNotOwnEntity(result_owner.unwrap().id),
+                    message: "".to_string()
+                }
+                .data_error,
+                "should not be able to close competition not owned"
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_find_active() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let insert_competition = create_competition_insert(random_user_id(), true);
+            let inserted_competition = save_competition(insert_competition, connection)?;
+            let active_competition = find_active_competition(connection)?;
+
+            assert_eq!(
+                inserted_competition,
+                active_competition.unwrap(),
+                "the correct competition must match the one active"
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_not_found() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let result: Option<Competition> = find_active_competition(connection)?;
+
+            assert_eq!(result, None, "should return None since there is no data");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_save_song() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let active_competition = save_competition(
+                create_competition_insert(random_user_id(), false),
+                connection,
+            )?;
+
+            let inserted_song = save_song("".to_string(), "cmd".to_string(), connection)?;
+            assert_eq!(
+                inserted_song.competition_id, active_competition.id,
+                "inserted song needs to match the id of the active competition"
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_save_song_exists() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            save_competition(
+                create_competition_insert(random_user_id(), false),
+                connection,
+            )?;
+
+            let inserted_song_first =
+                save_song("song_1_uri".to_string(), "user".to_string(), connection)?;
+            let inserted_song_second =
+                save_song("song_2_uri".to_string(), "user".to_string(), connection)?;
+            let inserted_song_other_user = save_song(
+                "song_other_uri".to_string(),
+                "user_other".to_string(),
+                connection,
+            )?;
+
+            let (_, songs) = list_songs_active_competition(connection)?;
+
+            assert!(
+                songs.contains(&inserted_song_other_user),
+                "inserting a new song for one user should not impact other users"
+            );
+            assert!(
+                songs.contains(&inserted_song_second),
+                "second inserted song should be present"
+            );
+            assert!(
+                !songs.contains(&inserted_song_first),
+                "first inserted song should be removed by second inserted song"
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_song_numbers_not_reused() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            save_competition(
+                create_competition_insert(random_user_id(), false),
+                connection,
+            )?;
+            let submitter = random_user_id();
+
+            save_song("song_uri".to_string(), random_user_id(), connection)?;
+            let first = save_song("song_uri".to_string(), submitter.clone(), connection)?;
+            let replaced = save_song("song_uri".to_string(), submitter, connection)?;
+
+            assert_eq!(first.number, 2);
+            assert_eq!(
+                replaced.number, 3,
+                "the replaced song's number is not handed out again"
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_list_songs_active_competition() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            save_competition(
+                create_competition_insert(random_user_id(), true),
+                connection,
+            )?;
+
+            for _ in 1..=10 {
+                save_song(
+                    "http://example.org/song123".to_string(),
+                    random_user_id(),
+                    connection,
+                )?;
+            }
+
+            let (_, active_songs) = list_songs_active_competition(connection)?;
+
+            assert_eq!(active_songs.len(), 10, "all songs should be present");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_vote_song() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let active_competition = save_competition(
+                create_competition_insert(random_user_id(), false),
+                connection,
+            )?;
+            let inserted_song = save_song(
+                "http://example.org/song123".to_string(),
+                random_user_id(),
+                connection,
+            )?;
+            let new_song_vote = SongVoteInsert {
+                user_id: "example|123".to_string(),
+                song_id: inserted_song.id,
+                rank: 1,
+                score: 1,
+                runoff: false,
+                category: None,
+                panel: false,
+                criterion: None,
+                bracket_match_id: None,
+            };
+            let voted_song = save_song_vote(new_song_vote, BALLOT_KEY, connection)?;
+
+            assert_eq!(
+                inserted_song.competition_id, active_competition.id,
+                "song must match competition"
+            );
+            assert_eq!(
+                voted_song.song_id, inserted_song.id,
+                "voted song must match song"
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_save_ballot() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            save_competition(
+                create_competition_insert(random_user_id(), false),
+                connection,
+            )?;
+            let first = save_song("song_1_uri".to_string(), random_user_id(), connection)?;
+            let second = save_song("song_2_uri".to_string(), random_user_id(), connection)?;
+            let voter = random_user_id();
+
+            let ranked = save_ballot(choices("1 2"), voter.clone(), BALLOT_KEY, connection);
+            let replaced = save_ballot(
+                choices(&second.id.to_string()),
+                voter,
+                BALLOT_KEY,
+                connection,
+            )?;
+            let missing = save_ballot(choices("3"), random_user_id(), BALLOT_KEY, connection);
+
+            assert_eq!((first.number, second.number), (1, 2), "songs are numbered");
+            assert!(
+                matches!(
+                    ranked.err().unwrap().data_error,
+                    DataError::InvalidArgument(_)
+                ),
+                "plurality should only take a single song"
+            );
+            assert_eq!(replaced.1.len(), 1);
+            assert_eq!(replaced.1[0].song_id, second.id);
+            assert!(missing.is_err(), "song must be in the competition");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_save_scored_ballot() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let mut competition_insert = create_competition_insert(random_user_id(), false);
+            competition_insert.voting_method = VotingMethod::Points;
+            competition_insert.points_budget = 6;
+            let competition = save_competition(competition_insert, connection)?;
+            for _ in 1..=2 {
+                save_song("song_uri".to_string(), random_user_id(), connection)?;
+            }
+
+            let within_budget =
+                save_ballot(choices("1=4 2=2"), random_user_id(), BALLOT_KEY, connection);
+            let over_budget =
+                save_ballot(choices("1=4 2=3"), random_user_id(), BALLOT_KEY, connection);
+            let missing_points =
+                save_ballot(choices("1 2=2"), random_user_id(), BALLOT_KEY, connection);
+            save_ballot(choices("2=6"), random_user_id(), BALLOT_KEY, connection)?;
+
+            let songs = list_songs(competition.id, connection)?;
+            let tally = tally_competition(&competition, &songs, BALLOT_KEY, connection)?;
+
+            assert!(within_budget.is_ok(), "the whole budget can be spent");
+            assert!(over_budget.is_err(), "points over the budget are rejected");
+            assert!(missing_points.is_err(), "every song needs its points");
+            assert_eq!(
+                tally.rounds[0].counts,
+                vec![(songs[1].id, 8), (songs[0].id, 4)]
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_tally_ranked_ballots() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let mut competition_insert = create_competition_insert(random_user_id(), false);
+            competition_insert.voting_method = VotingMethod::InstantRunoff;
+            let competition = save_competition(competition_insert, connection)?;
+            for _ in 1..=3 {
+                save_song("song_uri".to_string(), random_user_id(), connection)?;
+            }
+
+            let voter = random_user_id();
+            save_ballot(choices("1"), voter.clone(), BALLOT_KEY, connection)?;
+            save_ballot(choices("3 2"), voter, BALLOT_KEY, connection)?;
+            save_ballot(choices("2"), random_user_id(), BALLOT_KEY, connection)?;
+            save_ballot(choices("1"), random_user_id(), BALLOT_KEY, connection)?;
+            save_ballot(choices("1"), random_user_id(), BALLOT_KEY, connection)?;
+            save_ballot(choices("2"), random_user_id(), BALLOT_KEY, connection)?;
+
+            let songs = list_songs(competition.id, connection)?;
+            let tally = tally_competition(&competition, &songs, BALLOT_KEY, connection)?;
+
+            assert_eq!(
+                tally.rounds[0].eliminated,
+                vec![songs[2].id],
+                "only the latest ballot of a user counts"
+            );
+            assert_eq!(tally.winners, vec![songs[1].id]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_stop_earliest_tie_break() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let host = random_user_id();
+            save_competition(create_competition_insert(host.clone(), false), connection)?;
+            for _ in 1..=3 {
+                save_song("song_uri".to_string(), random_user_id(), connection)?;
+            }
+            save_ballot(choices("3"), random_user_id(), BALLOT_KEY, connection)?;
+            save_ballot(choices("2"), random_user_id(), BALLOT_KEY, connection)?;
+
+            let results = match stop_competition(Actor::user(host), None, BALLOT_KEY, connection)? {
+                Closing::Closed(results) => results,
+                closing => panic!("expected the competition to close, got {:?}", closing),
+            };
+
+            assert_eq!(
+                results.competition.winner_song_id,
+                Some(results.songs[1].id)
+            );
+            assert_eq!(
+                results.competition.tie_break_used,
+                Some("earliest submission".to_string())
+            );
+            assert!(!results.competition.is_active);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_stop_with_runoff() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let host = random_user_id();
+            let mut competition_insert = create_competition_insert(host.clone(), false);
+            competition_insert.tie_break = TieBreak::Runoff;
+            save_competition(competition_insert, connection)?;
+            for _ in 1..=3 {
+                save_song("song_uri".to_string(), random_user_id(), connection)?;
+            }
+            save_ballot(choices("1"), random_user_id(), BALLOT_KEY, connection)?;
+            save_ballot(choices("3"), random_user_id(), BALLOT_KEY, connection)?;
+
+            let tied =
+                match stop_competition(Actor::user(host.clone()), None, BALLOT_KEY, connection)? {
+                    Closing::Runoff(competition, tied) => {
+                        assert!(competition.runoff && competition.is_active);
+                        tied
+                    }
+                    closing => panic!("expected a runoff, got {:?}", closing),
+                };
+            let late_song = save_song("song_uri".to_string(), random_user_id(), connection);
+            let not_tied = save_ballot(choices("2"), random_user_id(), BALLOT_KEY, connection);
+            save_ballot(choices("3"), random_user_id(), BALLOT_KEY, connection)?;
+
+            let results = match stop_competition(Actor::user(host), None, BALLOT_KEY, connection)? {
+                Closing::Closed(results) => results,
+                closing => panic!("expected the competition to close, got {:?}", closing),
+            };
+
+            assert_eq!(
+                tied.iter().map(|song| song.number).collect::<Vec<i32>>(),
+                vec![1, 3]
+            );
+            assert!(late_song.is_err(), "no songs are added during a runoff");
+            assert!(not_tied.is_err(), "runoff votes are for tied songs only");
+            assert_eq!(results.competition.winner_song_id, Some(tied[1].id));
+            assert_eq!(
+                results.tally.winners.len(),
+                2,
+                "runoff votes are kept apart from the ballots"
+            );
+            assert_eq!(
+                results.runoff.map(|runoff| runoff.winners),
+                Some(vec![tied[1].id])
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_stop_with_casting_vote() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let host = random_user_id();
+            let mut competition_insert = create_competition_insert(host.clone(), false);
+            competition_insert.tie_break = TieBreak::CastingVote;
+            save_competition(competition_insert, connection)?;
+            for _ in 1..=2 {
+                save_song("song_uri".to_string(), random_user_id(), connection)?;
+            }
+            save_ballot(choices("1"), random_user_id(), BALLOT_KEY, connection)?;
+            save_ballot(choices("2"), random_user_id(), BALLOT_KEY, connection)?;
+
+            let without_vote =
+                stop_competition(Actor::user(host.clone()), None, BALLOT_KEY, connection);
+            let results = match stop_competition(
+                Actor::user(host),
+                Some(SongRef::Number(2)),
+                BALLOT_KEY,
+                connection,
+            )? {
+                Closing::Closed(results) => results,
+                closing => panic!("expected the competition to close, got {:?}", closing),
+            };
+
+            match without_vote {
+                Err(BotError {
+                    data_error: DataError::InvalidArgument(reason),
+                    ..
+                }) => assert!(reason.starts_with("tied between #1, #2")),
+                result => panic!(
+                    "expected the casting vote to be asked for, got {:?}",
+                    result
+                ),
+            }
+            assert_eq!(
+                results.competition.winner_song_id,
+                Some(results.songs[1].id)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_self_vote_rule() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            save_competition(
+                create_competition_insert(random_user_id(), false),
+                connection,
+            )?;
+            let submitter = random_user_id();
+            save_song("song_uri".to_string(), submitter.clone(), connection)?;
+            save_song("song_uri".to_string(), random_user_id(), connection)?;
+
+            let own_song = save_ballot(choices("1"), submitter.clone(), BALLOT_KEY, connection);
+            let ranked_own_song =
+                save_ballot(choices("2 1"), submitter.clone(), BALLOT_KEY, connection);
+            let (_, votes) = save_ballot(choices("2"), submitter, BALLOT_KEY, connection)?;
+
+            match own_song {
+                Err(BotError {
+                    data_error: DataError::InvalidArgument(reason),
+                    ..
+                }) => assert_eq!(reason, "voting for your own song #1 is not allowed"),
+                result => panic!("expected the vote to be rejected, got {:?}", result),
+            }
+            assert!(ranked_own_song.is_err());
+            assert_eq!(votes.len(), 1, "other songs may still be voted for");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_songs_per_user_rule() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let mut competition_insert = create_competition_insert(random_user_id(), false);
+            competition_insert.rules.songs_per_user = 2;
+            let competition = save_competition(competition_insert, connection)?;
+            let submitter = random_user_id();
+
+            save_song("first".to_string(), submitter.clone(), connection)?;
+            save_song("second".to_string(), submitter.clone(), connection)?;
+            let third = save_song("third".to_string(), submitter, connection);
+
+            assert!(third.is_err(), "only two songs are allowed per user");
+            assert_eq!(list_songs(competition.id, connection)?.len(), 2);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_late_submissions_rule() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let mut competition_insert = create_competition_insert(random_user_id(), false);
+            competition_insert.rules.late_submissions = false;
+            competition_insert.submission_deadline =
+                Some(chrono::Utc::now() - chrono::Duration::hours(1));
+            save_competition(competition_insert, connection)?;
+
+            let late_song = save_song("song_uri".to_string(), random_user_id(), connection);
+
+            match late_song {
+                Err(BotError {
+                    data_error: DataError::InvalidArgument(reason),
+                    ..
+                }) => assert!(reason.starts_with("submissions closed")),
+                result => panic!("expected the song to be rejected, got {:?}", result),
+            }
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_submitters_must_vote_rule() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let mut competition_insert = create_competition_insert(random_user_id(), false);
+            competition_insert.rules.submitters_must_vote = true;
+            let competition = save_competition(competition_insert, connection)?;
+            let (voting_submitter, idle_submitter) = (random_user_id(), random_user_id());
+            save_song("song_uri".to_string(), voting_submitter.clone(), connection)?;
+            save_song("song_uri".to_string(), idle_submitter, connection)?;
+
+            save_ballot(choices("2"), voting_submitter, BALLOT_KEY, connection)?;
+            save_ballot(choices("2"), random_user_id(), BALLOT_KEY, connection)?;
+            save_ballot(choices("1"), random_user_id(), BALLOT_KEY, connection)?;
+
+            let songs = list_songs(competition.id, connection)?;
+            let tally = tally_competition(&competition, &songs, BALLOT_KEY, connection)?;
+
+            assert_eq!(
+                tally.winners,
+                vec![songs[0].id],
+                "votes for the song of a submitter who did not vote are not counted"
+            );
+            assert_eq!(tally.ranking, vec![vec![songs[0].id]]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_secret_ballot() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let mut competition_insert = create_competition_insert(random_user_id(), false);
+            competition_insert.rules.secret_ballot = true;
+            competition_insert.rules.submitters_must_vote = true;
+            let competition = save_competition(competition_insert, connection)?;
+            let (submitter, voter) = (random_user_id(), random_user_id());
+            save_song("song_uri".to_string(), submitter.clone(), connection)?;
+            save_song("song_uri".to_string(), voter.clone(), connection)?;
+
+            save_ballot(choices("1"), voter.clone(), BALLOT_KEY, connection)?;
+            let (_, votes) = save_ballot(choices("1"), voter.clone(), BALLOT_KEY, connection)?;
+            let own_song = save_ballot(choices("1"), submitter.clone(), BALLOT_KEY, connection);
+            save_ballot(choices("2"), submitter, BALLOT_KEY, connection)?;
+            let without_key = save_ballot(choices("2"), random_user_id(), b"", connection);
+
+            let songs = list_songs(competition.id, connection)?;
+            let tally = tally_competition(&competition, &songs, BALLOT_KEY, connection)?;
+
+            assert_ne!(votes[0].user_id, voter, "the voter is not stored");
+            assert_eq!(votes[0].user_id, voter_id(&competition, &voter, BALLOT_KEY));
+            assert_ne!(
+                voter_id(&competition, &voter, BALLOT_KEY),
+                voter_id(&competition, &voter, b"other key"),
+                "the hash depends on the key"
+            );
+            assert!(own_song.is_err(), "rules still apply to secret ballots");
+            assert!(without_key.is_err());
+            assert_eq!(
+                tally.rounds[0].counts,
+                vec![(songs[0].id, 1), (songs[1].id, 1)],
+                "a voter keeps a single ballot, and submitters are known to have voted"
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_category_votes() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let host = random_user_id();
+            let mut competition_insert = create_competition_insert(host.clone(), false);
+            competition_insert.categories = vec!["lyrics".to_string(), "banger".to_string()];
+            save_competition(competition_insert, connection)?;
+            for _ in 1..=3 {
+                save_song("song_uri".to_string(), random_user_id(), connection)?;
+            }
+            let choice = |song: &str| song.parse::<VoteChoice>().unwrap();
+
+            let voter = random_user_id();
+            save_ballot(choices("1"), voter.clone(), BALLOT_KEY, connection)?;
+            save_category_vote(
+                "lyrics".to_string(),
+                choice("2"),
+                voter.clone(),
+                BALLOT_KEY,
+                connection,
+            )?;
+            save_category_vote(
+                "lyrics".to_string(),
+                choice("3"),
+                voter.clone(),
+                BALLOT_KEY,
+                connection,
+            )?;
+            save_category_vote(
+                "banger".to_string(),
+                choice("1"),
+                voter.clone(),
+                BALLOT_KEY,
+                connection,
+            )?;
+            save_category_vote(
+                "lyrics".to_string(),
+                choice("3"),
+                random_user_id(),
+                BALLOT_KEY,
+                connection,
+            )?;
+            let unknown = save_category_vote(
+                "vibes".to_string(),
+                choice("1"),
+                voter.clone(),
+                BALLOT_KEY,
+                connection,
+            );
+            save_ballot(choices("2"), voter, BALLOT_KEY, connection)?;
+
+            let results = match stop_competition(Actor::user(host), None, BALLOT_KEY, connection)? {
+                Closing::Closed(results) => results,
+                closing => panic!("expected the competition to close, got {:?}", closing),
+            };
+            let songs = &results.songs;
+
+            assert!(unknown.is_err(), "only the categories of the competition");
+            assert_eq!(
+                results.tally.winners,
+                vec![songs[1].id],
+                "category votes are kept apart from the ballot"
+            );
+            assert_eq!(results.categories[0].0, "lyrics");
+            assert_eq!(
+                results.categories[0].1.rounds[0].counts[0],
+                (songs[2].id, 2),
+                "a user has a single vote in each category"
+            );
+            assert_eq!(results.categories[1].1.winners, vec![songs[0].id]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_judges_panel() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let host = random_user_id();
+            let judges = vec![random_user_id(), random_user_id()];
+            let mut competition_insert = create_competition_insert(host.clone(), false);
+            competition_insert.judges = judges.clone();
+            save_competition(competition_insert, connection)?;
+            for _ in 1..=3 {
+                save_song("song_uri".to_string(), random_user_id(), connection)?;
+            }
+
+            for _ in 1..=3 {
+                save_ballot(choices("1"), random_user_id(), BALLOT_KEY, connection)?;
+            }
+            save_ballot(choices("2"), random_user_id(), BALLOT_KEY, connection)?;
+            save_ballot(choices("3"), judges[0].clone(), BALLOT_KEY, connection)?;
+            let (_, judged) = save_ballot(choices("2"), judges[0].clone(), BALLOT_KEY, connection)?;
+            save_ballot(choices("2"), judges[1].clone(), BALLOT_KEY, connection)?;
+
+            let results = match stop_competition(Actor::user(host), None, BALLOT_KEY, connection)? {
+                Closing::Closed(results) => results,
+                closing => panic!("expected the competition to close, got {:?}", closing),
+            };
+            let songs = &results.songs;
+            let combined = results.panel.unwrap();
+
+            assert!(judged[0].panel, "judges vote for the panel");
+            assert_eq!(
+                results.tally.winners,
+                vec![songs[0].id],
+                "judges are not part of the audience"
+            );
+            assert_eq!(
+                combined.panel,
+                vec![(songs[0].id, 0), (songs[1].id, 2), (songs[2].id, 0)],
+                "a judge keeps a single ballot"
+            );
+            assert_eq!(combined.shares(&songs[1].id), (25.0, 100.0, 62.5));
+            assert_eq!(results.competition.winner_song_id, Some(songs[1].id));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_rubric_scores() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let host = random_user_id();
+            let judge = random_user_id();
+            let mut competition_insert = create_competition_insert(host.clone(), false);
+            competition_insert.judges = vec![judge.clone()];
+            competition_insert.rubric = vec!["lyrics".to_string(), "production".to_string()];
+            save_competition(competition_insert, connection)?;
+            for _ in 1..=3 {
+                save_song("song_uri".to_string(), random_user_id(), connection)?;
+            }
+            let scores = |input: &[(&str, i32)]| -> Vec<(String, i32)> {
+                input
+                    .iter()
+                    .map(|(criterion, value)| (criterion.to_string(), *value))
+                    .collect()
+            };
+
+            save_ballot(choices("1"), random_user_id(), BALLOT_KEY, connection)?;
+            let judge_ballot = save_ballot(choices("2"), judge.clone(), BALLOT_KEY, connection);
+            let not_judge = save_rubric_scores(
+                SongRef::Number(1),
+                scores(&[("lyrics", 3)]),
+                random_user_id(),
+                BALLOT_KEY,
+                connection,
+            );
+            let unknown = save_rubric_scores(
+                SongRef::Number(1),
+                scores(&[("vibes", 3)]),
+                judge.clone(),
+                BALLOT_KEY,
+                connection,
+            );
+            let too_high = save_rubric_scores(
+                SongRef::Number(1),
+                scores(&[("lyrics", MAX_SCORE + 1)]),
+                judge.clone(),
+                BALLOT_KEY,
+                connection,
+            );
+            for (judged, given) in &[
+                (1, scores(&[("lyrics", 2), ("production", 1)])),
+                (1, scores(&[("lyrics", 5)])),
+                (2, scores(&[("lyrics", 4), ("production", 4)])),
+            ] {
+                save_rubric_scores(
+                    SongRef::Number(*judged),
+                    given.clone(),
+                    judge.clone(),
+                    BALLOT_KEY,
+                    connection,
+                )?;
+            }
+
+            let results = match stop_competition(Actor::user(host), None, BALLOT_KEY, connection)? {
+                Closing::Closed(results) => results,
+                closing => panic!("expected the competition to close, got {:?}", closing),
+            };
+            let songs = &results.songs;
+            let combined = results.panel.unwrap();
+
+            assert!(judge_ballot.is_err(), "judges score on the rubric");
+            assert!(not_judge.is_err(), "only judges score songs");
+            assert!(unknown.is_err(), "only the criteria of the rubric");
+            assert!(too_high.is_err());
+            assert_eq!(
+                combined.panel,
+                vec![(songs[0].id, 6), (songs[1].id, 8), (songs[2].id, 0)],
+                "scores are summed, a criterion scored again is replaced"
+            );
+            assert_eq!(combined.winners, vec![songs[0].id]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_bracket() {
+        let connection = &test_db_connection();
+
+        connection.test_transaction::<_, BotError, _>(|| {
+            let host = random_user_id();
+            for n in 1..=5 {
+                save_competition(create_competition_insert(host.clone(), false), connection)?;
+                save_song(format!("winner_{}", n), random_user_id(), connection)?;
+                save_ballot(choices("1"), random_user_id(), BALLOT_KEY, connection)?;
+                stop_competition(Actor::user(host.clone()), None, BALLOT_KEY, connection)?;
+            }
+            let mut bracket_insert = create_competition_insert(host.clone(), false);
+            bracket_insert.match_minutes = Some(60);
+            let started = bracket_insert.started;
+
+            let too_many = start_bracket(
+                CompetitionInsert {
+                    description: "too many".to_string(),
+                    ..create_competition_insert(host.clone(), false)
+                },
+                100,
+                connection,
+            );
+            let bracket = start_bracket(bracket_insert, 5, connection)?;
+            let seed = |number: i32| bracket.songs[number as usize - 1].id;
+            let bye_vote = save_ballot(choices("1"), random_user_id(), BALLOT_KEY, connection);
+            let song = save_song("song_uri".to_string(), random_user_id(), connection);
+            save_ballot(choices("5"), random_user_id(), BALLOT_KEY, connection)?;
+            let voter = random_user_id();
+            save_ballot(choices("5"), voter.clone(), BALLOT_KEY, connection)?;
+            save_ballot(choices("4"), voter, BALLOT_KEY, connection)?;
+            save_ballot(choices("4"), random_user_id(), BALLOT_KEY, connection)?;
+            save_ballot(choices("5"), random_user_id(), BALLOT_KEY, connection)?;
+
+            let early = advance_bracket(started + chrono::Duration::minutes(30), connection)?;
+            let first =
+                advance_bracket(started + chrono::Duration::minutes(61), connection)?.unwrap();
+            let second =
+                advance_bracket(started + chrono::Duration::minutes(122), connection)?.unwrap();
+            let last =
+                advance_bracket(started + chrono::Duration::minutes(183), connection)?.unwrap();
+
+            assert!(too_many.is_err(), "only past winners are seeded");
+            assert_eq!(
+                bracket.songs[0].song_uri, "winner_5",
+                "the latest winner is seed 1"
+            );
+            assert_eq!(bracket.competition.kind, CompetitionKind::Bracket);
+            assert_eq!(
+                bracket
+                    .matches
+                    .iter()
+                    .map(|m| (m.home_song_id, m.away_song_id, m.winner_song_id))
+                    .collect::<Vec<_>>(),
+                vec![
+                    (seed(1), None, Some(seed(1))),
+                    (seed(4), Some(seed(5)), None),
+                    (seed(2), None, Some(seed(2))),
+                    (seed(3), None, Some(seed(3))),
+                ],
+                "the top seeds get a bye"
+            );
+            assert!(bye_vote.is_err(), "a bye has no vote");
+            assert!(song.is_err(), "no songs are submitted to a bracket");
+            assert!(early.is_none(), "matches close on their deadline");
+            assert_eq!(
+                (first.closed[0].home_votes, first.closed[0].away_votes),
+                (2, 2),
+                "a user has a single vote in a match"
+            );
+            assert_eq!(
+                first.closed[0].winner_song_id,
+                Some(seed(4)),
+                "the better seed wins a tie"
+            );
+            assert_eq!(
+                first
+                    .opened
+                    .iter()
+                    .map(|m| (m.home_song_id, m.away_song_id))
+                    .collect::<Vec<_>>(),
+                vec![(seed(1), Some(seed(4))), (seed(2), Some(seed(3)))]
+            );
+            assert_eq!(second.opened.len(), 1, "the final");
+            assert!(!last.bracket.competition.is_active);
+            assert_eq!(last.bracket.competition.winner_song_id, Some(seed(1)));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_season() {
+        let connection = &test_db_connection();
 
-            let saved_song = insert_into(song)
-                .values(&new_song_insert)
-                .get_result::<Song>(connection)?;
+        connection.test_transaction::<_, BotError, _>(|| {
+            let host = random_user_id();
+            let season_insert = || SeasonInsert {
+                name: "2026 Q4".to_string(),
+                user_id: host.clone(),
+                started: chrono::Utc::now(),
+                competitions: 2,
+                points: vec![3, 1],
+            };
+            let season = start_season(season_insert(), connection)?;
+            let second_season = start_season(season_insert(), connection);
+            let submitters = vec![random_user_id(), random_user_id(), random_user_id()];
 
-            info!(
-                "Saved song for user_id={}, song={:#?}",
-                new_song_user_id, saved_song
+            let mut season_ends = vec![];
+            for votes in &["1", "2 2 3"] {
+                let competition =
+                    save_competition(create_competition_insert(host.clone(), false), connection)?;
+                assert_eq!(competition.season_id, Some(season.id));
+                for submitter in &submitters {
+                    save_song("song_uri".to_string(), submitter.clone(), connection)?;
+                }
+                for vote in votes.split_whitespace() {
+                    save_ballot(choices(vote), random_user_id(), BALLOT_KEY, connection)?;
+                }
+                match stop_competition(Actor::user(host.clone()), None, BALLOT_KEY, connection)? {
+                    Closing::Closed(results) => season_ends.push(results.season_end),
+                    closing => panic!("expected the competition to close, got {:?}", closing),
+                }
+            }
+
+            assert!(
+                matches!(
+                    second_season.err().unwrap().data_error,
+                    DataError::InvalidArgument(_)
+                ),
+                "one season runs at a time"
+            );
+            assert!(season_ends[0].is_none());
+            let season_end = season_ends[1]
+                .as_ref()
+                .expect("the last competition ends the season");
+            assert!(!season_end.season.is_active);
+            assert_eq!(season_end.closed, 2);
+            let totals: Vec<(&String, i32, i32)> = season_end
+                .standings
+                .iter()
+                .map(|standing| (&standing.user_id, standing.points, standing.wins))
+                .collect();
+            assert_eq!(
+                totals,
+                vec![
+                    (&submitters[1], 4, 1),
+                    (&submitters[0], 3, 1),
+                    (&submitters[2], 2, 0)
+                ],
+                "songs without votes share a place"
             );
+            assert_eq!(&find_season_standings(connection)?, season_end);
 
-            Ok(saved_song)
-        }
+            Ok(())
+        });
     }
-}
 
-pub fn save_song_vote(
-    new_vote_song_id: Uuid,
-    new_vote_song_user_id: String,
-    connection: &PgConnection,
-) -> Result<SongVote, BotError> {
-    use crate::schema::sotw::song_vote::dsl::song_vote;
+    #[test]
+    fn test_history() {
+        let connection = &test_db_connection();
 
-    let new_song_vote = SongVoteInsert {
-        user_id: new_vote_song_user_id,
-        song_id: new_vote_song_id,
-    };
+        connection.test_transaction::<_, BotError, _>(|| {
+            let host = random_user_id();
+            for description in &["Trains", "Boats", "Planes"] {
+                let mut competition_insert = create_competition_insert(host.clone(), false);
+                competition_insert.description = description.to_string();
+                save_competition(competition_insert, connection)?;
+                save_song("song_uri".to_string(), random_user_id(), connection)?;
+                save_ballot(choices("1"), random_user_id(), BALLOT_KEY, connection)?;
+                stop_competition(Actor::user(host.clone()), None, BALLOT_KEY, connection)?;
+            }
 
-    let saved_song_vote = insert_into(song_vote)
-        .values(&new_song_vote)
-        .get_result::<SongVote>(connection)?;
+            let first_page = list_past_competitions(1, 2, connection)?;
+            let last_page = list_past_competitions(2, 2, connection)?;
+            let descriptions = |page: &HistoryPage| -> Vec<String> {
+                page.competitions
+                    .iter()
+                    .map(|(past, _)| past.description.clone())
+                    .collect()
+            };
 
-    Ok(saved_song_vote)
-}
+            assert_eq!(first_page.total, 3);
+            assert_eq!(descriptions(&first_page), vec!["Planes", "Boats"]);
+            assert_eq!(descriptions(&last_page), vec!["Trains"]);
+            let (trains, winner) = &last_page.competitions[0];
+            assert_eq!(winner.as_ref().map(|song| song.id), trains.winner_song_id);
 
-#[cfg(test)]
-mod tests {
-    use crate::sotw_db::database::{
-        close_competition, find_active_competition, list_songs_active_competition,
-        save_competition, save_song, save_song_vote,
-    };
-    use crate::sotw_db::errors::{BotError, DataError};
-    use crate::sotw_db::model::{Competition, CompetitionInsert};
-    use diesel::{Connection, PgConnection};
+            match find_past_competition(CompetitionRef::Recent(3), BALLOT_KEY, connection)? {
+                PastCompetition::Open(results) => {
+                    assert_eq!(results.competition.id, trains.id);
+                    assert_eq!(results.tally.winners, vec![results.songs[0].id]);
+                }
+                past => panic!("expected an open competition, got {:?}", past),
+            }
+            let missing = find_past_competition(CompetitionRef::Recent(4), BALLOT_KEY, connection);
+            assert!(matches!(
+                missing.err().unwrap().data_error,
+                DataError::InvalidArgument(_)
+            ));
 
-    fn random_user_id() -> String {
-        uuid::Uuid::new_v4().to_string()
+            Ok(())
+        });
     }
 
-    fn test_db_connection() -> PgConnection {
-        dotenv::dotenv().ok();
+    #[test]
+    fn test_user_stats() {
+        let connection = &test_db_connection();
 
-        let connection_string =
-            std::env::var("DATABASE_URL").expect("Database connection string missing!");
-        PgConnection::establish(&connection_string).unwrap()
-    }
+        connection.test_transaction::<_, BotError, _>(|| {
+            let host = random_user_id();
+            let user = random_user_id();
+            let other = random_user_id();
+            // The user's song and the votes in each competition, latest last
+            let competitions = vec![
+                (Some("https://open.spotify.com/track/1"), "1 1 2"),
+                (None, "1"),
+                (Some("https://youtu.be/1"), "1"),
+                (Some("spotify:track:2"), "2"),
+            ];
+            for (song_uri, votes) in competitions {
+                save_competition(create_competition_insert(host.clone(), false), connection)?;
+                save_song("song_uri".to_string(), other.clone(), connection)?;
+                if let Some(song_uri) = song_uri {
+                    save_song(song_uri.to_string(), user.clone(), connection)?;
+                }
+                for vote in votes.split_whitespace() {
+                    save_ballot(choices(vote), random_user_id(), BALLOT_KEY, connection)?;
+                }
+                stop_competition(Actor::user(host.clone()), None, BALLOT_KEY, connection)?;
+            }
 
-    fn create_competition_insert(user_id: String, is_active: bool) -> CompetitionInsert {
-        CompetitionInsert {
-            description: "asdf".to_string(),
-            user_id,
-            started: chrono::Utc::now(),
-            ended: if !is_active {
-                Some(chrono::Utc::now())
-            } else {
-                None
-            },
-            is_active,
-        }
+            let stats = user_stats(user.clone(), connection)?;
+
+            assert_eq!(stats.entered, 3);
+            assert_eq!(stats.wins, 1, "the user only won the latest");
+            assert_eq!(stats.podiums, 3);
+            assert_eq!(stats.streak, 2);
+            let share = stats.average_share.unwrap();
+            assert!((share - (100.0 / 3.0 + 0.0 + 100.0) / 3.0).abs() < 1e-9);
+            assert_eq!(stats.provider, Some(("Spotify".to_string(), 2)));
+            assert_eq!(user_stats(random_user_id(), connection)?.entered, 0);
+
+            Ok(())
+        });
     }
 
     #[test]
-    fn test_save() {
+    fn test_badges() {
         let connection = &test_db_connection();
 
-        connection.test_transaction::<_, DataError, _>(|| {
-            let competition = create_competition_insert(random_user_id(), true);
-
-            let result = save_competition(competition, connection);
+        connection.test_transaction::<_, BotError, _>(|| {
+            let host = random_user_id();
+            let submitter = random_user_id();
+            let mut awarded = vec![];
+            for _ in 0..2 {
+                save_competition(create_competition_insert(host.clone(), false), connection)?;
+                save_song("song_uri".to_string(), submitter.clone(), connection)?;
+                save_ballot(choices("1"), random_user_id(), BALLOT_KEY, connection)?;
+                match stop_competition(Actor::user(host.clone()), None, BALLOT_KEY, connection)? {
+                    Closing::Closed(results) => awarded.push(results.badges),
+                    closing => panic!("expected the competition to close, got {:?}", closing),
+                }
+            }
 
-            assert!(
-                !result.is_err(),
-                "should not be an error when doing a clean save"
+            let first: Vec<(&String, &str)> = awarded[0]
+                .iter()
+                .map(|badge| (&badge.user_id, badge.achievement.as_str()))
+                .collect();
+            assert_eq!(
+                first,
+                vec![(&submitter, "first_win"), (&host, "theme_host")]
             );
+            assert!(awarded[1].is_empty(), "a badge is only awarded once");
+            assert_eq!(list_badges(&submitter, connection)?.len(), 1);
 
             Ok(())
         });
     }
 
     #[test]
-    fn test_save_exists() {
+    fn test_host_roster() {
         let connection = &test_db_connection();
 
         connection.test_transaction::<_, BotError, _>(|| {
-            let user_id_owner = random_user_id();
+            let channel = random_user_id();
+            let hosts: Vec<String> = (0..3).map(|_| random_user_id()).collect();
+            for host in &hosts {
+                join_roster(channel.clone(), host.clone(), connection)?;
+            }
+            let rejoined = join_roster(channel.clone(), hosts[0].clone(), connection);
+            assert!(rejoined.is_err(), "a user is in the queue once");
 
-            let result_owner_ok = save_competition(
-                create_competition_insert(user_id_owner.clone(), true),
-                connection,
-            );
-            let result_owner_err =
-                save_competition(create_competition_insert(user_id_owner, true), connection);
+            let roster = skip_host(&Actor::user(hosts[0].clone()), channel.clone(), connection)?;
+            assert_eq!(roster.nominee(), Some(hosts[1].as_str()));
 
-            assert!(
-                &result_owner_ok.is_ok(),
-                "owner should be able to insert the first competition fine"
-            );
+            let mut competition = create_competition_insert(hosts[1].clone(), false);
+            competition.channel_id = Some(channel.clone());
+            save_competition(competition, connection)?;
+            let next_host = match stop_competition(
+                Actor::user(hosts[1].clone()),
+                None,
+                BALLOT_KEY,
+                connection,
+            )? {
+                Closing::Closed(results) => results.next_host,
+                closing => panic!("expected the competition to close, got {:?}", closing),
+            };
+            assert_eq!(next_host, Some(hosts[2].clone()));
 
+            let roster = leave_roster(channel.clone(), hosts[2].clone(), connection)?;
             assert_eq!(
-                result_owner_err.err().unwrap().data_error,
-                BotError {
-                    data_error: DataError::ActiveCompetitionExists(result_owner_ok.unwrap().id),
-                    message: "".to_string(),
-                }
-                .data_error,
-                "should not be able to save while active competition exists"
+                roster.nominee(),
+                Some(hosts[0].as_str()),
+                "the turn wraps around to the start of the queue"
             );
+            assert_eq!(roster.members.len(), 2);
 
             Ok(())
         });
     }
 
     #[test]
-    fn test_close() {
+    fn test_roles() {
         let connection = &test_db_connection();
 
         connection.test_transaction::<_, BotError, _>(|| {
-            let user_id_owner = random_user_id();
-            let user_id_other = random_user_id();
+            let channel = random_user_id();
+            let host = Actor::user(random_user_id());
+            let co_host = Actor::user(random_user_id());
+            let admin = Actor {
+                user_id: random_user_id(),
+                admin: true,
+            };
+            save_competition(
+                create_competition_insert(host.user_id.clone(), false),
+                connection,
+            )?;
 
-            let result_owner = save_competition(
-                create_competition_insert(user_id_owner.clone(), true),
+            let not_permitted = grant_role(
+                &host,
+                co_host.user_id.clone(),
+                Role::ChannelAdmin,
+                channel.clone(),
                 connection,
             );
-
-            let result_close_other = close_competition(user_id_other, connection);
-            let result_close_owner = close_competition(user_id_owner, connection);
-
-            assert!(
-                result_close_owner.is_ok(),
-                "owner should be able to close competition"
+            assert_eq!(
+                not_permitted.err().unwrap().data_error,
+                DataError::NotPermitted(
+                    "only admins can grant or revoke the channel admin role".to_string()
+                )
             );
 
-            assert!(
-                result_close_owner.unwrap().ended.is_some(),
-                "should contain an ended date when closed"
+            grant_role(
+                &host,
+                co_host.user_id.clone(),
+                Role::CoHost,
+                channel.clone(),
+                connection,
+            )?;
+            grant_role(
+                &admin,
+                host.user_id.clone(),
+                Role::ChannelAdmin,
+                channel.clone(),
+                connection,
+            )?;
+            let twice = grant_role(
+                &host,
+                co_host.user_id.clone(),
+                Role::CoHost,
+                channel.clone(),
+                connection,
             );
+            assert!(twice.is_err(), "a role is granted once");
+            assert_eq!(list_roles(channel.clone(), connection)?.len(), 2);
 
-            assert_eq!(
-                result_close_other.err().unwrap().data_error,
-                BotError {
-                    data_error: DataError::UserDoesNotOwnEntity(result_owner.unwrap().id),
-                    message: "".to_string()
-                }
-                .data_error,
-                "should not be able to close competition not owned"
+            revoke_role(
+                &admin,
+                host.user_id.clone(),
+                Role::ChannelAdmin,
+                channel.clone(),
+                connection,
+            )?;
+            assert_eq!(list_roles(channel, connection)?.len(), 1);
+
+            let closing = stop_competition(co_host, None, BALLOT_KEY, connection)?;
+            assert!(
+                matches!(closing, Closing::Closed(_)),
+                "co-hosts can stop the competition"
             );
 
             Ok(())
@@ -291,90 +4119,168 @@ mod tests {
     }
 
     #[test]
-    fn test_find_active() {
+    fn test_handover() {
         let connection = &test_db_connection();
 
         connection.test_transaction::<_, BotError, _>(|| {
-            let insert_competition = create_competition_insert(random_user_id(), true);
-            let inserted_competition = save_competition(insert_competition, connection)?;
-            let active_competition = find_active_competition(connection)?;
+            let host = Actor::user(random_user_id());
+            let new_host = Actor::user(random_user_id());
+            let co_host = Actor::user(random_user_id());
+            let competition = save_competition(
+                create_competition_insert(host.user_id.clone(), false),
+                connection,
+            )?;
 
+            let handover = handover_competition(&host, new_host.user_id.clone(), connection)?;
             assert_eq!(
-                inserted_competition,
-                active_competition.unwrap(),
-                "the correct competition must match the one active"
+                (handover.from_user_id.as_str(), handover.to_user_id.as_str()),
+                (host.user_id.as_str(), new_host.user_id.as_str())
+            );
+            let handed_over = find_active_competition(connection)?.unwrap();
+            assert_eq!(handed_over.user_id, new_host.user_id);
+            assert_eq!(
+                handed_over.previous_user_id,
+                Some(host.user_id.clone()),
+                "the competition records who hosted it before"
+            );
+            assert_eq!(handed_over.handed_over, Some(handover.handed_over));
+            assert_eq!(
+                close_competition(&host, connection)
+                    .err()
+                    .unwrap()
+                    .data_error,
+                DataError::UserDoesNotOwnEntity(competition.id),
+                "the old host no longer owns the competition"
             );
 
+            grant_role(
+                &new_host,
+                co_host.user_id.clone(),
+                Role::CoHost,
+                random_user_id(),
+                connection,
+            )?;
+            let closed = close_competition(&co_host, connection)?;
+            assert_eq!(closed.user_id, new_host.user_id);
+
             Ok(())
-        })
+        });
     }
 
     #[test]
-    fn test_not_found() {
+    fn test_edit_extend_reopen() {
         let connection = &test_db_connection();
 
         connection.test_transaction::<_, BotError, _>(|| {
-            let result: Option<Competition> = find_active_competition(connection)?;
+            let host = Actor::user(random_user_id());
+            let channel = random_user_id();
+            let now = chrono::Utc::now();
+            let mut competition_insert = create_competition_insert(host.user_id.clone(), false);
+            competition_insert.channel_id = Some(channel.clone());
+            competition_insert.submission_deadline = Some(now - chrono::Duration::hours(1));
+            let trains = save_competition(competition_insert, connection)?;
 
-            assert_eq!(result, None, "should return None since there is no data");
+            let not_host = edit_description(
+                &Actor::user(random_user_id()),
+                "Boats".to_string(),
+                connection,
+            );
+            assert!(not_host.is_err(), "only the host edits the competition");
+            let (old, edited) = edit_description(&host, "Boats".to_string(), connection)?;
+            assert_eq!(
+                (old.as_str(), edited.description.as_str()),
+                (trains.description.as_str(), "Boats")
+            );
+
+            let extension = extend_deadline(&host, chrono::Duration::hours(2), now, connection)?;
+            assert_eq!(
+                extension,
+                Extension::Submissions(now + chrono::Duration::hours(2)),
+                "a passed deadline is extended from now"
+            );
+
+            save_song("song_uri".to_string(), random_user_id(), connection)?;
+            save_ballot(choices("1"), random_user_id(), BALLOT_KEY, connection)?;
+            stop_competition(host.clone(), None, BALLOT_KEY, connection)?;
+            let reopened = reopen_competition(&host, None, channel.clone(), connection)?;
+            assert_eq!(reopened.id, trains.id);
+            assert!(reopened.is_active && reopened.winner_song_id.is_none());
+            assert_eq!(list_songs(trains.id, connection)?[0].place, None);
+
+            close_competition(&host, connection)?;
+            let mut newer = create_competition_insert(host.user_id.clone(), false);
+            newer.channel_id = Some(channel.clone());
+            newer.started = now + chrono::Duration::minutes(1);
+            save_competition(newer, connection)?;
+            close_competition(&host, connection)?;
+            let superseded = reopen_competition(
+                &host,
+                Some(CompetitionRef::Id(trains.id)),
+                channel,
+                connection,
+            );
+            assert_eq!(
+                superseded.err().unwrap().data_error,
+                DataError::InvalidArgument(
+                    "a newer competition was started in its channel".to_string()
+                )
+            );
 
             Ok(())
         });
     }
 
     #[test]
-    fn test_save_song() {
+    fn test_claim_request() {
         let connection = &test_db_connection();
 
         connection.test_transaction::<_, BotError, _>(|| {
-            let active_competition = save_competition(
-                create_competition_insert(random_user_id(), false),
+            let key = format!("T1:{}", random_user_id());
+
+            let first = claim_request(key.clone(), connection)?;
+            let retry_in_progress = claim_request(key.clone(), connection)?;
+
+            complete_request(
+                key.clone(),
+                200,
+                Some("application/json".to_string()),
+                "{}".to_string(),
                 connection,
             )?;
+            let retry_completed = claim_request(key, connection)?;
 
-            let inserted_song = save_song("".to_string(), "cmd".to_string(), connection)?;
+            assert_eq!(first, RequestClaim::Claimed, "first request should run");
             assert_eq!(
-                inserted_song.competition_id, active_competition.id,
-                "inserted song needs to match the id of the active competition"
+                retry_in_progress,
+                RequestClaim::InProgress,
+                "retry should not run while the first is still running"
             );
+            match retry_completed {
+                RequestClaim::Completed(stored) => {
+                    assert_eq!(stored.status_code, Some(200));
+                    assert_eq!(stored.response_body, Some("{}".to_string()));
+                }
+                other => panic!("retry should get the stored outcome, got {:?}", other),
+            }
 
             Ok(())
         });
     }
 
     #[test]
-    fn test_save_song_exists() {
+    fn test_release_request() {
         let connection = &test_db_connection();
 
         connection.test_transaction::<_, BotError, _>(|| {
-            save_competition(
-                create_competition_insert(random_user_id(), false),
-                connection,
-            )?;
-
-            let inserted_song_first =
-                save_song("song_1_uri".to_string(), "user".to_string(), connection)?;
-            let inserted_song_second =
-                save_song("song_2_uri".to_string(), "user".to_string(), connection)?;
-            let inserted_song_other_user = save_song(
-                "song_other_uri".to_string(),
-                "user_other".to_string(),
-                connection,
-            )?;
+            let key = format!("T1:{}", random_user_id());
 
-            let songs = list_songs_active_competition(connection)?;
+            claim_request(key.clone(), connection)?;
+            release_request(key.clone(), connection)?;
 
-            assert!(
-                songs.contains(&inserted_song_other_user),
-                "inserting a new song for one user should not impact other users"
-            );
-            assert!(
-                songs.contains(&inserted_song_second),
-                "second inserted song should be present"
-            );
-            assert!(
-                !songs.contains(&inserted_song_first),
-                "first inserted song should be removed by second inserted song"
+            assert_eq!(
+                claim_request(key, connection)?,
+                RequestClaim::Claimed,
+                "a released request should run again"
             );
 
             Ok(())
@@ -382,57 +4288,93 @@ mod tests {
     }
 
     #[test]
-    fn test_list_songs_active_competition() {
+    fn test_record_signature() {
         let connection = &test_db_connection();
 
         connection.test_transaction::<_, BotError, _>(|| {
-            save_competition(
-                create_competition_insert(random_user_id(), true),
-                connection,
-            )?;
-
-            for _ in 1..=10 {
-                save_song(
-                    "http://example.org/song123".to_string(),
-                    random_user_id(),
-                    connection,
-                )?;
-            }
+            let signature = format!("v0={}", random_user_id());
+            let timestamp = chrono::Utc::now().timestamp();
 
-            let active_songs = list_songs_active_competition(connection)?;
+            let first = record_signature(signature.clone(), timestamp, connection)?;
+            let replayed = record_signature(signature, timestamp, connection)?;
+            let other =
+                record_signature(format!("v0={}", random_user_id()), timestamp, connection)?;
 
-            assert_eq!(active_songs.len(), 10, "all songs should be present");
+            assert!(first, "first use of a signature should be accepted");
+            assert!(
+                !replayed,
+                "reusing a signature within the window should be rejected"
+            );
+            assert!(other, "other signatures should not be affected");
 
             Ok(())
         });
     }
 
     #[test]
-    fn test_vote_song() {
+    fn test_moderation() {
         let connection = &test_db_connection();
 
         connection.test_transaction::<_, BotError, _>(|| {
-            let active_competition = save_competition(
-                create_competition_insert(random_user_id(), false),
-                connection,
-            )?;
-            let inserted_song = save_song(
-                "http://example.org/song123".to_string(),
-                random_user_id(),
+            let host = Actor::user(random_user_id());
+            let stranger = Actor::user(random_user_id());
+            let channel = random_user_id();
+            let mut competition_insert = create_competition_insert(host.user_id.clone(), true);
+            competition_insert.channel_id = Some(channel.clone());
+            save_competition(competition_insert, connection)?;
+            for _ in 0..3 {
+                save_song("song_uri".to_string(), random_user_id(), connection)?;
+            }
+            save_ballot(choices("1"), random_user_id(), BALLOT_KEY, connection)?;
+            save_ballot(choices("1"), random_user_id(), BALLOT_KEY, connection)?;
+            save_ballot(choices("2"), random_user_id(), BALLOT_KEY, connection)?;
+            save_ballot(choices("3"), random_user_id(), BALLOT_KEY, connection)?;
+
+            let refused = remove_song(&stranger, SongRef::Number(3), connection);
+            let removed = remove_song(&host, SongRef::Number(3), connection)?;
+            let missing = remove_song(&host, SongRef::Number(3), connection);
+            assert!(matches!(
+                refused.err().unwrap().data_error,
+                DataError::UserDoesNotOwnEntity(_)
+            ));
+            assert_eq!(removed.number, 3);
+            assert!(missing.is_err(), "the song is gone");
+
+            let disqualified = disqualify_song(&host, SongRef::Number(1), connection)?;
+            let again = disqualify_song(&host, SongRef::Number(1), connection);
+            let vote = save_ballot(choices("1"), random_user_id(), BALLOT_KEY, connection);
+            let (competition, songs) = list_songs_active_competition(connection)?;
+            let counted = tally_competition(&competition, &songs, BALLOT_KEY, connection)?;
+            assert!(disqualified.disqualified);
+            assert!(again.is_err());
+            assert!(vote.is_err(), "disqualified songs can not be voted for");
+            assert_eq!(songs.len(), 2, "disqualified songs stay listed");
+            assert_eq!(counted.winners, vec![songs[1].id]);
+
+            let banned = random_user_id();
+            let not_allowed =
+                ban_user(&stranger, banned.clone(), channel.clone(), None, connection);
+            ban_user(
+                &host,
+                banned.clone(),
+                channel.clone(),
+                Some("spamming links".to_string()),
                 connection,
             )?;
-            let voted_song =
-                save_song_vote(inserted_song.id, "example|123".to_string(), connection)?;
-
-            assert_eq!(
-                inserted_song.competition_id, active_competition.id,
-                "song must match competition"
-            );
-            assert_eq!(
-                voted_song.song_id, inserted_song.id,
-                "voted song must match song"
+            let song = save_song("song_uri".to_string(), banned.clone(), connection);
+            let vote = save_ballot(choices("2"), banned.clone(), BALLOT_KEY, connection);
+            assert!(not_allowed.is_err());
+            assert!(song.is_err());
+            assert!(vote.is_err());
+            assert_eq!(list_bans(channel.clone(), connection)?.len(), 1);
+            assert!(
+                list_bans(random_user_id(), connection)?.is_empty(),
+                "bans only apply in their channel"
             );
 
+            unban_user(&host, banned.clone(), channel, connection)?;
+            save_song("song_uri".to_string(), banned, connection)?;
+
             Ok(())
         });
     }
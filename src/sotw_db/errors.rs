@@ -9,9 +9,11 @@ use uuid::Uuid;
 pub enum DataError {
     NotImplementedError,
     CmdParsingError(String),
+    InvalidArgument(String),
     NoActiveCompetition,
     ActiveCompetitionExists(Uuid),
     UserDoesNotOwnEntity(Uuid),
+    NotPermitted(String),
     DieselError(String),
 }
 
@@ -28,6 +30,7 @@ impl fmt::Display for DataError {
             DataError::CmdParsingError(ref msg) => {
                 write!(f, "Error trying to parse cmd err={:?}", msg)
             }
+            DataError::InvalidArgument(ref msg) => write!(f, "Invalid argument: {}", msg),
             DataError::NoActiveCompetition => write!(f, "No active competition available"),
             DataError::ActiveCompetitionExists(ref id) => {
                 write!(f, "An active competition already exists id={:?}", id)
@@ -35,6 +38,7 @@ impl fmt::Display for DataError {
             DataError::UserDoesNotOwnEntity(ref id) => {
                 write!(f, "Competition id={:?} is owned by another user", id)
             }
+            DataError::NotPermitted(ref msg) => write!(f, "Not permitted: {}", msg),
             DataError::DieselError(error) => write!(f, "{:?}", error.to_string()),
         }
     }
@@ -52,8 +56,21 @@ impl fmt::Display for BotError {
 
 impl From<diesel::result::Error> for BotError {
     fn from(error: diesel::result::Error) -> Self {
+        use diesel::result::{DatabaseErrorKind, Error};
+
+        // Missing rows and constraint violations come from the request itself,
+        // anything else is the database failing and worth a retry
+        let data_error = match error {
+            Error::NotFound
+            | Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)
+            | Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => {
+                DataError::CmdParsingError(error.to_string())
+            }
+            _ => DataError::DieselError(error.to_string()),
+        };
+
         BotError {
-            data_error: DataError::CmdParsingError(error.to_string()),
+            data_error,
             message: error.to_string(),
         }
     }
@@ -63,9 +80,11 @@ impl ResponseError for BotError {
     fn status_code(&self) -> StatusCode {
         match self.data_error {
             DataError::NotImplementedError => StatusCode::NOT_IMPLEMENTED,
+            DataError::InvalidArgument(_) => StatusCode::BAD_REQUEST,
             DataError::NoActiveCompetition => StatusCode::NOT_FOUND,
-            DataError::DieselError(_) => StatusCode::METHOD_NOT_ALLOWED,
+            DataError::DieselError(_) => StatusCode::SERVICE_UNAVAILABLE,
             DataError::ActiveCompetitionExists(_) => StatusCode::CONFLICT,
+            DataError::NotPermitted(_) => StatusCode::FORBIDDEN,
             _ => StatusCode::IM_A_TEAPOT,
         }
     }
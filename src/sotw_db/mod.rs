@@ -1,3 +1,9 @@
+pub mod achievements;
+pub mod bracket;
 pub mod database;
 pub mod errors;
 pub mod model;
+pub mod permissions;
+pub mod season;
+pub mod stats;
+pub mod tally;
@@ -1,9 +1,26 @@
+use crate::schema::sotw::badge as badge_table;
+use crate::schema::sotw::ban as ban_table;
+use crate::schema::sotw::bracket_match as bracket_match_table;
 use crate::schema::sotw::competition as competition_table;
+use crate::schema::sotw::handover as handover_table;
+use crate::schema::sotw::host_roster as host_roster_table;
+use crate::schema::sotw::season as season_table;
+use crate::schema::sotw::slack_request as slack_request_table;
+use crate::schema::sotw::slack_signature as slack_signature_table;
 use crate::schema::sotw::song as song_table;
 use crate::schema::sotw::song_vote as song_vote_table;
+use crate::schema::sotw::user_role as user_role_table;
 
 use chrono::{DateTime, Utc};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
 use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
 use uuid::Uuid;
 
 /// Describes the SOTW specific tables in the database
@@ -18,6 +35,26 @@ pub struct Competition {
     pub started: DateTime<Utc>,
     pub ended: Option<DateTime<Utc>>,
     pub is_active: bool,
+    pub voting_method: VotingMethod,
+    pub points_budget: i32,
+    pub tie_break: TieBreak,
+    pub runoff: bool,
+    pub winner_song_id: Option<Uuid>,
+    pub tie_break_used: Option<String>,
+    pub tie_break_seed: Option<i64>,
+    pub rules: CompetitionRules,
+    pub submission_deadline: Option<DateTime<Utc>>,
+    pub categories: Vec<String>,
+    pub judges: Vec<String>,
+    pub panel_weight: i32,
+    pub rubric: Vec<String>,
+    pub kind: CompetitionKind,
+    pub match_minutes: Option<i32>,
+    pub season_id: Option<Uuid>,
+    pub channel_id: Option<String>,
+    pub next_song_number: i32,
+    pub previous_user_id: Option<String>,
+    pub handed_over: Option<DateTime<Utc>>,
 }
 
 #[derive(PartialEq, Debug, Deserialize, Insertable)]
@@ -28,15 +65,281 @@ pub struct CompetitionInsert {
     pub started: DateTime<Utc>,
     pub ended: Option<DateTime<Utc>>,
     pub is_active: bool,
+    pub voting_method: VotingMethod,
+    pub points_budget: i32,
+    pub tie_break: TieBreak,
+    pub rules: CompetitionRules,
+    pub submission_deadline: Option<DateTime<Utc>>,
+    pub categories: Vec<String>,
+    pub judges: Vec<String>,
+    pub panel_weight: i32,
+    pub rubric: Vec<String>,
+    pub kind: CompetitionKind,
+    pub match_minutes: Option<i32>,
+    pub season_id: Option<Uuid>,
+    pub channel_id: Option<String>,
+}
+
+impl Competition {
+    /// Whether a song submitted at the given time is past the submission deadline
+    pub fn is_late(&self, submitted: DateTime<Utc>) -> bool {
+        matches!(self.submission_deadline, Some(deadline) if submitted > deadline)
+    }
+
+    /// Whether the user is on the panel of judges
+    pub fn is_judge(&self, user: &str) -> bool {
+        self.judges.iter().any(|judge| judge == user)
+    }
+}
+
+#[cfg(test)]
+impl Competition {
+    /// Active open competition of the host with the default settings, tests
+    /// override the fields they check
+    pub fn for_test(host: &str) -> Competition {
+        Competition {
+            id: Uuid::new_v4(),
+            description: "trains".to_string(),
+            user_id: host.to_string(),
+            started: Utc::now(),
+            ended: None,
+            is_active: true,
+            voting_method: VotingMethod::Plurality,
+            points_budget: 10,
+            tie_break: TieBreak::Earliest,
+            runoff: false,
+            winner_song_id: None,
+            tie_break_used: None,
+            tie_break_seed: None,
+            rules: CompetitionRules::default(),
+            submission_deadline: None,
+            categories: vec![],
+            judges: vec![],
+            panel_weight: 50,
+            rubric: vec![],
+            kind: CompetitionKind::Open,
+            match_minutes: None,
+            season_id: None,
+            channel_id: None,
+            next_song_number: 1,
+            previous_user_id: None,
+            handed_over: None,
+        }
+    }
+}
+
+#[cfg(test)]
+impl CompetitionInsert {
+    /// Active open competition of the host to save, like `Competition::for_test`
+    pub fn for_test(host: &str) -> CompetitionInsert {
+        CompetitionInsert {
+            description: "trains".to_string(),
+            user_id: host.to_string(),
+            started: Utc::now(),
+            ended: None,
+            is_active: true,
+            voting_method: VotingMethod::Plurality,
+            points_budget: 10,
+            tie_break: TieBreak::Earliest,
+            rules: CompetitionRules::default(),
+            submission_deadline: None,
+            categories: vec![],
+            judges: vec![],
+            panel_weight: 50,
+            rubric: vec![],
+            kind: CompetitionKind::Open,
+            match_minutes: None,
+            season_id: None,
+            channel_id: None,
+        }
+    }
+}
+
+// An achievement earned by a user, awarded once
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Queryable)]
+pub struct Badge {
+    pub id: Uuid,
+    pub user_id: String,
+    pub achievement: String,
+    pub competition_id: Uuid,
+    pub awarded: DateTime<Utc>,
+}
+
+#[derive(PartialEq, Debug, Deserialize, Insertable)]
+#[table_name = "badge_table"]
+pub struct BadgeInsert {
+    pub user_id: String,
+    pub achievement: String,
+    pub competition_id: Uuid,
+    pub awarded: DateTime<Utc>,
+}
+
+// A competition handed over to a new host
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Queryable)]
+pub struct Handover {
+    pub id: Uuid,
+    pub competition_id: Uuid,
+    pub from_user_id: String,
+    pub to_user_id: String,
+    pub user_id: String,
+    pub handed_over: DateTime<Utc>,
+}
+
+#[derive(PartialEq, Debug, Deserialize, Insertable)]
+#[table_name = "handover_table"]
+pub struct HandoverInsert {
+    pub competition_id: Uuid,
+    pub from_user_id: String,
+    pub to_user_id: String,
+    pub user_id: String,
+    pub handed_over: DateTime<Utc>,
+}
+
+// A user banned from submitting songs and voting in a channel
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Queryable)]
+pub struct Ban {
+    pub id: Uuid,
+    pub user_id: String,
+    pub channel_id: String,
+    pub reason: Option<String>,
+    pub banned_by: String,
+    pub banned: DateTime<Utc>,
+}
+
+#[derive(PartialEq, Debug, Deserialize, Insertable)]
+#[table_name = "ban_table"]
+pub struct BanInsert {
+    pub user_id: String,
+    pub channel_id: String,
+    pub reason: Option<String>,
+    pub banned_by: String,
+    pub banned: DateTime<Utc>,
+}
+
+// A member of the host roster of a channel, members take turns hosting
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Queryable)]
+pub struct HostRosterMember {
+    pub id: Uuid,
+    pub channel_id: String,
+    pub user_id: String,
+    pub position: i32,
+    pub nominated: Option<DateTime<Utc>>,
+    pub joined: DateTime<Utc>,
+}
+
+#[derive(PartialEq, Debug, Deserialize, Insertable)]
+#[table_name = "host_roster_table"]
+pub struct HostRosterInsert {
+    pub channel_id: String,
+    pub user_id: String,
+    pub position: i32,
+    pub nominated: Option<DateTime<Utc>>,
+    pub joined: DateTime<Utc>,
+}
+
+// A role granted to a user, channel admins are granted a channel and co-hosts a
+// competition
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Queryable)]
+pub struct UserRole {
+    pub id: Uuid,
+    pub user_id: String,
+    pub role: Role,
+    pub channel_id: Option<String>,
+    pub competition_id: Option<Uuid>,
+    pub granted_by: String,
+    pub granted: DateTime<Utc>,
+}
+
+#[derive(PartialEq, Debug, Deserialize, Insertable)]
+#[table_name = "user_role_table"]
+pub struct UserRoleInsert {
+    pub user_id: String,
+    pub role: Role,
+    pub channel_id: Option<String>,
+    pub competition_id: Option<Uuid>,
+    pub granted_by: String,
+    pub granted: DateTime<Utc>,
+}
+
+// A season groups competitions, with standings over all of them
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Queryable)]
+pub struct Season {
+    pub id: Uuid,
+    pub name: String,
+    pub user_id: String,
+    pub started: DateTime<Utc>,
+    pub ended: Option<DateTime<Utc>>,
+    pub is_active: bool,
+    pub competitions: i32,
+    pub points: Vec<i32>,
+}
+
+#[derive(PartialEq, Debug, Deserialize, Insertable)]
+#[table_name = "season_table"]
+pub struct SeasonInsert {
+    pub name: String,
+    pub user_id: String,
+    pub started: DateTime<Utc>,
+    pub competitions: i32,
+    pub points: Vec<i32>,
+}
+
+// Points by place in a season, written like 10,7,5,3,1
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String")]
+pub struct PointsScheme(pub Vec<i32>);
+
+impl Default for PointsScheme {
+    fn default() -> Self {
+        PointsScheme(vec![10, 7, 5, 3, 1])
+    }
+}
+
+impl FromStr for PointsScheme {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let points = s
+            .split(',')
+            .map(|points| points.trim().parse::<i32>().map_err(|_| ()))
+            .collect::<Result<Vec<i32>, ()>>()?;
+        // Points never go up for a lower place
+        let descending = points.windows(2).all(|pair| pair[0] >= pair[1]);
+        if points.is_empty() || !descending || points.iter().any(|p| *p < 0) {
+            return Err(());
+        }
+
+        Ok(PointsScheme(points))
+    }
+}
+
+impl TryFrom<String> for PointsScheme {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value
+            .parse()
+            .map_err(|_| format!("invalid points {}, like 10,7,5,3,1", value))
+    }
+}
+
+impl fmt::Display for PointsScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let points: Vec<String> = self.0.iter().map(|p| p.to_string()).collect();
+        write!(f, "{}", points.join(","))
+    }
 }
 
 // A song for the competition
-#[derive(PartialEq, Debug, Serialize, Deserialize, Queryable)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Queryable)]
 pub struct Song {
     pub id: Uuid,
     pub user_id: String,
     pub song_uri: String,
     pub competition_id: Uuid,
+    pub number: i32,
+    pub place: Option<i32>,
+    pub disqualified: bool,
 }
 
 #[derive(PartialEq, Debug, Deserialize, Insertable)]
@@ -45,6 +348,121 @@ pub struct SongInsert {
     pub user_id: String,
     pub song_uri: String,
     pub competition_id: Uuid,
+    pub number: i32,
+}
+
+// How a user refers to a song in a command,
+// by its number in the competition, as shown in the list, or by id
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum SongRef {
+    Number(i32),
+    Id(Uuid),
+}
+
+impl FromStr for SongRef {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim_start_matches('#');
+        match s.parse::<i32>() {
+            Ok(number) if number > 0 => Ok(SongRef::Number(number)),
+            Ok(_) => Err(()),
+            Err(_) => Uuid::from_str(s).map(SongRef::Id).map_err(|_| ()),
+        }
+    }
+}
+
+impl SongRef {
+    pub fn matches(&self, song: &Song) -> bool {
+        match self {
+            SongRef::Number(number) => song.number == *number,
+            SongRef::Id(id) => song.id == *id,
+        }
+    }
+}
+
+impl fmt::Display for SongRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SongRef::Number(number) => write!(f, "#{}", number),
+            SongRef::Id(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+// How a user refers to a past competition in a command,
+// by its number in the history, counting back from the latest, or by id
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum CompetitionRef {
+    Recent(i64),
+    Id(Uuid),
+}
+
+impl FromStr for CompetitionRef {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim_start_matches('#');
+        match s.parse::<i64>() {
+            Ok(number) if number > 0 => Ok(CompetitionRef::Recent(number)),
+            Ok(_) => Err(()),
+            Err(_) => Uuid::from_str(s).map(CompetitionRef::Id).map_err(|_| ()),
+        }
+    }
+}
+
+impl fmt::Display for CompetitionRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompetitionRef::Recent(number) => write!(f, "#{}", number),
+            CompetitionRef::Id(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+// A song on a user's ballot, with the score or points given as song=value
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct VoteChoice {
+    pub song: SongRef,
+    pub value: Option<i32>,
+}
+
+impl FromStr for VoteChoice {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '=');
+        let song = parts.next().ok_or(())?.parse()?;
+        let value = match parts.next() {
+            Some(value) => Some(value.parse().map_err(|_| ())?),
+            None => None,
+        };
+
+        Ok(VoteChoice { song, value })
+    }
+}
+
+impl fmt::Display for VoteChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.value {
+            Some(value) => write!(f, "{}={}", self.song, value),
+            None => write!(f, "{}", self.song),
+        }
+    }
+}
+
+// Longest name of an award category
+pub static MAX_CATEGORY_LENGTH: usize = 32;
+
+/// Award categories are named by a lowercase word, like `lyrics` or `best_banger`,
+/// which can't be mistaken for a song
+pub fn is_category_name(name: &str) -> bool {
+    name.len() <= MAX_CATEGORY_LENGTH
+        && name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+        && Uuid::from_str(name).is_err()
 }
 
 // A vote for any given song
@@ -54,6 +472,13 @@ pub struct SongVote {
     pub id: Uuid,
     pub user_id: String,
     pub song_id: Uuid,
+    pub rank: i32,
+    pub score: i32,
+    pub runoff: bool,
+    pub category: Option<String>,
+    pub panel: bool,
+    pub criterion: Option<String>,
+    pub bracket_match_id: Option<Uuid>,
 }
 
 #[derive(PartialEq, Debug, Deserialize, Insertable)]
@@ -61,4 +486,319 @@ pub struct SongVote {
 pub struct SongVoteInsert {
     pub user_id: String,
     pub song_id: Uuid,
+    pub rank: i32,
+    pub score: i32,
+    pub runoff: bool,
+    pub category: Option<String>,
+    pub panel: bool,
+    pub criterion: Option<String>,
+    pub bracket_match_id: Option<Uuid>,
+}
+
+// A head-to-head match in a bracket, the winner advances to the next round
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Queryable)]
+pub struct BracketMatch {
+    pub id: Uuid,
+    pub competition_id: Uuid,
+    pub round: i32,
+    pub slot: i32,
+    pub home_song_id: Uuid,
+    pub away_song_id: Option<Uuid>,
+    pub deadline: DateTime<Utc>,
+    pub closed: Option<DateTime<Utc>>,
+    pub winner_song_id: Option<Uuid>,
+    pub home_votes: i32,
+    pub away_votes: i32,
+}
+
+#[derive(PartialEq, Debug, Deserialize, Insertable)]
+#[table_name = "bracket_match_table"]
+pub struct BracketMatchInsert {
+    pub competition_id: Uuid,
+    pub round: i32,
+    pub slot: i32,
+    pub home_song_id: Uuid,
+    pub away_song_id: Option<Uuid>,
+    pub deadline: DateTime<Utc>,
+    pub closed: Option<DateTime<Utc>>,
+    pub winner_song_id: Option<Uuid>,
+}
+
+impl BracketMatch {
+    pub fn is_open(&self) -> bool {
+        self.closed.is_none()
+    }
+
+    pub fn has_song(&self, song_id: Uuid) -> bool {
+        self.home_song_id == song_id || self.away_song_id == Some(song_id)
+    }
+}
+
+// Enums stored in text columns, by the names from `as_str` and `FromStr`
+macro_rules! text_enum_sql {
+    ($enum:ty, $name:expr) => {
+        impl ToSql<Text, Pg> for $enum {
+            fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+                out.write_all(self.as_str().as_bytes())?;
+                Ok(IsNull::No)
+            }
+        }
+
+        impl FromSql<Text, Pg> for $enum {
+            fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+                let value = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+                value
+                    .parse()
+                    .map_err(|_| format!("Unrecognized {} {}", $name, value).into())
+            }
+        }
+    };
+}
+
+// How votes are cast and counted in a competition, stored by name
+#[derive(
+    PartialEq, Eq, Debug, Clone, Copy, Default, Serialize, Deserialize, AsExpression, FromSqlRow,
+)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "Text"]
+pub enum VotingMethod {
+    #[default]
+    Plurality, // A single vote per user, the most votes wins
+    InstantRunoff, // Ranked ballots, the last placed song is eliminated until one has a majority
+    Schulze, // Ranked ballots, songs are compared pairwise and placed by their strongest paths
+    Approval, // Users approve any number of songs, the most approvals wins
+    Score,   // Users score any number of songs from 0 to MAX_SCORE, the highest total wins
+    Points,  // Users spread a budget of points over the songs, the most points wins
+}
+
+// Highest score a song can be given when voting by score
+pub static MAX_SCORE: i32 = 5;
+
+impl VotingMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VotingMethod::Plurality => "plurality",
+            VotingMethod::InstantRunoff => "instant_runoff",
+            VotingMethod::Schulze => "schulze",
+            VotingMethod::Approval => "approval",
+            VotingMethod::Score => "score",
+            VotingMethod::Points => "points",
+        }
+    }
+
+    pub fn is_ranked(&self) -> bool {
+        matches!(self, VotingMethod::InstantRunoff | VotingMethod::Schulze)
+    }
+
+    /// Whether every song on the ballot is given a score or points
+    pub fn is_scored(&self) -> bool {
+        matches!(self, VotingMethod::Score | VotingMethod::Points)
+    }
+}
+
+impl FromStr for VotingMethod {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "plurality" => Ok(VotingMethod::Plurality),
+            "instant_runoff" | "ranked" | "irv" => Ok(VotingMethod::InstantRunoff),
+            "schulze" | "condorcet" => Ok(VotingMethod::Schulze),
+            "approval" => Ok(VotingMethod::Approval),
+            "score" => Ok(VotingMethod::Score),
+            "points" | "budget" => Ok(VotingMethod::Points),
+            _ => Err(()),
+        }
+    }
+}
+
+text_enum_sql!(VotingMethod, "voting method");
+
+// How a tie for the win is broken when a competition is closed
+#[derive(
+    PartialEq, Eq, Debug, Clone, Copy, Default, Serialize, Deserialize, AsExpression, FromSqlRow,
+)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "Text"]
+pub enum TieBreak {
+    #[default]
+    Earliest, // The song submitted first wins
+    Runoff,      // The tied songs go to a new vote, before the competition is closed
+    CastingVote, // The host picks the winner when closing the competition
+    Random,      // Drawn with a random seed, published with the results
+}
+
+impl TieBreak {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TieBreak::Earliest => "earliest",
+            TieBreak::Runoff => "runoff",
+            TieBreak::CastingVote => "casting_vote",
+            TieBreak::Random => "random",
+        }
+    }
+}
+
+impl FromStr for TieBreak {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "earliest" => Ok(TieBreak::Earliest),
+            "runoff" => Ok(TieBreak::Runoff),
+            "casting_vote" | "casting" | "host" => Ok(TieBreak::CastingVote),
+            "random" | "draw" => Ok(TieBreak::Random),
+            _ => Err(()),
+        }
+    }
+}
+
+text_enum_sql!(TieBreak, "tie break");
+
+// How the songs of a competition are decided between
+#[derive(
+    PartialEq, Eq, Debug, Clone, Copy, Default, Serialize, Deserialize, AsExpression, FromSqlRow,
+)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "Text"]
+pub enum CompetitionKind {
+    #[default]
+    Open, // Users submit songs and a single vote decides the winner
+    Bracket, // Past winners are seeded into head-to-head matches, round by round
+}
+
+impl CompetitionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompetitionKind::Open => "open",
+            CompetitionKind::Bracket => "bracket",
+        }
+    }
+}
+
+impl FromStr for CompetitionKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "open" => Ok(CompetitionKind::Open),
+            "bracket" => Ok(CompetitionKind::Bracket),
+            _ => Err(()),
+        }
+    }
+}
+
+text_enum_sql!(CompetitionKind, "competition kind");
+
+// Roles granting permissions beyond hosting a competition, stored by name
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "Text"]
+pub enum Role {
+    BotAdmin,     // Manages the bot in every channel
+    ChannelAdmin, // Manages the competitions and the host roster of a channel
+    CoHost,       // Runs a competition along with its host
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::BotAdmin => "bot_admin",
+            Role::ChannelAdmin => "channel_admin",
+            Role::CoHost => "co_host",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "bot_admin" | "admin" => Ok(Role::BotAdmin),
+            "channel_admin" => Ok(Role::ChannelAdmin),
+            "co_host" | "cohost" => Ok(Role::CoHost),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Role::BotAdmin => write!(f, "bot admin"),
+            Role::ChannelAdmin => write!(f, "channel admin"),
+            Role::CoHost => write!(f, "co-host"),
+        }
+    }
+}
+
+text_enum_sql!(Role, "role");
+
+// Rules for taking part in a competition, stored as json with the competition.
+// Rules missing from the stored json take their default.
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[serde(default)]
+#[sql_type = "Text"]
+pub struct CompetitionRules {
+    pub self_vote: bool,             // Users may vote for their own songs
+    pub songs_per_user: i32,         // With a single song, submitting again replaces it
+    pub submitters_must_vote: bool, // Songs of submitters who did not vote are left out of the tally
+    pub late_submissions: bool,     // Songs are accepted after the submission deadline
+    pub anonymous_submissions: bool, // Submitters are hidden until the competition closes
+    pub secret_ballot: bool, // Votes are confirmed only to the voter, and stored under a keyed hash
+}
+
+impl Default for CompetitionRules {
+    fn default() -> Self {
+        CompetitionRules {
+            self_vote: false,
+            songs_per_user: 1,
+            submitters_must_vote: false,
+            late_submissions: true,
+            anonymous_submissions: false,
+            secret_ballot: false,
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for CompetitionRules {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        serde_json::to_writer(out, self)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for CompetitionRules {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        serde_json::from_str(&value)
+            .map_err(|error| format!("Unrecognized competition rules {}: {}", value, error).into())
+    }
+}
+
+// A Slack request which has been handled, or is being handled.
+// Used to answer retries with the original outcome.
+#[derive(PartialEq, Debug, Queryable)]
+pub struct SlackRequest {
+    pub request_key: String,
+    pub status_code: Option<i32>,
+    pub content_type: Option<String>,
+    pub response_body: Option<String>,
+    pub created: DateTime<Utc>,
+    pub completed: Option<DateTime<Utc>>,
+}
+
+#[derive(PartialEq, Debug, Insertable)]
+#[table_name = "slack_request_table"]
+pub struct SlackRequestInsert {
+    pub request_key: String,
+}
+
+// Used to reject replayed requests.
+#[derive(PartialEq, Debug, Insertable)]
+#[table_name = "slack_signature_table"]
+pub struct SlackSignatureInsert {
+    pub signature: String,
+    pub request_timestamp: i64,
 }
@@ -0,0 +1,232 @@
+// Who may take the privileged actions. Every privileged command is checked through
+// `is_allowed`, a new action only needs a variant and its rule.
+use crate::sotw_db::model::{Competition, Role, UserRole};
+use std::fmt;
+
+/// The user giving a command. Bot admins from the configuration and admins of
+/// the Slack workspace are admins without a stored role.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Actor {
+    pub user_id: String,
+    pub admin: bool,
+}
+
+impl Actor {
+    /// A user with only the roles stored for them
+    pub fn user(user_id: String) -> Actor {
+        Actor {
+            user_id,
+            admin: false,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Action {
+    Stop,         // Stop a competition and announce its results
+    Handover,     // Hand a competition over to a new host
+    Edit,         // Change the description or deadline of a competition, or reopen it
+    ManageRoster, // Skip someone else's turn to host, or start out of turn
+    Moderate,     // Remove or disqualify a song
+    Ban,          // Ban a user from submitting songs and voting in a channel
+    Grant(Role),  // Grant or revoke a role
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Stop => write!(f, "stop the competition"),
+            Action::Handover => write!(f, "hand the competition over"),
+            Action::Edit => write!(f, "edit the competition"),
+            Action::ManageRoster => write!(f, "manage the host roster"),
+            Action::Moderate => write!(f, "moderate the songs"),
+            Action::Ban => write!(f, "ban users"),
+            Action::Grant(role) => write!(f, "grant or revoke the {} role", role),
+        }
+    }
+}
+
+/// Where an action takes place
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Scope<'a> {
+    Channel(&'a str),
+    Competition(&'a Competition),
+}
+
+impl<'a> Scope<'a> {
+    fn channel_id(&self) -> Option<&'a str> {
+        match *self {
+            Scope::Channel(channel_id) => Some(channel_id),
+            Scope::Competition(competition) => competition.channel_id.as_deref(),
+        }
+    }
+}
+
+/// Whether the actor, with the roles granted to them, may take the action.
+/// Bot admins may do anything, channel admins anything in their channel, and
+/// the host of a competition and its co-hosts run the competition.
+pub fn is_allowed(actor: &Actor, roles: &[UserRole], action: Action, scope: Scope) -> bool {
+    let granted = |role: Role| roles.iter().filter(move |granted| granted.role == role);
+
+    let admin = actor.admin || granted(Role::BotAdmin).next().is_some();
+    let channel_admin = admin
+        || matches!(scope.channel_id(), Some(channel_id)
+            if granted(Role::ChannelAdmin).any(|r| r.channel_id.as_deref() == Some(channel_id)));
+    let host = match scope {
+        Scope::Competition(competition) => {
+            competition.user_id == actor.user_id
+                || granted(Role::CoHost).any(|r| r.competition_id == Some(competition.id))
+        }
+        Scope::Channel(_) => false,
+    };
+
+    match action {
+        Action::Stop | Action::Handover | Action::Edit | Action::Moderate | Action::Ban => {
+            channel_admin || host
+        }
+        Action::ManageRoster => channel_admin,
+        Action::Grant(Role::BotAdmin) => admin,
+        Action::Grant(Role::ChannelAdmin) => channel_admin,
+        Action::Grant(Role::CoHost) => channel_admin || host,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sotw_db::model::{Competition, Role, UserRole};
+    use crate::sotw_db::permissions::{is_allowed, Action, Actor, Scope};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn role(role: Role, channel_id: Option<&str>, competition_id: Option<Uuid>) -> UserRole {
+        UserRole {
+            id: Uuid::new_v4(),
+            user_id: "UA".to_string(),
+            role,
+            channel_id: channel_id.map(String::from),
+            competition_id,
+            granted_by: "UADMIN".to_string(),
+            granted: Utc::now(),
+        }
+    }
+
+    fn competition(host: &str) -> Competition {
+        Competition {
+            channel_id: Some("CSOTW".to_string()),
+            ..Competition::for_test(host)
+        }
+    }
+
+    #[test]
+    fn test_host_and_co_host() {
+        let actor = Actor::user("UA".to_string());
+        let own = competition("UA");
+        let other = competition("UB");
+        let co_host = vec![role(Role::CoHost, None, Some(other.id))];
+
+        assert!(is_allowed(
+            &actor,
+            &[],
+            Action::Stop,
+            Scope::Competition(&own)
+        ));
+        assert!(!is_allowed(
+            &actor,
+            &[],
+            Action::Stop,
+            Scope::Competition(&other)
+        ));
+        assert!(is_allowed(
+            &actor,
+            &co_host,
+            Action::Stop,
+            Scope::Competition(&other)
+        ));
+        assert!(is_allowed(
+            &actor,
+            &co_host,
+            Action::Moderate,
+            Scope::Competition(&other)
+        ));
+        assert!(
+            !is_allowed(&actor, &[], Action::Ban, Scope::Channel("CSOTW")),
+            "without a competition only admins ban"
+        );
+        assert!(
+            !is_allowed(
+                &actor,
+                &co_host,
+                Action::ManageRoster,
+                Scope::Channel("CSOTW")
+            ),
+            "co-hosts only run their competition"
+        );
+        assert!(is_allowed(
+            &actor,
+            &[],
+            Action::Grant(Role::CoHost),
+            Scope::Competition(&own)
+        ));
+        assert!(!is_allowed(
+            &actor,
+            &[],
+            Action::Grant(Role::ChannelAdmin),
+            Scope::Channel("CSOTW")
+        ));
+    }
+
+    #[test]
+    fn test_admins() {
+        let actor = Actor::user("UA".to_string());
+        let other = competition("UB");
+        let channel_admin = vec![role(Role::ChannelAdmin, Some("CSOTW"), None)];
+
+        assert!(is_allowed(
+            &actor,
+            &channel_admin,
+            Action::Stop,
+            Scope::Competition(&other)
+        ));
+        assert!(is_allowed(
+            &actor,
+            &channel_admin,
+            Action::ManageRoster,
+            Scope::Channel("CSOTW")
+        ));
+        assert!(
+            !is_allowed(
+                &actor,
+                &channel_admin,
+                Action::ManageRoster,
+                Scope::Channel("COTHER")
+            ),
+            "channel admins manage their own channel"
+        );
+        assert!(!is_allowed(
+            &actor,
+            &channel_admin,
+            Action::Grant(Role::BotAdmin),
+            Scope::Channel("CSOTW")
+        ));
+
+        let bot_admin = vec![role(Role::BotAdmin, None, None)];
+        let configured = Actor {
+            user_id: "UA".to_string(),
+            admin: true,
+        };
+        for (actor, roles) in &[(&actor, &bot_admin), (&configured, &vec![])] {
+            assert!(is_allowed(
+                actor,
+                roles,
+                Action::Grant(Role::BotAdmin),
+                Scope::Channel("COTHER")
+            ));
+            assert!(is_allowed(
+                actor,
+                roles,
+                Action::Stop,
+                Scope::Competition(&other)
+            ));
+        }
+    }
+}
@@ -0,0 +1,134 @@
+// Placing songs in a closed competition and adding up the season standings.
+use uuid::Uuid;
+
+/// A user's total over the closed competitions of a season
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Standing {
+    pub user_id: String,
+    pub points: i32,
+    pub wins: i32,
+}
+
+/// Final places of the songs in a ranking, best group first. The winner is
+/// placed first even if it shared the top of the ranking, songs tied with it
+/// come second. Songs in the same group share a place and the next group is
+/// placed after all of them, like 1, 2, 2, 4.
+pub fn placements(ranking: &[Vec<Uuid>], winner: Option<Uuid>) -> Vec<(Uuid, i32)> {
+    let mut placed = vec![];
+    let mut place = 1;
+    if let Some(winner) = winner {
+        placed.push((winner, place));
+        place += 1;
+    }
+
+    for group in ranking {
+        let songs: Vec<Uuid> = group
+            .iter()
+            .filter(|song_id| Some(**song_id) != winner)
+            .cloned()
+            .collect();
+        for song_id in &songs {
+            placed.push((*song_id, place));
+        }
+        place += songs.len() as i32;
+    }
+
+    placed
+}
+
+/// Points for a place, places past the end of the scheme get none
+pub fn place_points(points: &[i32], place: i32) -> i32 {
+    if place < 1 {
+        return 0;
+    }
+    points.get(place as usize - 1).cloned().unwrap_or(0)
+}
+
+/// Standings from the places of every song in a season, by points and then
+/// by wins. Users with the same points and wins keep the order they first placed in.
+pub fn standings(points: &[i32], places: &[(String, i32)]) -> Vec<Standing> {
+    let mut standings: Vec<Standing> = vec![];
+    for (user_id, place) in places {
+        let index = match standings.iter().position(|s| &s.user_id == user_id) {
+            Some(index) => index,
+            None => {
+                standings.push(Standing {
+                    user_id: user_id.clone(),
+                    points: 0,
+                    wins: 0,
+                });
+                standings.len() - 1
+            }
+        };
+        standings[index].points += place_points(points, *place);
+        if *place == 1 {
+            standings[index].wins += 1;
+        }
+    }
+
+    standings.sort_by(|a, b| b.points.cmp(&a.points).then(b.wins.cmp(&a.wins)));
+    standings
+}
+
+/// Users leading the standings, more than one if they have the same points and wins
+pub fn champions(standings: &[Standing]) -> Vec<&Standing> {
+    match standings.first() {
+        Some(first) => standings
+            .iter()
+            .take_while(|s| s.points == first.points && s.wins == first.wins)
+            .collect(),
+        None => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sotw_db::season::{champions, place_points, placements, standings};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_placements() {
+        let songs: Vec<Uuid> = (0..4).map(|_| Uuid::new_v4()).collect();
+        let ranking = vec![vec![songs[0], songs[1]], vec![songs[2]], vec![songs[3]]];
+
+        assert_eq!(
+            placements(&ranking, Some(songs[1])),
+            vec![(songs[1], 1), (songs[0], 2), (songs[2], 3), (songs[3], 4)],
+            "the winner is first even if the ranking had a tie"
+        );
+        assert_eq!(
+            placements(&ranking, None),
+            vec![(songs[0], 1), (songs[1], 1), (songs[2], 3), (songs[3], 4)],
+            "tied songs share a place"
+        );
+        assert_eq!(placements(&[], None), vec![]);
+    }
+
+    #[test]
+    fn test_standings() {
+        let points = vec![10, 7, 5];
+        assert_eq!(place_points(&points, 2), 7);
+        assert_eq!(place_points(&points, 4), 0);
+
+        let places = vec![
+            ("UA".to_string(), 1),
+            ("UB".to_string(), 2),
+            ("UC".to_string(), 4),
+            ("UB".to_string(), 1),
+            ("UA".to_string(), 2),
+            ("UC".to_string(), 3),
+        ];
+        let standings = standings(&points, &places);
+
+        let totals: Vec<(&str, i32, i32)> = standings
+            .iter()
+            .map(|s| (s.user_id.as_str(), s.points, s.wins))
+            .collect();
+        assert_eq!(totals, vec![("UA", 17, 1), ("UB", 17, 1), ("UC", 5, 0)]);
+        assert_eq!(
+            champions(&standings).len(),
+            2,
+            "same points and wins share the title"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+// Per-user statistics over past competitions: streaks, vote share and favourite service.
+use uuid::Uuid;
+
+/// Name of the service a song is shared from, like `Spotify` for
+/// `https://open.spotify.com/track/..` or `spotify:track:..`. Unknown services
+/// are named by their host.
+pub fn provider(song_uri: &str) -> String {
+    let uri = song_uri.trim().to_lowercase();
+    let host = match uri.find("://") {
+        Some(index) => uri[index + 3..]
+            .split(&['/', '?', '#', ':'][..])
+            .next()
+            .unwrap_or(""),
+        // URIs like spotify:track:.. only have a scheme
+        None => uri.split(':').next().unwrap_or(""),
+    };
+    let host = host.trim_start_matches("www.");
+
+    let known = [
+        ("spotify", "Spotify"),
+        ("spotify.com", "Spotify"),
+        ("youtube.com", "YouTube"),
+        ("youtu.be", "YouTube"),
+        ("soundcloud.com", "SoundCloud"),
+        ("bandcamp.com", "Bandcamp"),
+        ("music.apple.com", "Apple Music"),
+        ("tidal.com", "Tidal"),
+        ("deezer.com", "Deezer"),
+    ];
+    known
+        .iter()
+        .find(|(domain, _)| host == *domain || host.ends_with(&format!(".{}", domain)))
+        .map_or_else(|| host.to_string(), |(_, name)| name.to_string())
+}
+
+/// The provider most songs came from with its number of songs. A tie goes to the
+/// provider used first.
+pub fn favourite_provider(song_uris: &[&str]) -> Option<(String, i64)> {
+    let mut counts: Vec<(String, i64)> = vec![];
+    for song_uri in song_uris.iter() {
+        let name = provider(song_uri);
+        match counts.iter_mut().find(|(counted, _)| *counted == name) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((name, 1)),
+        }
+    }
+
+    counts
+        .into_iter()
+        .fold(None, |best, (name, count)| match best {
+            Some((_, best_count)) if best_count >= count => best,
+            _ => Some((name, count)),
+        })
+}
+
+/// Competitions in a row the user entered, counting back from the latest.
+/// Competitions are given latest first.
+pub fn streak(competition_ids: &[Uuid], entered: &[Uuid]) -> i64 {
+    competition_ids
+        .iter()
+        .take_while(|competition_id| entered.contains(competition_id))
+        .count() as i64
+}
+
+/// Average share of the votes as a percentage, from the user's votes and all votes
+/// of each competition. Competitions without votes are left out.
+pub fn average_share(votes: &[(i64, i64)]) -> Option<f64> {
+    let shares: Vec<f64> = votes
+        .iter()
+        .filter(|(_, total)| *total > 0)
+        .map(|(own, total)| *own as f64 * 100.0 / *total as f64)
+        .collect();
+    if shares.is_empty() {
+        return None;
+    }
+
+    Some(shares.iter().sum::<f64>() / shares.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sotw_db::stats::{average_share, favourite_provider, provider, streak};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_provider() {
+        assert_eq!(provider("https://open.spotify.com/track/1"), "Spotify");
+        assert_eq!(provider("spotify:track:1"), "Spotify");
+        assert_eq!(provider("https://www.youtube.com/watch?v=1"), "YouTube");
+        assert_eq!(provider("https://youtu.be/1"), "YouTube");
+        assert_eq!(provider("https://music.youtube.com/watch?v=1"), "YouTube");
+        assert_eq!(provider("https://artist.bandcamp.com/track/1"), "Bandcamp");
+        assert_eq!(provider("https://example.org:8080/song"), "example.org");
+        assert_eq!(
+            favourite_provider(&[
+                "https://youtu.be/1",
+                "spotify:track:1",
+                "https://youtu.be/2"
+            ]),
+            Some(("YouTube".to_string(), 2))
+        );
+        assert_eq!(favourite_provider(&[]), None);
+    }
+
+    #[test]
+    fn test_streak_and_share() {
+        let competitions: Vec<Uuid> = (0..4).map(|_| Uuid::new_v4()).collect();
+
+        assert_eq!(
+            streak(
+                &competitions,
+                &[competitions[0], competitions[1], competitions[3]]
+            ),
+            2
+        );
+        assert_eq!(streak(&competitions, &[competitions[1]]), 0);
+        assert_eq!(average_share(&[(1, 4), (3, 4), (0, 0)]), Some(50.0));
+        assert_eq!(average_share(&[(0, 0)]), None);
+    }
+}
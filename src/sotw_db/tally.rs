@@ -0,0 +1,645 @@
+use crate::sotw_db::model::VotingMethod;
+use std::cmp::Reverse;
+use uuid::Uuid;
+
+// Counting of ballots, kept apart from the database so every voting method
+// can be tested on plain data.
+
+/// A single user's ballot, songs in order of preference.
+/// `scores[i]` is the approval, score or points given to `ranking[i]`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Ballot {
+    pub ranking: Vec<Uuid>,
+    pub scores: Vec<u32>,
+}
+
+#[cfg(test)]
+impl Ballot {
+    pub fn ranked(ranking: Vec<Uuid>) -> Ballot {
+        let scores = vec![1; ranking.len()];
+        Ballot { ranking, scores }
+    }
+}
+
+/// Votes for each song still in the running, most votes first,
+/// and the songs eliminated after the round.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Round {
+    pub counts: Vec<(Uuid, u32)>,
+    pub eliminated: Vec<Uuid>,
+}
+
+/// How many ballots prefer one song over another, for every pair of songs.
+/// `preferences[i][j]` counts the ballots ranking `songs[i]` above `songs[j]`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Pairwise {
+    pub songs: Vec<Uuid>,
+    pub preferences: Vec<Vec<u32>>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Tally {
+    pub method: VotingMethod,
+    pub rounds: Vec<Round>,
+    pub pairwise: Option<Pairwise>,
+    // Every song from first to last place, songs sharing a place are grouped
+    pub ranking: Vec<Vec<Uuid>>,
+    // Empty when no votes were cast, more than one is a tie
+    pub winners: Vec<Uuid>,
+}
+
+/// The audience and the panel of judges combined, each deciding its share of the
+/// result. Points are given per song, in the order the songs were submitted.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Combined {
+    // Percentage of the result decided by the panel
+    pub panel_weight: u32,
+    pub audience: Vec<(Uuid, u32)>,
+    pub panel: Vec<(Uuid, u32)>,
+    pub ranking: Vec<Vec<Uuid>>,
+    pub winners: Vec<Uuid>,
+}
+
+impl Combined {
+    /// Percentages of the audience points, the panel points and the combined result a song got
+    pub fn shares(&self, song_id: &Uuid) -> (f64, f64, f64) {
+        let audience = share(&self.audience, song_id);
+        let panel = share(&self.panel, song_id);
+        let weight = f64::from(self.panel_weight) / 100.0;
+
+        (audience, panel, audience * (1.0 - weight) + panel * weight)
+    }
+}
+
+fn share(points: &[(Uuid, u32)], song_id: &Uuid) -> f64 {
+    let total: u32 = points.iter().map(|(_, points)| points).sum();
+    match points.iter().find(|(id, _)| id == song_id) {
+        Some((_, points)) if total > 0 => f64::from(*points) * 100.0 / f64::from(total),
+        _ => 0.0,
+    }
+}
+
+/// Points of every song in a tally, so tallies of different ballots can be combined.
+/// These are the votes or totals of the first round, first preferences under
+/// instant runoff, and the number of songs beaten head to head under Schulze.
+pub fn points(songs: &[Uuid], tally: &Tally) -> Vec<(Uuid, u32)> {
+    songs
+        .iter()
+        .map(|song_id| {
+            let points =
+                match (&tally.pairwise, tally.rounds.first()) {
+                    (Some(pairwise), _) => pairwise
+                        .songs
+                        .iter()
+                        .position(|id| id == song_id)
+                        .map_or(0, |i| {
+                            let beaten = (0..pairwise.songs.len()).filter(|&j| {
+                                pairwise.preferences[i][j] > pairwise.preferences[j][i]
+                            });
+                            beaten.count() as u32
+                        }),
+                    (None, Some(round)) => round
+                        .counts
+                        .iter()
+                        .find(|(id, _)| id == song_id)
+                        .map_or(0, |(_, count)| *count),
+                    (None, None) => 0,
+                };
+            (*song_id, points)
+        })
+        .collect()
+}
+
+/// Combine the points of the audience and the panel, both as a share of their
+/// total points, with the panel deciding `panel_weight` percent of the result
+pub fn combine(
+    songs: &[Uuid],
+    audience: Vec<(Uuid, u32)>,
+    panel: Vec<(Uuid, u32)>,
+    panel_weight: u32,
+) -> Combined {
+    let panel_weight = panel_weight.min(100);
+    let points_of = |points: &[(Uuid, u32)], song_id: &Uuid| {
+        points
+            .iter()
+            .find(|(id, _)| id == song_id)
+            .map_or(0, |(_, points)| u64::from(*points))
+    };
+    let audience_total: u64 = audience.iter().map(|(_, points)| u64::from(*points)).sum();
+    let panel_total: u64 = panel.iter().map(|(_, points)| u64::from(*points)).sum();
+
+    // Compared over a common denominator, so equal shares are an exact tie
+    let mut combined: Vec<(Uuid, u64)> = songs
+        .iter()
+        .map(|song_id| {
+            let from_audience =
+                u64::from(100 - panel_weight) * points_of(&audience, song_id) * panel_total.max(1);
+            let from_panel =
+                u64::from(panel_weight) * points_of(&panel, song_id) * audience_total.max(1);
+            (*song_id, from_audience + from_panel)
+        })
+        .collect();
+    combined.sort_by_key(|(_, result)| Reverse(*result));
+
+    let ranking = places(&combined);
+    let winners = match (combined.first(), ranking.first()) {
+        (Some(&(_, best)), Some(first)) if best > 0 => first.clone(),
+        _ => vec![],
+    };
+
+    Combined {
+        panel_weight,
+        audience,
+        panel,
+        ranking,
+        winners,
+    }
+}
+
+/// Count ballots for the given songs, in the order the songs were submitted
+pub fn tally(method: VotingMethod, songs: &[Uuid], ballots: &[Ballot]) -> Tally {
+    match method {
+        VotingMethod::Plurality => plurality(songs, ballots),
+        VotingMethod::InstantRunoff => instant_runoff(songs, ballots),
+        VotingMethod::Schulze => schulze(songs, ballots),
+        VotingMethod::Approval | VotingMethod::Score | VotingMethod::Points => {
+            totals(method, songs, ballots)
+        }
+    }
+}
+
+fn plurality(songs: &[Uuid], ballots: &[Ballot]) -> Tally {
+    single_round(VotingMethod::Plurality, first_preferences(songs, ballots))
+}
+
+/// Approvals, scores and points are all added up per song
+fn totals(method: VotingMethod, songs: &[Uuid], ballots: &[Ballot]) -> Tally {
+    let mut counts: Vec<(Uuid, u32)> = songs.iter().map(|song_id| (*song_id, 0)).collect();
+
+    for ballot in ballots {
+        for (choice, score) in ballot.ranking.iter().zip(&ballot.scores) {
+            if let Some(index) = counts.iter().position(|(song_id, _)| song_id == choice) {
+                counts[index].1 += score;
+            }
+        }
+    }
+
+    counts.sort_by_key(|(_, count)| Reverse(*count));
+    single_round(method, counts)
+}
+
+fn single_round(method: VotingMethod, counts: Vec<(Uuid, u32)>) -> Tally {
+    let winners = leaders(&counts);
+
+    Tally {
+        method,
+        ranking: places(&counts),
+        rounds: vec![Round {
+            counts,
+            eliminated: vec![],
+        }],
+        pairwise: None,
+        winners,
+    }
+}
+
+/// Songs with the fewest first preferences are eliminated together, and their
+/// ballots move on to the next preference, until a song has a majority of the
+/// ballots still in play. Songs left level with each other are a tie.
+fn instant_runoff(songs: &[Uuid], ballots: &[Ballot]) -> Tally {
+    let mut remaining = songs.to_vec();
+    let mut rounds = Vec::new();
+
+    let winners = loop {
+        let counts = first_preferences(&remaining, ballots);
+        let total: u32 = counts.iter().map(|(_, count)| count).sum();
+
+        let (leader, most) = match counts.first() {
+            Some(&(leader, most)) if total > 0 => (leader, most),
+            _ => {
+                rounds.push(Round {
+                    counts,
+                    eliminated: vec![],
+                });
+                break vec![];
+            }
+        };
+
+        if most * 2 > total {
+            rounds.push(Round {
+                counts,
+                eliminated: vec![],
+            });
+            break vec![leader];
+        }
+
+        let fewest = counts.last().map_or(0, |(_, count)| *count);
+        let eliminated: Vec<Uuid> = counts
+            .iter()
+            .filter(|(_, count)| *count == fewest)
+            .map(|(song_id, _)| *song_id)
+            .collect();
+
+        if eliminated.len() == remaining.len() {
+            rounds.push(Round {
+                counts,
+                eliminated: vec![],
+            });
+            break remaining;
+        }
+
+        remaining.retain(|song_id| !eliminated.contains(song_id));
+        rounds.push(Round { counts, eliminated });
+    };
+
+    // Songs in the final round by their count, then the rest by how long they lasted
+    let mut ranking = rounds
+        .last()
+        .map_or_else(Vec::new, |round| places(&round.counts));
+    ranking.extend(
+        rounds
+            .iter()
+            .rev()
+            .filter(|round| !round.eliminated.is_empty())
+            .map(|round| round.eliminated.clone()),
+    );
+
+    Tally {
+        method: VotingMethod::InstantRunoff,
+        rounds,
+        pairwise: None,
+        ranking,
+        winners,
+    }
+}
+
+/// Every pair of songs is compared on the ballots, a song being preferred over
+/// every song ranked below it and every song left off the ballot. The strength of
+/// the strongest path of pairwise wins between two songs decides which is placed
+/// higher, which gives a full ranking even when preferences are cyclic.
+fn schulze(songs: &[Uuid], ballots: &[Ballot]) -> Tally {
+    let preferences = pairwise_preferences(songs, ballots);
+    let n = songs.len();
+
+    let mut strength = vec![vec![0; n]; n];
+    for i in 0..n {
+        for j in 0..n {
+            if i != j && preferences[i][j] > preferences[j][i] {
+                strength[i][j] = preferences[i][j];
+            }
+        }
+    }
+    for k in 0..n {
+        for i in 0..n {
+            for j in 0..n {
+                if i != j && i != k && j != k {
+                    strength[i][j] = strength[i][j].max(strength[i][k].min(strength[k][j]));
+                }
+            }
+        }
+    }
+
+    // Beating a song on the strongest path is transitive, so songs can be placed
+    // by how many others they beat
+    let mut beaten: Vec<(Uuid, u32)> = songs
+        .iter()
+        .enumerate()
+        .map(|(i, song_id)| {
+            let beats = (0..n).filter(|&j| strength[i][j] > strength[j][i]).count();
+            (*song_id, beats as u32)
+        })
+        .collect();
+    beaten.sort_by_key(|(_, beats)| Reverse(*beats));
+
+    let ranking = places(&beaten);
+    let voted = ballots.iter().any(|ballot| !ballot.ranking.is_empty());
+    let winners = match ranking.first() {
+        Some(first) if voted => first.clone(),
+        _ => vec![],
+    };
+
+    Tally {
+        method: VotingMethod::Schulze,
+        rounds: vec![],
+        pairwise: Some(Pairwise {
+            songs: songs.to_vec(),
+            preferences,
+        }),
+        ranking,
+        winners,
+    }
+}
+
+fn pairwise_preferences(songs: &[Uuid], ballots: &[Ballot]) -> Vec<Vec<u32>> {
+    let n = songs.len();
+    let mut preferences = vec![vec![0; n]; n];
+
+    for ballot in ballots {
+        // Songs left off the ballot share the last place
+        let place = |song_id: &Uuid| {
+            ballot
+                .ranking
+                .iter()
+                .position(|choice| choice == song_id)
+                .unwrap_or(usize::MAX)
+        };
+        let places: Vec<usize> = songs.iter().map(place).collect();
+
+        for i in 0..n {
+            for j in 0..n {
+                if places[i] < places[j] {
+                    preferences[i][j] += 1;
+                }
+            }
+        }
+    }
+
+    preferences
+}
+
+/// Each ballot counts for its highest ranked song among `songs`
+fn first_preferences(songs: &[Uuid], ballots: &[Ballot]) -> Vec<(Uuid, u32)> {
+    let mut counts: Vec<(Uuid, u32)> = songs.iter().map(|song_id| (*song_id, 0)).collect();
+
+    for ballot in ballots {
+        let preference = ballot
+            .ranking
+            .iter()
+            .find_map(|choice| counts.iter().position(|(song_id, _)| song_id == choice));
+        if let Some(index) = preference {
+            counts[index].1 += 1;
+        }
+    }
+
+    // Stable, so songs with equal counts keep their submission order
+    counts.sort_by_key(|(_, count)| Reverse(*count));
+    counts
+}
+
+/// Draw one of the tied songs, given in submission order. The seed is mixed with
+/// splitmix64, so anyone with the published seed can repeat the draw.
+pub fn seeded_draw(seed: u64, tied: &[Uuid]) -> Option<Uuid> {
+    if tied.is_empty() {
+        return None;
+    }
+
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+
+    Some(tied[(z % tied.len() as u64) as usize])
+}
+
+/// Group songs, sorted on their count, into places
+fn places<T: PartialEq + Copy>(counts: &[(Uuid, T)]) -> Vec<Vec<Uuid>> {
+    let mut places: Vec<(T, Vec<Uuid>)> = Vec::new();
+    for (song_id, count) in counts {
+        match places.last_mut() {
+            Some((place_count, place)) if place_count == count => place.push(*song_id),
+            _ => places.push((*count, vec![*song_id])),
+        }
+    }
+
+    places.into_iter().map(|(_, place)| place).collect()
+}
+
+fn leaders(counts: &[(Uuid, u32)]) -> Vec<Uuid> {
+    match counts.first() {
+        Some(&(_, most)) if most > 0 => counts
+            .iter()
+            .filter(|(_, count)| *count == most)
+            .map(|(song_id, _)| *song_id)
+            .collect(),
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sotw_db::model::VotingMethod;
+    use crate::sotw_db::tally::{combine, points, seeded_draw, tally, Ballot};
+    use uuid::Uuid;
+
+    fn songs(n: usize) -> Vec<Uuid> {
+        (0..n).map(|_| Uuid::new_v4()).collect()
+    }
+
+    fn ballots(songs: &[Uuid], rankings: &[(usize, &[usize])]) -> Vec<Ballot> {
+        rankings
+            .iter()
+            .flat_map(|(voters, ranking)| {
+                let ballot = Ballot::ranked(ranking.iter().map(|i| songs[*i]).collect());
+                vec![ballot; *voters]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_plurality() {
+        let songs = songs(3);
+        let ballots = ballots(&songs, &[(2, &[1]), (1, &[0]), (2, &[2])]);
+
+        let result = tally(VotingMethod::Plurality, &songs, &ballots);
+
+        assert_eq!(
+            result.winners,
+            vec![songs[1], songs[2]],
+            "songs level on most votes should tie"
+        );
+        assert_eq!(result.rounds[0].counts[2], (songs[0], 1));
+    }
+
+    #[test]
+    fn test_instant_runoff_majority() {
+        let songs = songs(3);
+        let ballots = ballots(&songs, &[(3, &[0, 1]), (1, &[1]), (1, &[2, 1])]);
+
+        let result = tally(VotingMethod::InstantRunoff, &songs, &ballots);
+
+        assert_eq!(result.winners, vec![songs[0]]);
+        assert_eq!(
+            result.rounds.len(),
+            1,
+            "a first round majority should not need eliminations"
+        );
+    }
+
+    #[test]
+    fn test_instant_runoff_eliminations() {
+        let songs = songs(4);
+        // Song 0 leads on first preferences, but song 1 wins once 2 and 3 are transferred
+        let ballots = ballots(&songs, &[(6, &[0]), (5, &[1]), (3, &[2, 1]), (1, &[3, 2])]);
+
+        let result = tally(VotingMethod::InstantRunoff, &songs, &ballots);
+
+        assert_eq!(result.winners, vec![songs[1]]);
+        assert_eq!(
+            result
+                .rounds
+                .iter()
+                .map(|round| round.eliminated.clone())
+                .collect::<Vec<_>>(),
+            vec![vec![songs[3]], vec![songs[2]], vec![]],
+            "the last placed song should be eliminated each round"
+        );
+        assert_eq!(result.rounds[2].counts, vec![(songs[1], 8), (songs[0], 6)]);
+    }
+
+    #[test]
+    fn test_instant_runoff_tie_and_no_votes() {
+        let songs = songs(2);
+        let tied = tally(
+            VotingMethod::InstantRunoff,
+            &songs,
+            &ballots(&songs, &[(1, &[0]), (1, &[1])]),
+        );
+        let empty = tally(VotingMethod::InstantRunoff, &songs, &[]);
+
+        assert_eq!(tied.winners, songs, "level songs should all be winners");
+        assert!(empty.winners.is_empty(), "no votes should have no winner");
+    }
+
+    #[test]
+    fn test_schulze() {
+        // The example from Markus Schulze's paper, 45 voters and 5 candidates A-E
+        let songs = songs(5);
+        let (a, b, c, d, e) = (0, 1, 2, 3, 4);
+        let ballots = ballots(
+            &songs,
+            &[
+                (5, &[a, c, b, e, d]),
+                (5, &[a, d, e, c, b]),
+                (8, &[b, e, d, a, c]),
+                (3, &[c, a, b, e, d]),
+                (7, &[c, a, e, b, d]),
+                (2, &[c, b, a, d, e]),
+                (7, &[d, c, e, b, a]),
+                (8, &[e, b, a, d, c]),
+            ],
+        );
+
+        let result = tally(VotingMethod::Schulze, &songs, &ballots);
+        let pairwise = result.pairwise.unwrap();
+
+        assert_eq!(
+            (pairwise.preferences[a][b], pairwise.preferences[b][a]),
+            (20, 25)
+        );
+        assert_eq!(
+            result.ranking,
+            vec![
+                vec![songs[e]],
+                vec![songs[a]],
+                vec![songs[c]],
+                vec![songs[b]],
+                vec![songs[d]]
+            ],
+            "ranking should follow the strongest paths"
+        );
+        assert_eq!(result.winners, vec![songs[e]]);
+    }
+
+    #[test]
+    fn test_schulze_unranked_songs() {
+        let songs = songs(3);
+        // Songs left off a ballot are placed below the ones on it
+        let ballots = ballots(&songs, &[(2, &[2]), (1, &[0, 1])]);
+
+        let result = tally(VotingMethod::Schulze, &songs, &ballots);
+
+        assert_eq!(
+            result.ranking,
+            vec![vec![songs[2]], vec![songs[0]], vec![songs[1]]]
+        );
+        assert!(tally(VotingMethod::Schulze, &songs, &[]).winners.is_empty());
+    }
+
+    #[test]
+    fn test_totals() {
+        let songs = songs(3);
+        let scored = |scores: &[(usize, u32)]| Ballot {
+            ranking: scores.iter().map(|(i, _)| songs[*i]).collect(),
+            scores: scores.iter().map(|(_, score)| *score).collect(),
+        };
+        let scores = vec![
+            scored(&[(0, 5), (1, 3)]),
+            scored(&[(1, 4), (2, 0)]),
+            scored(&[(2, 5)]),
+        ];
+
+        let result = tally(VotingMethod::Score, &songs, &scores);
+        let approvals = tally(
+            VotingMethod::Approval,
+            &songs,
+            &ballots(&songs, &[(2, &[0, 2]), (1, &[1, 2])]),
+        );
+
+        assert_eq!(
+            result.rounds[0].counts,
+            vec![(songs[1], 7), (songs[0], 5), (songs[2], 5)]
+        );
+        assert_eq!(result.winners, vec![songs[1]]);
+        assert_eq!(
+            result.ranking,
+            vec![vec![songs[1]], vec![songs[0], songs[2]]],
+            "songs with the same total share a place"
+        );
+        assert_eq!(approvals.winners, vec![songs[2]]);
+    }
+
+    #[test]
+    fn test_combine() {
+        let songs = songs(3);
+        let audience = tally(
+            VotingMethod::Plurality,
+            &songs,
+            &ballots(&songs, &[(3, &[0]), (1, &[1])]),
+        );
+        let schulze = tally(
+            VotingMethod::Schulze,
+            &songs,
+            &ballots(&songs, &[(1, &[1, 2, 0])]),
+        );
+        let panel = vec![(songs[0], 2), (songs[1], 6), (songs[2], 0)];
+
+        let even = combine(&songs, points(&songs, &audience), panel.clone(), 50);
+        let audience_only = combine(&songs, points(&songs, &audience), panel.clone(), 0);
+        let no_votes = combine(&songs, points(&songs, &audience), vec![], 100);
+
+        assert_eq!(
+            points(&songs, &schulze),
+            vec![(songs[0], 0), (songs[1], 2), (songs[2], 1)],
+            "Schulze points are the songs beaten head to head"
+        );
+        // Audience shares are 75% and 25%, panel shares 25% and 75%
+        assert_eq!(even.winners, vec![songs[0], songs[1]]);
+        assert_eq!(even.shares(&songs[1]), (25.0, 75.0, 50.0));
+        assert_eq!(even.ranking, vec![vec![songs[0], songs[1]], vec![songs[2]]]);
+        assert_eq!(audience_only.winners, vec![songs[0]]);
+        assert!(
+            no_votes.winners.is_empty(),
+            "a panel without votes should have no winner"
+        );
+    }
+
+    #[test]
+    fn test_seeded_draw() {
+        let songs = songs(3);
+
+        let draws: Vec<Uuid> = (0..30)
+            .map(|seed| seeded_draw(seed, &songs).unwrap())
+            .collect();
+
+        assert_eq!(
+            seeded_draw(7, &songs),
+            seeded_draw(7, &songs),
+            "the same seed should draw the same song"
+        );
+        assert!(
+            songs.iter().all(|song_id| draws.contains(song_id)),
+            "every tied song should be drawn by some seed"
+        );
+        assert_eq!(seeded_draw(7, &[]), None);
+    }
+}
@@ -0,0 +1,51 @@
+use crate::config::{LogFormat, TelemetryConfig};
+use crate::telemetry::otlp::OtlpHandle;
+use crate::telemetry::redact::{set_log_sensitive, RedactingMakeWriter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Registry};
+
+pub mod otlp;
+pub mod redact;
+
+/// Install the global subscriber: structured logs on stdout and, when an
+/// endpoint is configured, span export to an OTLP collector.
+/// Records from crates using `log` (actix, diesel) are forwarded as well.
+/// Secrets and user content are redacted unless `log_sensitive` is set.
+pub fn init(log_level: &str, telemetry: &TelemetryConfig) -> Option<OtlpHandle> {
+    set_log_sensitive(telemetry.log_sensitive);
+
+    let (otlp_layer, otlp_handle) = match &telemetry.otlp_endpoint {
+        Some(endpoint) => {
+            let (layer, handle) = otlp::layer(endpoint, &telemetry.service_name);
+            (Some(layer), Some(handle))
+        }
+        None => (None, None),
+    };
+
+    let (json_layer, text_layer) = match telemetry.log_format {
+        LogFormat::Json => (
+            Some(
+                fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(true)
+                    .with_writer(RedactingMakeWriter(std::io::stdout)),
+            ),
+            None,
+        ),
+        LogFormat::Text => (
+            None,
+            Some(fmt::layer().with_writer(RedactingMakeWriter(std::io::stdout))),
+        ),
+    };
+
+    Registry::default()
+        .with(EnvFilter::new(log_level))
+        .with(json_layer)
+        .with(text_layer)
+        .with(otlp_layer)
+        .init();
+
+    otlp_handle
+}
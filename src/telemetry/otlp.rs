@@ -0,0 +1,513 @@
+use crate::metrics::DROPPED_SPANS_TOTAL;
+use crate::telemetry::redact::{is_sensitive_field, log_sensitive, redact};
+use serde_json::{json, Value};
+use std::fmt::Debug;
+use std::sync::mpsc::{
+    channel, sync_channel, Receiver, RecvTimeoutError, Sender, SyncSender, TrySendError,
+};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+use uuid::Uuid;
+
+// Minimal OTLP exporter sending finished spans as OTLP/HTTP JSON.
+// Spans are batched on a background thread so request handling never waits for the collector.
+// While the collector is slow or down the queue fills up and further spans are dropped.
+
+static MAX_BATCH_SIZE: usize = 256;
+static MAX_QUEUED_SPANS: usize = 4096;
+static FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+static FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+// OTLP span kinds
+static SPAN_KIND_INTERNAL: u8 = 1;
+static SPAN_KIND_SERVER: u8 = 2;
+static SPAN_KIND_CLIENT: u8 = 3;
+
+enum Message {
+    Span(Value),
+    Flush(Sender<()>),
+}
+
+/// Tracing layer turning closed spans into OTLP spans
+pub struct OtlpLayer {
+    sender: Mutex<SyncSender<Message>>,
+}
+
+/// Handle used to push out buffered spans, e.g. before shutdown
+#[derive(Clone)]
+pub struct OtlpHandle {
+    sender: SyncSender<Message>,
+}
+
+// Per span bookkeeping kept in the span extensions until the span closes
+struct SpanData {
+    trace_id: String,
+    span_id: String,
+    parent_span_id: Option<String>,
+    kind: u8,
+    start: SystemTime,
+    attributes: Vec<Value>,
+}
+
+/// Create the layer and start the exporter thread posting to `{endpoint}/v1/traces`
+pub fn layer(endpoint: &str, service_name: &str) -> (OtlpLayer, OtlpHandle) {
+    let (sender, receiver) = sync_channel(MAX_QUEUED_SPANS);
+    let url = format!("{}/v1/traces", endpoint.trim_end_matches('/'));
+    let service_name = service_name.to_string();
+
+    thread::Builder::new()
+        .name("otlp-exporter".to_string())
+        .spawn(move || export_loop(receiver, url, service_name))
+        .expect("Unable to start otlp exporter thread!");
+
+    (
+        OtlpLayer {
+            sender: Mutex::new(sender.clone()),
+        },
+        OtlpHandle { sender },
+    )
+}
+
+impl OtlpHandle {
+    /// Export everything buffered so far and wait for the collector to answer
+    pub fn flush(&self) {
+        let (ack_sender, ack_receiver) = channel();
+        if self.sender.send(Message::Flush(ack_sender)).is_ok() {
+            let _ = ack_receiver.recv_timeout(FLUSH_TIMEOUT);
+        }
+    }
+}
+
+fn export_loop(receiver: Receiver<Message>, url: String, service_name: String) {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(FLUSH_TIMEOUT)
+        .build()
+        .expect("Unable to create reqwest client for otlp export!");
+
+    let mut batch: Vec<Value> = Vec::new();
+    let mut last_export = Instant::now();
+
+    loop {
+        match receiver.recv_timeout(FLUSH_INTERVAL) {
+            Ok(Message::Span(span)) => {
+                batch.push(span);
+                if batch.len() >= MAX_BATCH_SIZE || last_export.elapsed() >= FLUSH_INTERVAL {
+                    export(&client, &url, &service_name, &mut batch);
+                    last_export = Instant::now();
+                }
+            }
+            Ok(Message::Flush(ack)) => {
+                export(&client, &url, &service_name, &mut batch);
+                last_export = Instant::now();
+                let _ = ack.send(());
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                export(&client, &url, &service_name, &mut batch);
+                last_export = Instant::now();
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                export(&client, &url, &service_name, &mut batch);
+                return;
+            }
+        }
+    }
+}
+
+fn export(
+    client: &reqwest::blocking::Client,
+    url: &str,
+    service_name: &str,
+    batch: &mut Vec<Value>,
+) {
+    if batch.is_empty() {
+        return;
+    }
+
+    let spans = std::mem::take(batch);
+    let payload = json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [attribute("service.name", json!({ "stringValue": service_name }))]
+            },
+            "scopeSpans": [{
+                "scope": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans
+            }]
+        }]
+    });
+
+    // Logging here would create new events while exporting, so just write to stderr
+    if let Err(e) = client
+        .post(url)
+        .json(&payload)
+        .send()
+        .and_then(|r| r.error_for_status())
+    {
+        eprintln!("Unable to export spans to otlp collector err={}", e);
+    }
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    json!({ "key": key, "value": value })
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn new_trace_id() -> String {
+    Uuid::new_v4().to_simple().to_string()
+}
+
+fn new_span_id() -> String {
+    Uuid::new_v4().to_simple().to_string()[..16].to_string()
+}
+
+// Collects span fields as OTLP attributes, `otel.kind` selects the span kind
+struct AttributeVisitor<'a> {
+    attributes: &'a mut Vec<Value>,
+    kind: &'a mut u8,
+}
+
+impl<'a> Visit for AttributeVisitor<'a> {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.attributes.push(attribute(
+            field.name(),
+            json!({ "intValue": value.to_string() }),
+        ));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.attributes.push(attribute(
+            field.name(),
+            json!({ "intValue": value.to_string() }),
+        ));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.attributes
+            .push(attribute(field.name(), json!({ "boolValue": value })));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "otel.kind" {
+            *self.kind = match value {
+                "server" => SPAN_KIND_SERVER,
+                "client" => SPAN_KIND_CLIENT,
+                _ => SPAN_KIND_INTERNAL,
+            };
+            return;
+        }
+
+        let value = if is_sensitive_field(field.name()) && !log_sensitive() {
+            "[redacted]".into()
+        } else {
+            redact(value)
+        };
+        self.attributes
+            .push(attribute(field.name(), json!({ "stringValue": value })));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.record_str(field, &format!("{:?}", value));
+    }
+}
+
+impl<S> Layer<S> for OtlpLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let span = match ctx.span(id) {
+            Some(span) => span,
+            None => return,
+        };
+
+        let parent = span.parent().and_then(|parent| {
+            parent
+                .extensions()
+                .get::<SpanData>()
+                .map(|data| (data.trace_id.clone(), data.span_id.clone()))
+        });
+
+        let mut data = SpanData {
+            trace_id: parent
+                .as_ref()
+                .map_or_else(new_trace_id, |(trace_id, _)| trace_id.clone()),
+            span_id: new_span_id(),
+            parent_span_id: parent.map(|(_, span_id)| span_id),
+            kind: SPAN_KIND_INTERNAL,
+            start: SystemTime::now(),
+            attributes: Vec::new(),
+        };
+
+        attrs.record(&mut AttributeVisitor {
+            attributes: &mut data.attributes,
+            kind: &mut data.kind,
+        });
+
+        span.extensions_mut().insert(data);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(data) = span.extensions_mut().get_mut::<SpanData>() {
+                values.record(&mut AttributeVisitor {
+                    attributes: &mut data.attributes,
+                    kind: &mut data.kind,
+                });
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let span = match ctx.span(&id) {
+            Some(span) => span,
+            None => return,
+        };
+        let data = match span.extensions_mut().remove::<SpanData>() {
+            Some(data) => data,
+            None => return,
+        };
+
+        let otlp_span = json!({
+            "traceId": data.trace_id,
+            "spanId": data.span_id,
+            "parentSpanId": data.parent_span_id.unwrap_or_default(),
+            "name": span.name(),
+            "kind": data.kind,
+            "startTimeUnixNano": unix_nanos(data.start),
+            "endTimeUnixNano": unix_nanos(SystemTime::now()),
+            "attributes": data.attributes,
+        });
+
+        if let Ok(sender) = self.sender.lock() {
+            if let Err(TrySendError::Full(_)) = sender.try_send(Message::Span(otlp_span)) {
+                DROPPED_SPANS_TOTAL.inc();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::telemetry::otlp::layer;
+    use serde_json::Value;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use std::time::Duration;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::Registry;
+
+    // Stand-in for an otlp collector, accepts a single export and hands back the body
+    fn collector() -> (String, std::sync::mpsc::Receiver<(String, Value)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = channel();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .unwrap();
+
+            sender
+                .send((request_line, serde_json::from_slice(&body).unwrap()))
+                .unwrap();
+        });
+
+        (endpoint, receiver)
+    }
+
+    #[test]
+    fn test_export_to_collector() {
+        let (endpoint, exports) = collector();
+        let (otlp_layer, handle) = layer(&endpoint, "sotw-test");
+        let subscriber = Registry::default().with(otlp_layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let request = info_span!("request", otel.kind = "server", team = "T1", user = "U1");
+            let _request = request.enter();
+            let db = info_span!("db", operation = "save_song");
+            let _db = db.enter();
+        });
+        handle.flush();
+
+        let (request_line, payload) = exports.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(request_line.starts_with("POST /v1/traces "));
+
+        let resource = &payload["resourceSpans"][0];
+        assert_eq!(
+            resource["resource"]["attributes"][0]["value"]["stringValue"],
+            "sotw-test"
+        );
+
+        let spans = resource["scopeSpans"][0]["spans"].as_array().unwrap();
+        let find = |name: &str| spans.iter().find(|s| s["name"] == name).unwrap();
+        let request = find("request");
+        let db = find("db");
+
+        assert_eq!(request["kind"], 2, "otel.kind should set the span kind");
+        assert_eq!(
+            db["traceId"], request["traceId"],
+            "child spans should share the trace"
+        );
+        assert_eq!(db["parentSpanId"], request["spanId"]);
+        assert!(request["attributes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|a| a["key"] == "team" && a["value"]["stringValue"] == "T1"));
+    }
+
+    // Fields of the messages in ExportTraceServiceRequest, as the OTLP/HTTP JSON
+    // encoding names them
+    const RESOURCE_SPANS_FIELDS: &[&str] = &["resource", "scopeSpans", "schemaUrl"];
+    const RESOURCE_FIELDS: &[&str] = &["attributes", "droppedAttributesCount"];
+    const SCOPE_SPANS_FIELDS: &[&str] = &["scope", "spans", "schemaUrl"];
+    const SCOPE_FIELDS: &[&str] = &["name", "version", "attributes", "droppedAttributesCount"];
+    const SPAN_FIELDS: &[&str] = &[
+        "traceId",
+        "spanId",
+        "traceState",
+        "parentSpanId",
+        "flags",
+        "name",
+        "kind",
+        "startTimeUnixNano",
+        "endTimeUnixNano",
+        "attributes",
+        "droppedAttributesCount",
+        "events",
+        "droppedEventsCount",
+        "links",
+        "droppedLinksCount",
+        "status",
+    ];
+
+    fn assert_fields(message: &Value, fields: &[&str]) {
+        for key in message.as_object().unwrap().keys() {
+            assert!(fields.contains(&key.as_str()), "unknown field {}", key);
+        }
+    }
+
+    fn assert_hex_id(id: &Value, length: usize) {
+        let id = id.as_str().unwrap();
+        assert_eq!(
+            id.len(),
+            length,
+            "id {} should be {} hex digits",
+            id,
+            length
+        );
+        assert!(id
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+        assert!(id.chars().any(|c| c != '0'), "all zero ids are invalid");
+    }
+
+    // fixed64 fields are encoded as decimal strings
+    fn unix_nanos(time: &Value) -> u128 {
+        time.as_str().unwrap().parse().unwrap()
+    }
+
+    fn assert_attributes(attributes: &Value) {
+        for attribute in attributes.as_array().unwrap() {
+            assert_fields(attribute, &["key", "value"]);
+            assert!(attribute["key"].is_string());
+
+            let value = attribute["value"].as_object().unwrap();
+            assert_eq!(value.len(), 1, "an AnyValue holds exactly one value");
+            let (kind, value) = value.iter().next().unwrap();
+            match kind.as_str() {
+                "stringValue" => assert!(value.is_string()),
+                "boolValue" => assert!(value.is_boolean()),
+                "intValue" => assert!(value.as_str().unwrap().parse::<i64>().is_ok()),
+                "doubleValue" => assert!(value.is_number()),
+                other => panic!("unexpected value kind {}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_export_trace_service_request() {
+        let (endpoint, exports) = collector();
+        let (otlp_layer, handle) = layer(&endpoint, "sotw-test");
+        let subscriber = Registry::default().with(otlp_layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let request = info_span!("request", otel.kind = "server", retry = false, count = 3);
+            let _request = request.enter();
+            let api = info_span!("api", otel.kind = "client", offset = -1, method = ?"GET");
+            let _api = api.enter();
+        });
+        handle.flush();
+
+        let (_, payload) = exports.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_fields(&payload, &["resourceSpans"]);
+
+        let resource_spans = payload["resourceSpans"].as_array().unwrap();
+        assert_eq!(resource_spans.len(), 1);
+        let resource_spans = &resource_spans[0];
+        assert_fields(resource_spans, RESOURCE_SPANS_FIELDS);
+        assert_fields(&resource_spans["resource"], RESOURCE_FIELDS);
+        assert_attributes(&resource_spans["resource"]["attributes"]);
+
+        let scope_spans = &resource_spans["scopeSpans"][0];
+        assert_fields(scope_spans, SCOPE_SPANS_FIELDS);
+        assert_fields(&scope_spans["scope"], SCOPE_FIELDS);
+        assert!(scope_spans["scope"]["name"].is_string());
+        assert!(scope_spans["scope"]["version"].is_string());
+
+        let spans = scope_spans["spans"].as_array().unwrap();
+        assert_eq!(spans.len(), 2);
+        for span in spans {
+            assert_fields(span, SPAN_FIELDS);
+            assert!(span["name"].is_string());
+            assert_hex_id(&span["traceId"], 32);
+            assert_hex_id(&span["spanId"], 16);
+            match &span["parentSpanId"] {
+                Value::Null => {}
+                Value::String(id) if id.is_empty() => {}
+                parent => assert_hex_id(parent, 16),
+            }
+            let kind = span["kind"].as_u64().unwrap();
+            assert!((1..=5).contains(&kind), "kind {} is not a SpanKind", kind);
+            assert!(unix_nanos(&span["startTimeUnixNano"]) > 0);
+            assert!(unix_nanos(&span["endTimeUnixNano"]) >= unix_nanos(&span["startTimeUnixNano"]));
+            assert_attributes(&span["attributes"]);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use regex::Regex;
+use std::borrow::Cow;
+use std::fmt::{self, Debug, Display};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing_subscriber::fmt::MakeWriter;
+
+// Secrets and user content are kept out of logs and exported spans.
+// `Redacted` hides values logged on purpose, the writer below scrubs anything
+// that slips through. Both can be turned off with the debug-only LOG_SENSITIVE.
+
+static REDACTED: &str = "[redacted]";
+
+static LOG_SENSITIVE: AtomicBool = AtomicBool::new(false);
+
+/// Field names which never have their values logged
+pub static SENSITIVE_FIELDS: &[&str] = &[
+    "token",
+    "signature",
+    "slack_signature",
+    "response_url",
+    "text",
+    "body",
+];
+
+lazy_static! {
+    static ref SENSITIVE_PATTERNS: Vec<(Regex, &'static str)> = vec![
+        // Sensitive fields as written by the json and text formatters
+        (
+            Regex::new(r#""(token|signature|slack_signature|response_url|text|body)":"(?:[^"\\]|\\.)*""#)
+                .unwrap(),
+            r#""$1":"[redacted]""#,
+        ),
+        (
+            Regex::new(r#"\b(token|signature|slack_signature|response_url|text|body)=("(?:[^"\\]|\\.)*"|[^&\s]*)"#)
+                .unwrap(),
+            "$1=[redacted]",
+        ),
+        // Slack request signatures and api tokens
+        (Regex::new(r"v0=[0-9a-fA-F]{64}").unwrap(), "v0=[redacted]"),
+        (Regex::new(r"xox[a-z]-[0-9A-Za-z-]+").unwrap(), REDACTED),
+        // Response urls, plain and url encoded
+        (
+            Regex::new(r"(?i)https?(://|%3A%2F%2F)hooks\.slack\.com[^\s&\x22]*").unwrap(),
+            REDACTED,
+        ),
+    ];
+}
+
+pub fn set_log_sensitive(enabled: bool) {
+    LOG_SENSITIVE.store(enabled, Ordering::Relaxed);
+}
+
+pub fn log_sensitive() -> bool {
+    LOG_SENSITIVE.load(Ordering::Relaxed)
+}
+
+pub fn is_sensitive_field(name: &str) -> bool {
+    SENSITIVE_FIELDS.contains(&name)
+}
+
+/// Scrub secrets and user content from a formatted log line
+pub fn redact(line: &str) -> Cow<'_, str> {
+    if log_sensitive() {
+        return Cow::Borrowed(line);
+    }
+
+    let mut result = Cow::Borrowed(line);
+    for (pattern, replacement) in SENSITIVE_PATTERNS.iter() {
+        if pattern.is_match(&result) {
+            result = Cow::Owned(pattern.replace_all(&result, *replacement).into_owned());
+        }
+    }
+
+    result
+}
+
+/// Wrapper for values which may only be logged with LOG_SENSITIVE enabled
+pub struct Redacted<T>(pub T);
+
+impl<T: Display> Display for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if log_sensitive() {
+            self.0.fmt(f)
+        } else {
+            f.write_str(REDACTED)
+        }
+    }
+}
+
+impl<T: Debug> Debug for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if log_sensitive() {
+            self.0.fmt(f)
+        } else {
+            f.write_str(REDACTED)
+        }
+    }
+}
+
+/// Wraps the log writer so every formatted event is scrubbed before it is written
+pub struct RedactingMakeWriter<M>(pub M);
+
+pub struct RedactingWriter<W>(W);
+
+impl<M: MakeWriter> MakeWriter for RedactingMakeWriter<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&self) -> Self::Writer {
+        RedactingWriter(self.0.make_writer())
+    }
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let line = String::from_utf8_lossy(buf);
+        self.0.write_all(redact(&line).as_bytes())?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::slack::verify_request::validate_slack_signature;
+    use crate::telemetry::redact::{redact, RedactingMakeWriter};
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+    use tracing::Subscriber;
+    use tracing_subscriber::fmt::MakeWriter;
+
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl MakeWriter for Captured {
+        type Writer = Captured;
+
+        fn make_writer(&self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    static BODY: &str = "token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&user_id=U2CERLKJA\
+        &command=%2Fsotw&text=song+https%3A%2F%2Fexample.org%2Fsecret-song\
+        &response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2FT1DC2JH3J%2F397700885554%2F96rGlfmibIGlgcZRskXaIFfN";
+    static SIGNATURE: &str = "v0=a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503";
+
+    fn assert_scrubbed(output: &str) {
+        for secret in &[
+            "xyzz0WbapA4vBCDEFasx0q6G",
+            "a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503",
+            "hooks.slack.com",
+            "96rGlfmibIGlgcZRskXaIFfN",
+            "secret-song",
+            "song x",
+        ] {
+            assert!(
+                !output.contains(secret),
+                "{} should not be in the log output: {}",
+                secret,
+                output
+            );
+        }
+    }
+
+    #[test]
+    fn test_failed_signature_is_redacted() {
+        for json in &[true, false] {
+            let captured = Captured::default();
+            let builder = tracing_subscriber::fmt()
+                .with_ansi(false)
+                .with_writer(RedactingMakeWriter(captured.clone()));
+            let subscriber: Box<dyn Subscriber + Send + Sync> = if *json {
+                Box::new(builder.json().finish())
+            } else {
+                Box::new(builder.finish())
+            };
+
+            tracing::subscriber::with_default(subscriber, || {
+                let result = validate_slack_signature(
+                    &["wrong secret".to_string()],
+                    SIGNATURE.to_string(),
+                    BODY.to_string(),
+                    1531420618,
+                );
+                assert!(result.is_err());
+
+                warn!(
+                    response_url = "https://hooks.slack.com/commands/1/2/3",
+                    text = "song x"
+                );
+                warn!("unstructured {}", BODY);
+            });
+
+            let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+            assert!(!output.is_empty(), "failures should still be logged");
+            assert_scrubbed(&output);
+        }
+    }
+
+    #[test]
+    fn test_redact_keeps_ids() {
+        let line = redact("team_id=T1DC2JH3J&user_id=U2CERLKJA&token=abc");
+
+        assert_eq!(line, "team_id=T1DC2JH3J&user_id=U2CERLKJA&token=[redacted]");
+    }
+}